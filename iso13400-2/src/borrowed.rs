@@ -0,0 +1,204 @@
+//! Borrowed views of the DoIP messages which are parsed in place.
+//!
+//! Table 16 — Generic DoIP header structure
+//!
+//! Table 21 — Payload type diagnostic message structure
+
+use crate::{
+    constants::*, error::Error, utils, Diagnostic, LogicAddress, Message, PayloadRegistry,
+    PayloadType, Version,
+};
+use bytes::{BufMut, BytesMut};
+
+/// A DoIP message borrowing the payload from the received data.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct MessageRef<'a> {
+    version: Version,
+    payload_type: PayloadType,
+    /// the complete message including the generic header
+    data: &'a [u8],
+}
+
+impl<'a> MessageRef<'a> {
+    #[inline]
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    #[inline]
+    pub fn payload_type(&self) -> PayloadType {
+        self.payload_type
+    }
+
+    /// The payload after the generic header.
+    #[inline]
+    pub fn payload(&self) -> &'a [u8] {
+        &self.data[SIZE_OF_HEADER..]
+    }
+
+    /// The complete message including the generic header.
+    #[inline]
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// The diagnostic message, `None` if the payload type is different.
+    pub fn diagnostic(&self) -> Option<Result<DiagnosticRef<'a>, Error>> {
+        match self.payload_type {
+            PayloadType::Diagnostic => Some(DiagnosticRef::try_from(self.payload())),
+            _ => None,
+        }
+    }
+
+    /// Parse the payload into the owned message, the manufacturer-specific payload types
+    /// are accepted if registered.
+    #[inline]
+    pub fn to_message(&self, registry: &PayloadRegistry) -> Result<Message, Error> {
+        Message::try_from((self.data, registry))
+    }
+
+    /// Append the message to `dst` unchanged.
+    #[inline]
+    pub fn encode(&self, dst: &mut BytesMut) {
+        dst.extend_from_slice(self.data);
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for MessageRef<'a> {
+    type Error = Error;
+    fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+        let data_len = data.len();
+        if data_len < SIZE_OF_HEADER {
+            return Err(Error::InvalidLength {
+                actual: data_len,
+                expected: SIZE_OF_HEADER,
+            });
+        }
+
+        let mut offset = 0;
+        let version = Version::try_from(data)?;
+        offset += SIZE_OF_VERSION;
+        let payload_type =
+            u16::from_be_bytes(data[offset..offset + SIZE_OF_DATA_TYPE].try_into().unwrap());
+        offset += SIZE_OF_DATA_TYPE;
+        let payload_len =
+            u32::from_be_bytes(data[offset..offset + SIZE_OF_LENGTH].try_into().unwrap());
+        offset += SIZE_OF_LENGTH;
+        let expected = data_len - offset;
+        if (payload_len as usize) != expected {
+            return Err(Error::InvalidPayloadLength {
+                actual: payload_len as usize,
+                expected,
+            });
+        }
+        // the registration of the manufacturer-specific payload types is left to the decoder
+        let payload_type = match PayloadType::try_from(payload_type) {
+            Ok(v) => v,
+            Err(_) if MANUFACTURER_PAYLOAD_TYPES.contains(&payload_type) => {
+                PayloadType::Custom(payload_type)
+            }
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self {
+            version,
+            payload_type,
+            data,
+        })
+    }
+}
+
+/// A diagnostic message borrowing the user data.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct DiagnosticRef<'a> {
+    src_addr: LogicAddress,
+    dst_addr: LogicAddress,
+    data: &'a [u8],
+}
+
+impl<'a> DiagnosticRef<'a> {
+    /// Create the diagnostic message, the addresses are in the order of the wire(SA, TA).
+    #[inline]
+    pub fn new(src_addr: LogicAddress, dst_addr: LogicAddress, data: &'a [u8]) -> Self {
+        Self {
+            src_addr,
+            dst_addr,
+            data,
+        }
+    }
+
+    #[inline]
+    pub fn src_addr(&self) -> LogicAddress {
+        self.src_addr
+    }
+
+    #[inline]
+    pub fn dst_addr(&self) -> LogicAddress {
+        self.dst_addr
+    }
+
+    /// The user data(e.g. UDS request or response).
+    #[inline]
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Append the message with the generic header to `dst`.
+    pub fn encode(&self, version: Version, dst: &mut BytesMut) {
+        let payload_len = SIZE_OF_ADDRESS + SIZE_OF_ADDRESS + self.data.len();
+        dst.reserve(SIZE_OF_HEADER + payload_len);
+        put_header(version, TCP_DIAGNOSTIC, payload_len as u32, dst);
+        dst.put_u16(self.src_addr.into());
+        dst.put_u16(self.dst_addr.into());
+        dst.put_slice(self.data);
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for DiagnosticRef<'a> {
+    type Error = Error;
+    fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+        let (_, mut offset) =
+            utils::data_len_check(data, SIZE_OF_ADDRESS + SIZE_OF_ADDRESS, false)?;
+        let src_addr =
+            u16::from_be_bytes(data[offset..offset + SIZE_OF_ADDRESS].try_into().unwrap());
+        offset += SIZE_OF_ADDRESS;
+        let dst_addr =
+            u16::from_be_bytes(data[offset..offset + SIZE_OF_ADDRESS].try_into().unwrap());
+        offset += SIZE_OF_ADDRESS;
+
+        Ok(Self::new(
+            LogicAddress::from(src_addr),
+            LogicAddress::from(dst_addr),
+            &data[offset..],
+        ))
+    }
+}
+
+impl<'a> From<&'a Diagnostic> for DiagnosticRef<'a> {
+    #[inline]
+    fn from(val: &'a Diagnostic) -> Self {
+        Self::new(val.src_addr, val.dst_addr, &val.data)
+    }
+}
+
+impl From<DiagnosticRef<'_>> for Diagnostic {
+    #[inline]
+    fn from(val: DiagnosticRef<'_>) -> Self {
+        Self::new(val.src_addr, val.dst_addr, val.data.to_vec())
+    }
+}
+
+/// Append the generic DoIP header to `dst`.
+#[inline]
+pub(crate) fn put_header(
+    version: Version,
+    payload_type: u16,
+    payload_len: u32,
+    dst: &mut BytesMut,
+) {
+    let version: u8 = version.into();
+    dst.put_u8(version);
+    dst.put_u8(!version);
+    dst.put_u16(payload_type);
+    dst.put_u32(payload_len);
+}
@@ -0,0 +1,40 @@
+use crate::{
+    codec::DEFAULT_MAX_PAYLOAD_SIZE, constants::PROTOCOL_VERSION, DoIpTiming, LogicAddress, Version,
+};
+
+/// waiting time(ms) of diagnostic response after the positive acknowledge
+const DEFAULT_DIAG_TIMEOUT: u64 = 5_000;
+/// waiting time(ms) before repeating a pending routing activation
+const DEFAULT_CONFIRM_INTERVAL: u64 = 500;
+
+/// The configuration of [`DoIpClient`](crate::client::DoIpClient).
+#[derive(Debug, Copy, Clone)]
+pub struct ClientConfig {
+    /// protocol version used by all messages sent by the client
+    pub version: Version,
+    /// logical address of the external test equipment
+    pub address: LogicAddress,
+    /// A_DoIP_Ctrl for the routing activation response and
+    /// A_DoIP_Diagnostic_Message for the diagnostic message acknowledge
+    pub timing: DoIpTiming,
+    /// timeout(ms) of the diagnostic response
+    pub diag_timeout: u64,
+    /// waiting time(ms) before repeating the routing activation request
+    /// which is pending for the authentication or confirmation
+    pub confirm_interval: u64,
+    /// max. payload size of the received messages
+    pub max_payload_size: u32,
+}
+
+impl ClientConfig {
+    pub fn new(address: LogicAddress) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            address,
+            timing: DoIpTiming::new(PROTOCOL_VERSION),
+            diag_timeout: DEFAULT_DIAG_TIMEOUT,
+            confirm_interval: DEFAULT_CONFIRM_INTERVAL,
+            max_payload_size: DEFAULT_MAX_PAYLOAD_SIZE,
+        }
+    }
+}
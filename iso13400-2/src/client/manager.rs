@@ -0,0 +1,306 @@
+use super::{ClientConfig, DoIpClient};
+use crate::{
+    discovery::{Discovery, DiscoveryRequest, VehicleAnnouncement},
+    error::Error,
+    Diagnostic, Eid, LogicAddress, RoutingActiveType,
+};
+use std::{
+    collections::HashMap,
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex as SyncMutex},
+};
+use tokio::sync::Mutex;
+
+/// The key of a DoIP entity in [`ConnectionManager`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum EntityKey {
+    Eid(Eid),
+    Ip(IpAddr),
+}
+
+/// A DoIP entity known by [`ConnectionManager`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EntityInfo {
+    pub key: EntityKey,
+    pub vin: String,
+    /// logical address of the DoIP entity
+    pub address: LogicAddress,
+    /// TCP_DATA address of the DoIP entity
+    pub addr: SocketAddr,
+}
+
+/// A DoIP entity and its TCP_DATA session, `None` until the first request.
+struct Entity {
+    info: EntityInfo,
+    session: Mutex<Option<Arc<DoIpClient>>>,
+}
+
+/// The DoIP clients of the entities of several vehicles.
+///
+/// One TCP_DATA session is kept per entity, connected and routing activated at the first
+/// request. When the session is lost(e.g. the entity is reset or closes the inactive
+/// socket), a new session is connected by the next request, the failed request is not
+/// repeated since it may not be idempotent.
+///
+/// The diagnostic messages are addressed by the VIN and the target address, which is resolved
+/// by the routes added by [`ConnectionManager::add_route`], the logical address of the
+/// entities, or the only entity of the vehicle.
+pub struct ConnectionManager {
+    config: ClientConfig,
+    active: RoutingActiveType,
+    user_def: Option<u32>,
+    entities: SyncMutex<HashMap<EntityKey, Arc<Entity>>>,
+    routes: SyncMutex<HashMap<(String, LogicAddress), EntityKey>>,
+}
+
+impl ConnectionManager {
+    pub fn new(config: ClientConfig) -> Self {
+        Self {
+            config,
+            active: RoutingActiveType::Default,
+            user_def: Default::default(),
+            entities: Default::default(),
+            routes: Default::default(),
+        }
+    }
+
+    #[inline]
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    /// The routing activation request of the new sessions,
+    /// [`RoutingActiveType::Default`] without OEM specific data by default.
+    #[inline]
+    pub fn set_activation(&mut self, active: RoutingActiveType, user_def: Option<u32>) {
+        self.active = active;
+        self.user_def = user_def;
+    }
+
+    /// Broadcast the identification request and register the responding entities.
+    pub async fn discover(
+        &self,
+        discovery: &Discovery,
+        request: DiscoveryRequest,
+    ) -> Result<Vec<VehicleAnnouncement>, Error> {
+        let announcements = discovery.broadcast(request).await?;
+        announcements.iter().for_each(|v| {
+            self.register(v);
+        });
+
+        Ok(announcements)
+    }
+
+    /// Register the entity of the announcement by its EID.
+    pub fn register(&self, announcement: &VehicleAnnouncement) -> EntityKey {
+        let vehicle = &announcement.vehicle;
+        let key = EntityKey::Eid(vehicle.eid());
+        self.insert(
+            key,
+            vehicle.vin().clone(),
+            vehicle.address(),
+            announcement.tcp_addr(),
+        );
+
+        key
+    }
+
+    /// Register the entity, the session is dropped if the entity has moved.
+    pub fn insert(&self, key: EntityKey, vin: String, address: LogicAddress, addr: SocketAddr) {
+        let info = EntityInfo {
+            key,
+            vin,
+            address,
+            addr,
+        };
+        let mut entities = self.entities.lock().unwrap();
+        if entities.get(&key).is_some_and(|v| v.info == info) {
+            return;
+        }
+
+        rsutil::debug!("ISO 13400-2 - register entity {} at {}", address, addr);
+        entities.insert(
+            key,
+            Arc::new(Entity {
+                info,
+                session: Default::default(),
+            }),
+        );
+    }
+
+    /// Remove the entity and its routes, the session is closed when the last request is done.
+    pub fn remove(&self, key: EntityKey) -> Option<EntityInfo> {
+        self.routes.lock().unwrap().retain(|_, v| *v != key);
+        self.entities
+            .lock()
+            .unwrap()
+            .remove(&key)
+            .map(|v| v.info.clone())
+    }
+
+    /// Route the diagnostic messages to `target` of the vehicle via the entity,
+    /// e.g. an ECU behind the gateway.
+    pub fn add_route(&self, vin: &str, target: LogicAddress, key: EntityKey) {
+        self.routes
+            .lock()
+            .unwrap()
+            .insert((vin.to_owned(), target), key);
+    }
+
+    pub fn entities(&self) -> Vec<EntityInfo> {
+        self.entities
+            .lock()
+            .unwrap()
+            .values()
+            .map(|v| v.info.clone())
+            .collect()
+    }
+
+    /// The VINs of the registered vehicles.
+    pub fn vehicles(&self) -> Vec<String> {
+        let mut result: Vec<_> = self
+            .entities
+            .lock()
+            .unwrap()
+            .values()
+            .map(|v| v.info.vin.clone())
+            .collect();
+        result.sort();
+        result.dedup();
+
+        result
+    }
+
+    /// The entity which the diagnostic messages to `target` of the vehicle are sent to.
+    pub fn resolve(&self, vin: &str, target: LogicAddress) -> Result<EntityKey, Error> {
+        if let Some(&key) = self.routes.lock().unwrap().get(&(vin.to_owned(), target)) {
+            return Ok(key);
+        }
+
+        let entities = self.entities.lock().unwrap();
+        let vehicle: Vec<_> = entities.values().filter(|v| v.info.vin == vin).collect();
+        if let Some(entity) = vehicle.iter().find(|v| v.info.address == target) {
+            return Ok(entity.info.key);
+        }
+        match vehicle.as_slice() {
+            [entity] => Ok(entity.info.key),
+            [] => Err(Error::InvalidParam(format!("unknown vehicle: {}", vin))),
+            _ => Err(Error::InvalidParam(format!(
+                "no route of {} in vehicle: {}",
+                target, vin
+            ))),
+        }
+    }
+
+    /// The session of the entity, connected and routing activated if none.
+    pub async fn session(&self, key: EntityKey) -> Result<Arc<DoIpClient>, Error> {
+        let entity = self.entity(key)?;
+        self.connect(&entity).await
+    }
+
+    /// Send the diagnostic message and wait for the acknowledge and the diagnostic response.
+    pub async fn diagnostic(
+        &self,
+        vin: &str,
+        target: LogicAddress,
+        data: Vec<u8>,
+    ) -> Result<Diagnostic, Error> {
+        let key = self.resolve(vin, target)?;
+        self.with_session(key, |client| async move {
+            client.diagnostic(target, data).await
+        })
+        .await
+    }
+
+    /// Send the diagnostic message and wait for the acknowledge only,
+    /// e.g. the positive response is suppressed.
+    pub async fn send_diagnostic(
+        &self,
+        vin: &str,
+        target: LogicAddress,
+        data: Vec<u8>,
+    ) -> Result<(), Error> {
+        let key = self.resolve(vin, target)?;
+        self.with_session(key, |client| async move {
+            client.send_diagnostic(target, data).await
+        })
+        .await
+    }
+
+    /// Drop the session of the entity, a new session is connected by the next request.
+    pub async fn disconnect(&self, key: EntityKey) -> Result<(), Error> {
+        let entity = self.entity(key)?;
+        entity.session.lock().await.take();
+
+        Ok(())
+    }
+
+    /// Drop the sessions of all entities.
+    pub async fn disconnect_all(&self) {
+        let entities: Vec<_> = self.entities.lock().unwrap().values().cloned().collect();
+        for entity in entities {
+            entity.session.lock().await.take();
+        }
+    }
+
+    fn entity(&self, key: EntityKey) -> Result<Arc<Entity>, Error> {
+        self.entities
+            .lock()
+            .unwrap()
+            .get(&key)
+            .cloned()
+            .ok_or_else(|| Error::InvalidParam(format!("unknown entity: {:?}", key)))
+    }
+
+    async fn connect(&self, entity: &Entity) -> Result<Arc<DoIpClient>, Error> {
+        let mut session = entity.session.lock().await;
+        match session.as_ref() {
+            Some(client) if !client.is_closed() => return Ok(client.clone()),
+            Some(_) => rsutil::info!(
+                "ISO 13400-2 - session of {} lost, reconnecting",
+                entity.info.addr
+            ),
+            None => {}
+        }
+
+        let client = DoIpClient::connect_addr(entity.info.addr, self.config).await?;
+        client
+            .routing_activation(self.active, self.user_def)
+            .await?;
+        let client = Arc::new(client);
+        session.replace(client.clone());
+
+        Ok(client)
+    }
+
+    /// The request is not repeated when the session is lost during it, the diagnostic message
+    /// may have been received by the entity(e.g. ECU reset). The lost session is dropped,
+    /// and the next request is sent on a new session.
+    async fn with_session<T, F, Fut>(&self, key: EntityKey, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(Arc<DoIpClient>) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let entity = self.entity(key)?;
+        let client = self.connect(&entity).await?;
+        match f(client.clone()).await {
+            Err(e) if is_disconnected(&e) => {
+                rsutil::info!("ISO 13400-2 - session of {} lost: {}", entity.info.addr, e);
+                // another request may have reconnected already
+                let mut session = entity.session.lock().await;
+                if session.as_ref().is_some_and(|v| Arc::ptr_eq(v, &client)) {
+                    session.take();
+                }
+
+                Err(e)
+            }
+            ret => ret,
+        }
+    }
+}
+
+#[inline]
+fn is_disconnected(e: &Error) -> bool {
+    matches!(e, Error::ConnectionClosed | Error::IoError(_))
+}
@@ -0,0 +1,474 @@
+//! Async DoIP client(external test equipment) over TCP.
+
+mod config;
+mod manager;
+#[cfg(feature = "tls")]
+mod tls;
+#[cfg(feature = "transport")]
+mod transport;
+
+pub use config::ClientConfig;
+pub use manager::{ConnectionManager, EntityInfo, EntityKey};
+#[cfg(feature = "tls")]
+pub use tls::TlsSettings;
+#[cfg(feature = "transport")]
+pub use transport::DoIpTransport;
+
+use crate::{
+    codec::MessageDecoder,
+    constants::*,
+    error::Error,
+    response,
+    state::{ClientConnection, ClientEvent, SocketState},
+    ActiveCode, Diagnostic, LogicAddress, Message, RoutingActiveType,
+};
+use std::{
+    collections::VecDeque,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex as SyncMutex},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+    sync::{mpsc, Mutex},
+    task::JoinHandle,
+    time::{timeout_at, Instant},
+};
+
+/// size of the TCP read buffer
+const SIZE_OF_BUFFER: usize = 4096;
+
+type Writer = Arc<Mutex<Box<dyn AsyncWrite + Send + Unpin>>>;
+
+/// The [`ClientConnection`] shared by the client and the read task.
+#[derive(Clone)]
+struct Machine {
+    connection: Arc<SyncMutex<ClientConnection>>,
+    /// the origin of the machine clock
+    origin: Instant,
+    events: mpsc::UnboundedSender<Result<ClientEvent, Error>>,
+}
+
+impl Machine {
+    /// Run `f` on the machine at the current time, forward the events to the inbox
+    /// and return the messages to send.
+    fn drive<T>(&self, f: impl FnOnce(&mut ClientConnection, u64) -> T) -> (T, Vec<Message>) {
+        let mut connection = self.connection.lock().unwrap();
+        let ret = f(&mut connection, self.origin.elapsed().as_millis() as u64);
+        while let Some(event) = connection.poll_event() {
+            let _ = self.events.send(Ok(event));
+        }
+        let transmits = std::iter::from_fn(|| connection.poll_transmit()).collect();
+
+        (ret, transmits)
+    }
+
+    #[inline]
+    fn check(&self, f: impl FnOnce(&ClientConnection) -> bool) -> bool {
+        f(&self.connection.lock().unwrap())
+    }
+
+    /// The deadline of the next timer.
+    #[inline]
+    fn deadline(&self) -> Option<Instant> {
+        self.connection
+            .lock()
+            .unwrap()
+            .poll_timeout()
+            .map(|v| self.origin + Duration::from_millis(v))
+    }
+}
+
+/// Events of the machine which are not consumed yet.
+struct Inbox {
+    receiver: mpsc::UnboundedReceiver<Result<ClientEvent, Error>>,
+    diagnostics: VecDeque<Diagnostic>,
+}
+
+/// A DoIP client connected to one DoIP entity.
+///
+/// The socket is driven by the [`ClientConnection`] state machine,
+/// the alive check request from the entity is answered in the background.
+pub struct DoIpClient {
+    config: ClientConfig,
+    peer: SocketAddr,
+    writer: Writer,
+    machine: Machine,
+    inbox: Mutex<Inbox>,
+    reader: JoinHandle<()>,
+}
+
+impl DoIpClient {
+    /// Connect to the DoIP entity at `TCP_SERVER_PORT`.
+    pub async fn connect(ip: IpAddr, config: ClientConfig) -> Result<Self, Error> {
+        Self::connect_addr(SocketAddr::new(ip, TCP_SERVER_PORT), config).await
+    }
+
+    /// Connect to the DoIP entity at the given socket address.
+    pub async fn connect_addr(addr: SocketAddr, config: ClientConfig) -> Result<Self, Error> {
+        let stream = TcpStream::connect(addr).await?;
+        stream.set_nodelay(true)?;
+        rsutil::debug!("ISO 13400-2 - connected to {}", addr);
+
+        Ok(Self::from_stream(stream, addr, config))
+    }
+
+    /// Create the client from an established stream.
+    pub fn from_stream<S>(stream: S, peer: SocketAddr, config: ClientConfig) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (reader, writer) = tokio::io::split(stream);
+        let writer: Writer = Arc::new(Mutex::new(Box::new(writer)));
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let machine = Machine {
+            connection: Arc::new(SyncMutex::new(ClientConnection::new(
+                config.address,
+                config.version,
+                config.timing,
+            ))),
+            origin: Instant::now(),
+            events: sender,
+        };
+        let reader = tokio::spawn(read_loop(reader, writer.clone(), config, machine.clone()));
+
+        Self {
+            config,
+            peer,
+            writer,
+            machine,
+            inbox: Mutex::new(Inbox {
+                receiver,
+                diagnostics: Default::default(),
+            }),
+            reader,
+        }
+    }
+
+    #[inline]
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    #[inline]
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer
+    }
+
+    /// Whether the connection is closed by the entity or a read error, no more messages are received.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.reader.is_finished()
+    }
+
+    /// The logical address of the DoIP entity, `None` before routing is activated.
+    #[inline]
+    pub fn entity_address(&self) -> Option<LogicAddress> {
+        self.machine.connection.lock().unwrap().entity_address()
+    }
+
+    /// Send the routing activation request and wait for the response.
+    ///
+    /// Return an error if the response code is not [`ActiveCode::Success`].
+    pub async fn routing_activation(
+        &self,
+        active: RoutingActiveType,
+        user_def: Option<u32>,
+    ) -> Result<response::RoutingActive, Error> {
+        let resp = self.request_routing(active, user_def).await?;
+        match resp.active_code {
+            ActiveCode::Success => Ok(resp),
+            code => Err(Error::RoutingActivation(code)),
+        }
+    }
+
+    /// Activate routing with the authentication or confirmation of the entity.
+    ///
+    /// When the response is [`ActiveCode::NeedConfirm`], [`ActiveCode::WithoutAuth`] or
+    /// [`ActiveCode::VMSpecific`], `escalate` decides the activation type and OEM specific data
+    /// of the next request from the response, which is sent after
+    /// [`ClientConfig::confirm_interval`]. Return an error if `escalate` gives up by `None`
+    /// or the entity refuses.
+    pub async fn routing_activation_with<F>(
+        &self,
+        mut active: RoutingActiveType,
+        mut user_def: Option<u32>,
+        mut escalate: F,
+    ) -> Result<response::RoutingActive, Error>
+    where
+        F: FnMut(&response::RoutingActive) -> Option<(RoutingActiveType, Option<u32>)>,
+    {
+        loop {
+            let resp = self.request_routing(active, user_def).await?;
+            let code = resp.active_code;
+            match code {
+                ActiveCode::Success => return Ok(resp),
+                ActiveCode::NeedConfirm | ActiveCode::WithoutAuth | ActiveCode::VMSpecific(_) => {
+                    match escalate(&resp) {
+                        Some(next) => (active, user_def) = next,
+                        None => return Err(Error::RoutingActivation(code)),
+                    }
+                    tokio::time::sleep(Duration::from_millis(self.config.confirm_interval)).await;
+                }
+                _ => return Err(Error::RoutingActivation(code)),
+            }
+        }
+    }
+
+    /// Send the routing activation request and wait for the response of any code.
+    async fn request_routing(
+        &self,
+        active: RoutingActiveType,
+        user_def: Option<u32>,
+    ) -> Result<response::RoutingActive, Error> {
+        let mut inbox = self.inbox.lock().await;
+        self.settle(&mut inbox, ClientConnection::is_routing_outstanding)
+            .await?;
+        let (ret, transmits) = self
+            .machine
+            .drive(|c, now| c.activate(now, active, user_def));
+        ret?;
+        write_messages(&self.writer, transmits).await?;
+
+        loop {
+            match self.next_event(&mut inbox).await? {
+                ClientEvent::RoutingActivated(resp)
+                | ClientEvent::RoutingPending(resp)
+                | ClientEvent::RoutingRefused(resp) => return Ok(resp),
+                ClientEvent::RoutingTimeout => {
+                    return Err(Error::Timeout {
+                        value: self.config.timing.ctrl,
+                        unit: "ms",
+                    })
+                }
+                event => unexpected(&mut inbox, event)?,
+            }
+        }
+    }
+
+    /// Send the diagnostic message and wait for the acknowledge.
+    pub async fn send_diagnostic(&self, target: LogicAddress, data: Vec<u8>) -> Result<(), Error> {
+        let mut inbox = self.inbox.lock().await;
+        self.settle(&mut inbox, ClientConnection::is_ack_outstanding)
+            .await?;
+        let (ret, transmits) = self
+            .machine
+            .drive(|c, now| c.send_diagnostic(now, target, data));
+        ret?;
+        write_messages(&self.writer, transmits).await?;
+
+        loop {
+            match self.next_event(&mut inbox).await? {
+                ClientEvent::DiagnosticAck(_) => return Ok(()),
+                ClientEvent::DiagnosticNack(resp) => {
+                    return Err(Error::DiagnosticNegative(resp.code));
+                }
+                ClientEvent::DiagnosticTimeout => {
+                    return Err(Error::Timeout {
+                        value: self.config.timing.diagnostic_message,
+                        unit: "ms",
+                    })
+                }
+                event => unexpected(&mut inbox, event)?,
+            }
+        }
+    }
+
+    /// Wait for a diagnostic message from the DoIP entity.
+    pub async fn receive_diagnostic(&self, timeout: u64) -> Result<Diagnostic, Error> {
+        let mut inbox = self.inbox.lock().await;
+        if let Some(diag) = inbox.diagnostics.pop_front() {
+            return Ok(diag);
+        }
+
+        let deadline = Instant::now() + Duration::from_millis(timeout);
+        loop {
+            match timeout_at(deadline, self.next_event(&mut inbox)).await {
+                Ok(event) => match event? {
+                    ClientEvent::Diagnostic(diag) => return Ok(diag),
+                    event => unexpected(&mut inbox, event)?,
+                },
+                Err(_) => {
+                    return Err(Error::Timeout {
+                        value: timeout,
+                        unit: "ms",
+                    })
+                }
+            }
+        }
+    }
+
+    /// Send the diagnostic message and wait for the acknowledge and the diagnostic response,
+    /// the diagnostic messages from the other sources are ignored.
+    pub async fn diagnostic(
+        &self,
+        target: LogicAddress,
+        data: Vec<u8>,
+    ) -> Result<Diagnostic, Error> {
+        self.send_diagnostic(target, data).await?;
+
+        let timeout = self.config.diag_timeout;
+        let deadline = Instant::now() + Duration::from_millis(timeout);
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let diag = match self.receive_diagnostic(remaining.as_millis() as u64).await {
+                Err(Error::Timeout { .. }) => {
+                    return Err(Error::Timeout {
+                        value: timeout,
+                        unit: "ms",
+                    })
+                }
+                ret => ret?,
+            };
+            if diag.src_addr == target {
+                return Ok(diag);
+            }
+            rsutil::warn!(
+                "ISO 13400-2 - ignore the diagnostic message from {} instead of {}",
+                diag.src_addr,
+                target
+            );
+        }
+    }
+
+    /// Shutdown the connection.
+    pub async fn close(self) -> Result<(), Error> {
+        self.reader.abort();
+        self.writer.lock().await.shutdown().await?;
+
+        Ok(())
+    }
+
+    /// Wait until the request of a cancelled call is answered or timed out.
+    async fn settle(
+        &self,
+        inbox: &mut Inbox,
+        outstanding: fn(&ClientConnection) -> bool,
+    ) -> Result<(), Error> {
+        while self.machine.check(outstanding) {
+            let event = self.next_event(inbox).await?;
+            unexpected(inbox, event)?;
+        }
+
+        Ok(())
+    }
+
+    /// Wait for the next event of the machine, the expired timers are handled meanwhile.
+    async fn next_event(&self, inbox: &mut Inbox) -> Result<ClientEvent, Error> {
+        loop {
+            let event = match self.machine.deadline() {
+                Some(deadline) => match timeout_at(deadline, inbox.receiver.recv()).await {
+                    Ok(v) => v,
+                    Err(_) => {
+                        self.machine.drive(|c, now| c.handle_timeout(now));
+                        continue;
+                    }
+                },
+                None => inbox.receiver.recv().await,
+            };
+
+            return event.unwrap_or(Err(Error::ConnectionClosed));
+        }
+    }
+}
+
+impl Drop for DoIpClient {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+async fn read_loop<R>(mut reader: R, writer: Writer, config: ClientConfig, machine: Machine)
+where
+    R: AsyncRead + Unpin,
+{
+    let mut decoder = MessageDecoder::new(config.max_payload_size);
+    let mut buffer = vec![0; SIZE_OF_BUFFER];
+    let error = loop {
+        let size = match reader.read(&mut buffer).await {
+            Ok(0) => break Error::ConnectionClosed,
+            Ok(v) => v,
+            Err(e) => {
+                rsutil::warn!("ISO 13400-2 - stop reading: {}", e);
+                break closed(e);
+            }
+        };
+        decoder.extend(&buffer[..size]);
+
+        if let Err(e) = process(&mut decoder, &writer, &machine).await {
+            break e;
+        }
+    };
+
+    machine.drive(|c, _| c.close());
+    let _ = machine.events.send(Err(error));
+}
+
+/// Handle all complete messages in the decoder.
+async fn process(
+    decoder: &mut MessageDecoder,
+    writer: &Writer,
+    machine: &Machine,
+) -> Result<(), Error> {
+    loop {
+        let message = match decoder.decode() {
+            Ok(Some(v)) => v,
+            Ok(None) => return Ok(()),
+            Err(e) => {
+                let (state, transmits) = machine.drive(|c, _| {
+                    c.handle_error(&e);
+                    c.state()
+                });
+                write_messages(writer, transmits).await?;
+                if state == SocketState::Finalized {
+                    return Err(e);
+                }
+                continue;
+            }
+        };
+
+        let (_, transmits) = machine.drive(|c, now| c.handle_message(now, message));
+        write_messages(writer, transmits).await?;
+    }
+}
+
+async fn write_messages(writer: &Writer, messages: Vec<Message>) -> Result<(), Error> {
+    if messages.is_empty() {
+        return Ok(());
+    }
+
+    let mut writer = writer.lock().await;
+    for message in messages {
+        let data: Vec<_> = message.into();
+        rsutil::trace!("ISO 13400-2 - sending: {}", hex::encode(&data));
+        writer.write_all(&data).await.map_err(closed)?;
+    }
+    writer.flush().await.map_err(closed)?;
+
+    Ok(())
+}
+
+/// Keep the diagnostic messages for [`DoIpClient::receive_diagnostic`]
+/// and turn the header negative acknowledge and the closing into an error.
+fn unexpected(inbox: &mut Inbox, event: ClientEvent) -> Result<(), Error> {
+    match event {
+        ClientEvent::Diagnostic(diag) => inbox.diagnostics.push_back(diag),
+        ClientEvent::HeaderNegative(code) => return Err(Error::HeaderNegative(code)),
+        ClientEvent::Closed => return Err(Error::ConnectionClosed),
+        event => rsutil::warn!("ISO 13400-2 - ignore unexpected event: {:?}", event),
+    }
+
+    Ok(())
+}
+
+#[inline]
+fn closed(e: std::io::Error) -> Error {
+    match e.kind() {
+        std::io::ErrorKind::UnexpectedEof
+        | std::io::ErrorKind::ConnectionReset
+        | std::io::ErrorKind::ConnectionAborted
+        | std::io::ErrorKind::BrokenPipe => Error::ConnectionClosed,
+        _ => Error::IoError(e),
+    }
+}
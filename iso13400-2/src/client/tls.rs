@@ -0,0 +1,84 @@
+use super::{ClientConfig, DoIpClient};
+use crate::{constants::*, error::Error, response, tls::tls_error, ActiveCode, RoutingActiveType};
+use rustls::pki_types::ServerName;
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+
+/// The settings of the secured connection.
+#[derive(Debug, Clone)]
+pub struct TlsSettings {
+    pub config: Arc<rustls::ClientConfig>,
+    /// the name to verify the certificate of the entity
+    pub server_name: ServerName<'static>,
+    /// `TLS_TCP_SERVER_PORT` by default
+    pub port: u16,
+}
+
+impl TlsSettings {
+    pub fn new(config: Arc<rustls::ClientConfig>, server_name: ServerName<'static>) -> Self {
+        Self {
+            config,
+            server_name,
+            port: TLS_TCP_SERVER_PORT,
+        }
+    }
+}
+
+impl DoIpClient {
+    /// Connect to the DoIP entity over TLS.
+    pub async fn connect_tls(
+        ip: IpAddr,
+        tls: &TlsSettings,
+        config: ClientConfig,
+    ) -> Result<Self, Error> {
+        Self::connect_tls_addr(SocketAddr::new(ip, tls.port), tls, config).await
+    }
+
+    /// Connect to the DoIP entity at `addr` over TLS, the port of `tls` is ignored.
+    pub async fn connect_tls_addr(
+        addr: SocketAddr,
+        tls: &TlsSettings,
+        config: ClientConfig,
+    ) -> Result<Self, Error> {
+        let stream = TcpStream::connect(addr).await?;
+        stream.set_nodelay(true)?;
+        let stream = TlsConnector::from(tls.config.clone())
+            .connect(tls.server_name.clone(), stream)
+            .await
+            .map_err(tls_error)?;
+        rsutil::debug!("ISO 13400-2 - connected to {} over TLS", addr);
+
+        Ok(Self::from_stream(stream, addr, config))
+    }
+
+    /// Connect to `addr` and activate routing,
+    /// reconnect over TLS if the entity responds [`ActiveCode::TLSRequired`].
+    pub async fn connect_and_activate(
+        addr: SocketAddr,
+        tls: &TlsSettings,
+        config: ClientConfig,
+        active: RoutingActiveType,
+        user_def: Option<u32>,
+    ) -> Result<(Self, response::RoutingActive), Error> {
+        let client = Self::connect_addr(addr, config).await?;
+        match client.routing_activation(active, user_def).await {
+            Ok(resp) => Ok((client, resp)),
+            Err(Error::RoutingActivation(ActiveCode::TLSRequired)) => {
+                rsutil::info!("ISO 13400-2 - {} requires TLS, reconnecting", addr);
+                drop(client);
+                // keep the scope of an IPv6 link-local address
+                let mut addr = addr;
+                addr.set_port(tls.port);
+                let client = Self::connect_tls_addr(addr, tls, config).await?;
+                let resp = client.routing_activation(active, user_def).await?;
+
+                Ok((client, resp))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use super::DoIpClient;
+use crate::{constants::SIZE_OF_ADDRESS, error::Error, LogicAddress};
+use iso14229_1::{
+    transport::{Addressing, DiagTransport, Pdu},
+    Iso14229Error,
+};
+use std::sync::Arc;
+
+/// The DoIP client session as the transport of the UDS PDUs(ISO 14229-5).
+///
+/// The PDUs are sent from the logical address of the test equipment to `target`,
+/// or to the functional address by the functional addressing.
+///
+/// The diagnostic messages received from any source are reported as physical,
+/// the target address of them isn't compared with the functional address.
+/// So the transport only suits the client side, a server on it would handle
+/// the functional requests as physical ones, e.g. without suppressing the NRCs.
+pub struct DoIpTransport {
+    client: Arc<DoIpClient>,
+    target: LogicAddress,
+    functional: Option<LogicAddress>,
+}
+
+impl DoIpTransport {
+    /// The routing shall be activated on the session.
+    pub fn new(client: Arc<DoIpClient>, target: LogicAddress) -> Self {
+        Self {
+            client,
+            target,
+            functional: Default::default(),
+        }
+    }
+
+    #[inline]
+    pub fn with_functional(mut self, address: LogicAddress) -> Self {
+        self.functional = Some(address);
+        self
+    }
+
+    #[inline]
+    pub fn client(&self) -> &Arc<DoIpClient> {
+        &self.client
+    }
+
+    #[inline]
+    pub fn target(&self) -> LogicAddress {
+        self.target
+    }
+}
+
+#[async_trait::async_trait]
+impl DiagTransport for DoIpTransport {
+    #[inline]
+    fn max_pdu_size(&self) -> usize {
+        (self.client.config().max_payload_size as usize).saturating_sub(2 * SIZE_OF_ADDRESS)
+    }
+
+    async fn send(&self, addressing: Addressing, data: &[u8]) -> Result<(), Iso14229Error> {
+        let target = match addressing {
+            Addressing::Physical => self.target,
+            Addressing::Functional => self.functional.ok_or_else(|| {
+                Iso14229Error::InvalidParam("functional address is not set".into())
+            })?,
+        };
+        self.client
+            .send_diagnostic(target, data.to_vec())
+            .await
+            .map_err(uds_error)
+    }
+
+    async fn receive(&self, timeout: u64) -> Result<Pdu, Iso14229Error> {
+        let diag = self
+            .client
+            .receive_diagnostic(timeout)
+            .await
+            .map_err(uds_error)?;
+
+        Ok(Pdu {
+            addressing: Addressing::Physical,
+            data: diag.data,
+        })
+    }
+}
+
+#[inline]
+fn uds_error(e: Error) -> Iso14229Error {
+    match e {
+        Error::Timeout { value, unit } => Iso14229Error::Timeout { value, unit },
+        _ => Iso14229Error::TransportError(e.to_string()),
+    }
+}
@@ -0,0 +1,402 @@
+//! Streaming codec of DoIP messages for TCP byte streams.
+//!
+//! Table 19 — Generic DoIP header NACK codes
+
+use crate::{
+    borrowed::put_header, constants::*, error::Error, DiagnosticRef, HeaderNegativeCode, Message,
+    Payload, PayloadRegistry, PayloadType, Version,
+};
+use bytes::{Buf, Bytes, BytesMut};
+
+/// The default max. payload size of the decoder and encoder.
+pub const DEFAULT_MAX_PAYLOAD_SIZE: u32 = 0x0001_0000;
+/// The protocol versions accepted by the decoder and encoder by default.
+pub const DEFAULT_VERSIONS: [Version; 3] = [
+    Version::ISO13400_2_2010,
+    Version::ISO13400_2_2012,
+    Version::ISO13400_2_2019,
+];
+
+/// The generic DoIP header which passed [`MessageDecoder::validate_header`].
+///
+/// Table 16 — Generic DoIP header structure
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Header {
+    pub version: Version,
+    pub payload_type: PayloadType,
+    pub payload_len: u32,
+}
+
+/// Incremental decoder which accepts arbitrary chunks of a TCP stream.
+///
+/// A failure is reported as [`Error::InvalidHeader`] with the code of the
+/// generic DoIP header negative acknowledge to send and whether the socket must be closed.
+/// The decoder can be used further after a failure which doesn't require closing.
+///
+/// The messages with a protocol version out of the accepted versions are rejected,
+/// except the vehicle identification requests with the default version(0xFF).
+/// The payload types out of the standard are accepted only if they are registered.
+#[derive(Debug, Clone)]
+pub struct MessageDecoder {
+    buffer: BytesMut,
+    max_payload_size: u32,
+    /// bytes which can be allocated for a message currently
+    available_memory: usize,
+    versions: Vec<Version>,
+    registry: PayloadRegistry,
+    /// remaining bytes of a discarded message
+    discard: usize,
+}
+
+impl Default for MessageDecoder {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_PAYLOAD_SIZE)
+    }
+}
+
+impl MessageDecoder {
+    pub fn new(max_payload_size: u32) -> Self {
+        Self {
+            buffer: Default::default(),
+            max_payload_size,
+            available_memory: usize::MAX,
+            versions: DEFAULT_VERSIONS.to_vec(),
+            registry: Default::default(),
+            discard: Default::default(),
+        }
+    }
+
+    #[inline]
+    pub fn max_payload_size(&self) -> u32 {
+        self.max_payload_size
+    }
+
+    /// The bytes which can be allocated for a message currently,
+    /// the larger messages are rejected by [`HeaderNegativeCode::OutOfMemory`].
+    #[inline]
+    pub fn available_memory(&self) -> usize {
+        self.available_memory
+    }
+
+    #[inline]
+    pub fn set_available_memory(&mut self, available_memory: usize) {
+        self.available_memory = available_memory;
+    }
+
+    /// The accepted protocol versions.
+    #[inline]
+    pub fn versions(&self) -> &[Version] {
+        &self.versions
+    }
+
+    #[inline]
+    pub fn set_versions(&mut self, versions: &[Version]) {
+        self.versions = versions.to_vec();
+    }
+
+    /// The registered manufacturer-specific payload types.
+    #[inline]
+    pub fn registry(&self) -> &PayloadRegistry {
+        &self.registry
+    }
+
+    #[inline]
+    pub fn set_registry(&mut self, registry: PayloadRegistry) {
+        self.registry = registry;
+    }
+
+    /// The count of bytes which are received but not decoded yet.
+    #[inline]
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Append the received data.
+    #[inline]
+    pub fn extend(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Drop all buffered data.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.discard = Default::default();
+    }
+
+    /// Validate the generic DoIP header at the beginning of `data`.
+    ///
+    /// The checks follow Figure 6 — Generic DoIP header handling:
+    /// the pattern(protocol version and inverse), the payload type,
+    /// the max. payload size, the available memory and the payload length of the type.
+    pub fn validate_header(&self, data: &[u8]) -> Result<Header, Error> {
+        if data.len() < SIZE_OF_HEADER {
+            return Err(Error::InvalidLength {
+                actual: data.len(),
+                expected: SIZE_OF_HEADER,
+            });
+        }
+
+        let mut offset = SIZE_OF_VERSION;
+        let payload_type =
+            u16::from_be_bytes(data[offset..offset + SIZE_OF_DATA_TYPE].try_into().unwrap());
+        offset += SIZE_OF_DATA_TYPE;
+        let payload_len = u32::from_be_bytes(data[offset..SIZE_OF_HEADER].try_into().unwrap());
+
+        let version = match Version::try_from(&data[..SIZE_OF_VERSION]) {
+            Ok(Version::Default) if is_identification(payload_type) => Version::Default,
+            Ok(version) if version != Version::Default && self.versions.contains(&version) => {
+                version
+            }
+            _ => return Err(header_error(HeaderNegativeCode::IncorrectPatternFormat)),
+        };
+        let payload_type = match PayloadType::try_from(payload_type) {
+            Ok(v) => v,
+            Err(_) if self.registry.contains(payload_type) => PayloadType::Custom(payload_type),
+            Err(_) => return Err(header_error(HeaderNegativeCode::UnknownPayloadTYpe)),
+        };
+        if payload_len > self.max_payload_size {
+            return Err(header_error(HeaderNegativeCode::MessageTooLarge));
+        }
+        if payload_len as usize > self.available_memory {
+            return Err(header_error(HeaderNegativeCode::OutOfMemory));
+        }
+        if !payload_type.is_valid_length(payload_len as usize) {
+            return Err(header_error(HeaderNegativeCode::InvalidPayloadLength));
+        }
+
+        Ok(Header {
+            version,
+            payload_type,
+            payload_len,
+        })
+    }
+
+    /// Decode the next complete message, `None` if more data is needed.
+    pub fn decode(&mut self) -> Result<Option<Message>, Error> {
+        let frame = match self.decode_frame()? {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+
+        match Message::try_from((frame.as_ref(), &self.registry)).and_then(|message| {
+            validate(&self.registry, &message)?;
+            Ok(message)
+        }) {
+            Ok(message) => Ok(Some(message)),
+            Err(e) => {
+                rsutil::warn!("ISO 13400-2 - invalid payload: {}", e);
+                self.clear();
+                Err(header_error(HeaderNegativeCode::InvalidPayloadLength))
+            }
+        }
+    }
+
+    /// Split the next complete message without parsing the payload,
+    /// `None` if more data is needed.
+    ///
+    /// Only the generic header is validated, the frame is viewed by [`MessageRef`](crate::MessageRef)
+    /// without copying the payload.
+    pub fn decode_frame(&mut self) -> Result<Option<Bytes>, Error> {
+        if self.discard > 0 {
+            let size = self.discard.min(self.buffer.len());
+            self.buffer.advance(size);
+            self.discard -= size;
+            if self.discard > 0 {
+                return Ok(None);
+            }
+        }
+
+        if self.buffer.len() < SIZE_OF_HEADER {
+            return Ok(None);
+        }
+
+        let header = match self.validate_header(&self.buffer[..SIZE_OF_HEADER]) {
+            Ok(v) => v,
+            Err(Error::InvalidHeader { code, close }) => {
+                rsutil::warn!(
+                    "ISO 13400-2 - invalid header {}: {:?}",
+                    hex::encode(&self.buffer[..SIZE_OF_HEADER]),
+                    code
+                );
+                match close {
+                    true => self.clear(),
+                    false => {
+                        let offset = SIZE_OF_VERSION + SIZE_OF_DATA_TYPE;
+                        let payload_len = u32::from_be_bytes(
+                            self.buffer[offset..SIZE_OF_HEADER].try_into().unwrap(),
+                        );
+                        self.buffer.advance(SIZE_OF_HEADER);
+                        self.discard = payload_len as usize;
+                    }
+                }
+                return Err(Error::InvalidHeader { code, close });
+            }
+            Err(e) => return Err(e),
+        };
+
+        let size = SIZE_OF_HEADER + header.payload_len as usize;
+        if self.buffer.len() < size {
+            return Ok(None);
+        }
+
+        Ok(Some(self.buffer.split_to(size).freeze()))
+    }
+}
+
+/// Encoder which rejects messages the peer can't receive.
+#[derive(Debug, Clone)]
+pub struct MessageEncoder {
+    max_payload_size: u32,
+    versions: Vec<Version>,
+    registry: PayloadRegistry,
+}
+
+impl Default for MessageEncoder {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_PAYLOAD_SIZE)
+    }
+}
+
+impl MessageEncoder {
+    pub fn new(max_payload_size: u32) -> Self {
+        Self {
+            max_payload_size,
+            versions: DEFAULT_VERSIONS.to_vec(),
+            registry: Default::default(),
+        }
+    }
+
+    #[inline]
+    pub fn max_payload_size(&self) -> u32 {
+        self.max_payload_size
+    }
+
+    /// The accepted protocol versions.
+    #[inline]
+    pub fn versions(&self) -> &[Version] {
+        &self.versions
+    }
+
+    #[inline]
+    pub fn set_versions(&mut self, versions: &[Version]) {
+        self.versions = versions.to_vec();
+    }
+
+    /// The registered manufacturer-specific payload types.
+    #[inline]
+    pub fn registry(&self) -> &PayloadRegistry {
+        &self.registry
+    }
+
+    #[inline]
+    pub fn set_registry(&mut self, registry: PayloadRegistry) {
+        self.registry = registry;
+    }
+
+    /// Append the encoded message to `dst`.
+    ///
+    /// The diagnostic messages and the manufacturer-specific payloads are written
+    /// into `dst` directly.
+    pub fn encode(&self, message: Message, dst: &mut BytesMut) -> Result<(), Error> {
+        self.check_version(message.version)?;
+        validate(&self.registry, &message)?;
+
+        match message.payload {
+            Payload::Diagnostic(v) => self.encode_diagnostic(message.version, (&v).into(), dst),
+            Payload::Custom { payload_type, data } => {
+                self.check_size(data.len())?;
+                dst.reserve(SIZE_OF_HEADER + data.len());
+                put_header(message.version, payload_type, data.len() as u32, dst);
+                dst.extend_from_slice(&data);
+
+                Ok(())
+            }
+            payload => {
+                // the length of the other payloads is patched after encoding
+                let start = dst.len();
+                put_header(message.version, payload.payload_type().into(), 0, dst);
+                payload.put(dst);
+                let payload_len = dst.len() - start - SIZE_OF_HEADER;
+                if let Err(e) = self.check_size(payload_len) {
+                    dst.truncate(start);
+                    return Err(e);
+                }
+                let offset = start + SIZE_OF_VERSION + SIZE_OF_DATA_TYPE;
+                dst[offset..start + SIZE_OF_HEADER]
+                    .copy_from_slice(&(payload_len as u32).to_be_bytes());
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Append the borrowed diagnostic message to `dst` without copying the user data elsewhere.
+    pub fn encode_diagnostic(
+        &self,
+        version: Version,
+        diag: DiagnosticRef<'_>,
+        dst: &mut BytesMut,
+    ) -> Result<(), Error> {
+        self.check_version(version)?;
+        if version == Version::Default {
+            return Err(Error::UnsupportedPayload {
+                version,
+                payload_type: PayloadType::Diagnostic,
+            });
+        }
+        self.check_size(SIZE_OF_ADDRESS + SIZE_OF_ADDRESS + diag.data().len())?;
+        diag.encode(version, dst);
+
+        Ok(())
+    }
+
+    fn check_version(&self, version: Version) -> Result<(), Error> {
+        if version != Version::Default && !self.versions.contains(&version) {
+            return Err(Error::InvalidParam(format!(
+                "version {:?} is not accepted",
+                version
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn check_size(&self, payload_len: usize) -> Result<(), Error> {
+        if payload_len > self.max_payload_size as usize {
+            return Err(Error::InvalidParam(format!(
+                "payload length {} exceeds the max. payload size {}",
+                payload_len, self.max_payload_size
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// The error of [`HeaderNegativeCode`], Table 19 defines whether the socket must be closed.
+#[inline]
+fn header_error(code: HeaderNegativeCode) -> Error {
+    let close = matches!(
+        code,
+        HeaderNegativeCode::IncorrectPatternFormat | HeaderNegativeCode::InvalidPayloadLength
+    );
+    Error::InvalidHeader { code, close }
+}
+
+/// Check the version rules and the data of the manufacturer-specific payload.
+fn validate(registry: &PayloadRegistry, message: &Message) -> Result<(), Error> {
+    message.validate()?;
+    match &message.payload {
+        Payload::Custom { payload_type, data } => registry.validate(*payload_type, data),
+        _ => Ok(()),
+    }
+}
+
+/// The vehicle identification requests which are allowed with the default version.
+#[inline]
+fn is_identification(payload_type: u16) -> bool {
+    matches!(
+        payload_type,
+        UDP_REQ_VEHICLE_IDENTIFIER | UDP_REQ_VEHICLE_ID_WITH_EID | UDP_REQ_VEHICLE_ID_WITH_VIN
+    )
+}
@@ -1,10 +1,15 @@
-use crate::{constants::*, error::Error, request, response, utils, PayloadType};
+use crate::{
+    constants::*, error::Error, request, response, utils, CustomPayload, PayloadRegistry,
+    PayloadType,
+};
+use bytes::{BufMut, BytesMut};
 use getset::{CopyGetters, Getters};
 use std::fmt::{Display, Formatter};
 
 /// Table 16 — Generic DoIP header structure at line #48(ISO 13400-2-2019)
 #[repr(u8)]
 #[derive(Debug, Copy, Clone, Eq, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Version {
     ISO13400_2_2010 = 0x01,
     ISO13400_2_2012 = 0x02,
@@ -72,6 +77,7 @@ impl TryFrom<&[u8]> for Version {
 /// Table 19 — Generic DoIP header NACK codes at line #52(ISO 13400-2-2019)
 #[repr(u8)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum HeaderNegativeCode {
     IncorrectPatternFormat = 0x00, // close socket
     UnknownPayloadTYpe = 0x01,
@@ -141,7 +147,7 @@ impl From<u8> for HeaderNegativeCode {
 ///
 /// F000 to FFFF ISO/SAE reserved
 #[repr(u8)]
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum LogicAddress {
     VMSpecific(u16),           // 0x0001 ~ 0x0DFF | 0x1000 ~ 0x7FFF
     Client(u16),               // 0x0E00 ~ 0x0FFF
@@ -181,9 +187,32 @@ impl Display for LogicAddress {
     }
 }
 
+/// The logical address is serialized as a hex string, e.g. `"0x0E80"`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for LogicAddress {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:#06X}", u16::from(*self)))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for LogicAddress {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        let digits = value
+            .strip_prefix("0x")
+            .or_else(|| value.strip_prefix("0X"))
+            .unwrap_or(&value);
+        u16::from_str_radix(digits, 16)
+            .map(Self::from)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
 /// Table 11 — DoIP entity status response
 #[repr(u8)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NodeType {
     Gateway = 0x00,
     Node = 0x01,
@@ -226,6 +255,7 @@ impl Display for NodeType {
 /// Table 6 — Definition of further action code values
 #[repr(u8)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FurtherAction {
     NoAction = 0x00,
     Reserved(u8), // 0x01 ~ 0x0f
@@ -261,6 +291,7 @@ impl From<u8> for FurtherAction {
 /// Table 7 — Definition of VIN/GID synchronization status code values
 #[repr(u8)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SyncStatus {
     VINorGIDSync = 0x00,
     VINorGIDNotSync = 0x10,
@@ -293,6 +324,7 @@ impl From<u8> for SyncStatus {
 /// Table 49 — Routing activation response code values
 #[repr(u8)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ActiveCode {
     SourceAddressUnknown = 0x00, // close TCP
     Activated = 0x01,            // close TCP
@@ -350,9 +382,21 @@ impl From<u8> for ActiveCode {
     }
 }
 
+impl ActiveCode {
+    /// Whether the socket is closed after the routing activation response.
+    #[inline]
+    pub(crate) fn closes_socket(&self) -> bool {
+        !matches!(
+            self,
+            Self::Success | Self::NeedConfirm | Self::WithoutAuth | Self::VMSpecific(_)
+        )
+    }
+}
+
 /// Table 9 — Diagnostic power mode information response
 #[repr(u8)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PowerMode {
     NotReady = 0x00,
     Ready = 0x01,
@@ -388,6 +432,7 @@ impl From<u8> for PowerMode {
 /// Table 47 — Routing activation request activation types
 #[repr(u8)]
 #[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RoutingActiveType {
     #[default]
     Default = 0x00,
@@ -427,6 +472,7 @@ impl From<u8> for RoutingActiveType {
 /// Table 24 — Diagnostic message positive acknowledge codes
 #[repr(u8)]
 #[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DiagnosticPositiveCode {
     #[default]
     Confirm = 0x00,
@@ -469,6 +515,7 @@ impl Display for DiagnosticPositiveCode {
 /// Table 26 — Diagnostic message negative acknowledge codes
 #[repr(u8)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DiagnosticNegativeCode {
     InvalidSourceAddress = 0x02,
     UnknownTargetAddress = 0x03,
@@ -540,21 +587,24 @@ impl Display for DiagnosticNegativeCode {
 /// then send 0x8001 response with UDS data.
 /// Otherwise, send 0x8003 response with UDS NRC data.
 #[derive(Debug, Clone, Eq, PartialEq, Getters, CopyGetters)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Diagnostic {
     // 0x8001
     #[getset(get_copy = "pub")]
-    pub(crate) dst_addr: LogicAddress,
-    #[getset(get_copy = "pub")]
     pub(crate) src_addr: LogicAddress,
+    #[getset(get_copy = "pub")]
+    pub(crate) dst_addr: LogicAddress,
     #[getset(get = "pub")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::hex_bytes"))]
     pub data: Vec<u8>,
 }
 
 impl Diagnostic {
-    pub fn new(dst_addr: LogicAddress, src_addr: LogicAddress, data: Vec<u8>) -> Self {
+    /// Create the diagnostic message, the addresses are in the order of the wire(SA, TA).
+    pub fn new(src_addr: LogicAddress, dst_addr: LogicAddress, data: Vec<u8>) -> Self {
         Self {
-            dst_addr,
             src_addr,
+            dst_addr,
             data,
         }
     }
@@ -570,17 +620,17 @@ impl TryFrom<&[u8]> for Diagnostic {
     type Error = Error;
     fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
         let (_, mut offset) = utils::data_len_check(data, Self::length(), false)?;
-        let dst_addr =
-            u16::from_be_bytes(data[offset..offset + SIZE_OF_ADDRESS].try_into().unwrap());
-        offset += SIZE_OF_ADDRESS;
-        let dst_addr = LogicAddress::from(dst_addr);
         let src_addr =
             u16::from_be_bytes(data[offset..offset + SIZE_OF_ADDRESS].try_into().unwrap());
         offset += SIZE_OF_ADDRESS;
         let src_addr = LogicAddress::from(src_addr);
+        let dst_addr =
+            u16::from_be_bytes(data[offset..offset + SIZE_OF_ADDRESS].try_into().unwrap());
+        offset += SIZE_OF_ADDRESS;
+        let dst_addr = LogicAddress::from(dst_addr);
         let data = data[offset..].to_vec();
 
-        Ok(Self::new(dst_addr, src_addr, data))
+        Ok(Self::new(src_addr, dst_addr, data))
     }
 }
 
@@ -589,10 +639,10 @@ impl From<Diagnostic> for Vec<u8> {
         let mut result = TCP_DIAGNOSTIC.to_be_bytes().to_vec();
         let length = (Diagnostic::length() + val.data.len()) as u32;
         result.extend(length.to_be_bytes());
-        let dst_addr: u16 = val.dst_addr.into();
-        result.extend(dst_addr.to_be_bytes());
         let src_addr: u16 = val.src_addr.into();
         result.extend(src_addr.to_be_bytes());
+        let dst_addr: u16 = val.dst_addr.into();
+        result.extend(dst_addr.to_be_bytes());
         result.append(&mut val.data);
 
         result
@@ -600,7 +650,9 @@ impl From<Diagnostic> for Vec<u8> {
 }
 
 /// Table 17 — Overview of DoIP payload types at line #49(ISO 13400-2-2019)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
 pub enum Payload {
     RespHeaderNegative(response::HeaderNegative), // UDP/TCP 0x0000
     ReqVehicleId(request::VehicleID),             // UDP 0x0001
@@ -618,6 +670,12 @@ pub enum Payload {
     Diagnostic(Diagnostic),                       // TCP 0x8001
     RespDiagPositive(response::DiagnosticPositive), // TCP 0x8002
     RespDiagNegative(response::DiagnosticNegative), // TCP 0x8003
+    /// manufacturer-specific payload
+    Custom {
+        payload_type: u16,
+        #[cfg_attr(feature = "serde", serde(with = "crate::utils::hex_bytes"))]
+        data: Vec<u8>,
+    },
 }
 
 impl Payload {
@@ -639,16 +697,137 @@ impl Payload {
             Payload::Diagnostic(_) => PayloadType::Diagnostic,
             Payload::RespDiagPositive(_) => PayloadType::RespDiagPositive,
             Payload::RespDiagNegative(_) => PayloadType::RespDiagNegative,
+            Payload::Custom { payload_type, .. } => PayloadType::Custom(*payload_type),
+        }
+    }
+
+    /// Append the payload without the generic header to `dst`.
+    pub(crate) fn put(&self, dst: &mut BytesMut) {
+        match self {
+            Payload::RespHeaderNegative(v) => dst.put_u8(v.code.into()),
+            Payload::ReqVehicleId(_)
+            | Payload::ReqAliveCheck(_)
+            | Payload::ReqEntityStatus(_)
+            | Payload::ReqDiagPowerMode(_) => {}
+            Payload::ReqVehicleWithEid(v) => dst.put_uint(v.eid.0, SIZE_OF_ID),
+            Payload::ReqVehicleWithVIN(v) => dst.put_slice(v.vin.as_bytes()),
+            Payload::RespVehicleId(v) => {
+                dst.put_slice(v.vin.as_bytes());
+                dst.put_u16(v.address.into());
+                dst.put_uint(v.eid.0, SIZE_OF_ID);
+                dst.put_uint(v.gid.0, SIZE_OF_ID);
+                dst.put_u8(v.further_act.into());
+                if let Some(status) = v.sync_status {
+                    dst.put_u8(status.into());
+                }
+            }
+            Payload::ReqRoutingActive(v) => {
+                dst.put_u16(v.src_addr.into());
+                dst.put_u8(v.active.into());
+                dst.put_u32(v.reserved);
+                if let Some(user_def) = v.user_def {
+                    dst.put_u32(user_def);
+                }
+            }
+            Payload::RespRoutingActive(v) => {
+                dst.put_u16(v.dst_addr.into());
+                dst.put_u16(v.src_addr.into());
+                dst.put_u8(v.active_code.into());
+                dst.put_u32(v.reserved);
+                if let Some(user_def) = v.user_def {
+                    dst.put_u32(user_def);
+                }
+            }
+            Payload::RespAliveCheck(v) => dst.put_u16(v.src_addr.into()),
+            Payload::RespEntityStatus(v) => {
+                dst.put_u8(v.node_type.into());
+                dst.put_u8(v.mcts);
+                dst.put_u8(v.ncts);
+                if let Some(size) = v.max_data_size {
+                    dst.put_u32(size);
+                }
+            }
+            Payload::RespDiagPowerMode(v) => dst.put_u8(v.mode.into()),
+            Payload::Diagnostic(v) => {
+                dst.put_u16(v.src_addr.into());
+                dst.put_u16(v.dst_addr.into());
+                dst.put_slice(&v.data);
+            }
+            Payload::RespDiagPositive(v) => {
+                dst.put_u16(v.src_addr.into());
+                dst.put_u16(v.dst_addr.into());
+                dst.put_u8(v.code.into());
+                dst.put_slice(&v.pre_diag_data);
+            }
+            Payload::RespDiagNegative(v) => {
+                dst.put_u16(v.src_addr.into());
+                dst.put_u16(v.dst_addr.into());
+                dst.put_u8(v.code.into());
+                dst.put_slice(&v.pre_diag_data);
+            }
+            Payload::Custom { data, .. } => dst.put_slice(data),
+        }
+    }
+
+    /// Encode the typed manufacturer-specific payload.
+    pub fn from_custom<T: CustomPayload>(payload: &T) -> Self {
+        Payload::Custom {
+            payload_type: T::PAYLOAD_TYPE,
+            data: payload.encode(),
+        }
+    }
+
+    /// Decode the typed manufacturer-specific payload, `None` if the payload type is different.
+    pub fn to_custom<T: CustomPayload>(&self) -> Option<Result<T, Error>> {
+        match self {
+            Payload::Custom { payload_type, data } if *payload_type == T::PAYLOAD_TYPE => {
+                Some(T::decode(data))
+            }
+            _ => None,
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Message {
     pub version: Version,
     pub payload: Payload,
 }
 
+impl Message {
+    /// Check the payload against the rules of the protocol version.
+    ///
+    /// The default version(0xFF) is only allowed for the vehicle identification requests.
+    pub fn validate(&self) -> Result<(), Error> {
+        let version = self.version;
+        let supported = match &self.payload {
+            Payload::ReqVehicleId(_)
+            | Payload::ReqVehicleWithEid(_)
+            | Payload::ReqVehicleWithVIN(_) => true,
+            _ if version == Version::Default => false,
+            Payload::RespRoutingActive(v) => {
+                v.active_code != ActiveCode::TLSRequired || version == Version::ISO13400_2_2019
+            }
+            Payload::RespVehicleId(v) => {
+                v.sync_status.is_none() || version != Version::ISO13400_2_2010
+            }
+            Payload::RespEntityStatus(v) => {
+                v.max_data_size.is_none() || version != Version::ISO13400_2_2010
+            }
+            _ => true,
+        };
+
+        match supported {
+            true => Ok(()),
+            false => Err(Error::UnsupportedPayload {
+                version,
+                payload_type: self.payload.payload_type(),
+            }),
+        }
+    }
+}
+
 impl From<Message> for Vec<u8> {
     fn from(val: Message) -> Self {
         let mut result: Vec<_> = val.version.into();
@@ -669,15 +848,34 @@ impl From<Message> for Vec<u8> {
             Payload::Diagnostic(v) => result.append(&mut v.into()),
             Payload::RespDiagPositive(v) => result.append(&mut v.into()),
             Payload::RespDiagNegative(v) => result.append(&mut v.into()),
+            Payload::Custom {
+                payload_type,
+                mut data,
+            } => {
+                result.extend(payload_type.to_be_bytes());
+                result.extend((data.len() as u32).to_be_bytes());
+                result.append(&mut data);
+            }
         }
 
         result
     }
 }
 
+/// Parse the message of the standard payload types, see [`PayloadRegistry`] for the others.
 impl TryFrom<&[u8]> for Message {
     type Error = Error;
+    #[inline]
     fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        Self::try_from((data, &PayloadRegistry::default()))
+    }
+}
+
+/// Parse the message with the manufacturer-specific payload types of the registry accepted,
+/// the data of them is checked by [`PayloadRegistry::validate`].
+impl TryFrom<(&[u8], &PayloadRegistry)> for Message {
+    type Error = Error;
+    fn try_from((data, registry): (&[u8], &PayloadRegistry)) -> Result<Self, Self::Error> {
         rsutil::debug!("ISO 13400-2 - parsing data: {}", hex::encode(data));
         let data_len = data.len();
         let expected = SIZE_OF_VERSION + SIZE_OF_DATA_TYPE + SIZE_OF_LENGTH;
@@ -704,7 +902,12 @@ impl TryFrom<&[u8]> for Message {
                 expected,
             });
         }
-        let payload = match PayloadType::try_from(payload_type)? {
+        let payload_type = match PayloadType::try_from(payload_type) {
+            Ok(v) => v,
+            Err(_) if registry.contains(payload_type) => PayloadType::Custom(payload_type),
+            Err(e) => return Err(e),
+        };
+        let payload = match payload_type {
             PayloadType::RespHeaderNegative => {
                 Payload::RespHeaderNegative(response::HeaderNegative::try_from(&data[offset..])?)
             }
@@ -751,6 +954,10 @@ impl TryFrom<&[u8]> for Message {
             PayloadType::RespDiagNegative => {
                 Payload::RespDiagNegative(response::DiagnosticNegative::try_from(&data[offset..])?)
             }
+            PayloadType::Custom(payload_type) => Payload::Custom {
+                payload_type,
+                data: data[offset..].to_vec(),
+            },
         };
 
         Ok(Self { version, payload })
@@ -2,6 +2,8 @@
 pub const TCP_SERVER_PORT: u16 = 13400;
 pub const TLS_TCP_SERVER_PORT: u16 = 3496;
 pub const UDP_SERVER_PORT: u16 = 13400;
+/// the link-local all-nodes multicast address for the IPv6 vehicle discovery
+pub const IPV6_ALL_NODES: std::net::Ipv6Addr = std::net::Ipv6Addr::new(0xFF02, 0, 0, 0, 0, 0, 0, 1);
 
 pub(crate) const HEADER_NEGATIVE: u16 = 0x0000;
 pub(crate) const UDP_REQ_VEHICLE_IDENTIFIER: u16 = 0x0001;
@@ -25,6 +27,9 @@ pub(crate) const TCP_DIAGNOSTIC: u16 = 0x8001;
 pub(crate) const TCP_RESP_DIAGNOSTIC_POSITIVE: u16 = 0x8002;
 pub(crate) const TCP_RESP_DIAGNOSTIC_NEGATIVE: u16 = 0x8003;
 
+/// the payload types reserved for the manufacturer by Table 17
+pub(crate) const MANUFACTURER_PAYLOAD_TYPES: std::ops::RangeInclusive<u16> = 0xF000..=0xFFFF;
+
 /// length of EID and GID
 pub(crate) const SIZE_OF_ID: usize = 6;
 pub const LENGTH_OF_VIN: usize = 17;
@@ -32,3 +37,13 @@ pub(crate) const SIZE_OF_ADDRESS: usize = 2;
 pub(crate) const SIZE_OF_VERSION: usize = 2;
 pub(crate) const SIZE_OF_DATA_TYPE: usize = 2;
 pub(crate) const SIZE_OF_LENGTH: usize = 4;
+pub(crate) const SIZE_OF_HEADER: usize = SIZE_OF_VERSION + SIZE_OF_DATA_TYPE + SIZE_OF_LENGTH;
+
+/// the latest version enabled by the features, ISO 13400-2:2012 if none
+pub(crate) const PROTOCOL_VERSION: crate::Version = if cfg!(feature = "std2019") {
+    crate::Version::ISO13400_2_2019
+} else if cfg!(feature = "std2012") || !cfg!(feature = "std2010") {
+    crate::Version::ISO13400_2_2012
+} else {
+    crate::Version::ISO13400_2_2010
+};
@@ -0,0 +1,244 @@
+//! DoIP vehicle discovery over UDP.
+//!
+//! Table 2 — Payload type vehicle identification request message — No message parameters
+//!
+//! Table 3 — Payload type vehicle identification request message with EID
+//!
+//! Table 4 — Payload type vehicle identification request message with VIN
+//!
+//! Table 5 — Payload type vehicle announcement/identification response message
+//!
+//! The IPv4 vehicles are discovered by broadcast, and the IPv6 vehicles by the link-local
+//! multicast to [`IPV6_ALL_NODES`] of an interface. A socket bound to `[::]` is dual-stack.
+
+use crate::{
+    constants::*, error::Error, request, response, socket, DoIpTiming, Eid, Message, Payload,
+    Version,
+};
+use std::{
+    io::ErrorKind,
+    net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV6},
+    time::Duration,
+};
+use tokio::{
+    net::UdpSocket,
+    time::{timeout_at, Instant},
+};
+
+/// max. size of a UDP datagram
+const SIZE_OF_DATAGRAM: usize = 0xFFFF;
+
+/// The vehicle identification request to send.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum DiscoveryRequest {
+    /// identify all vehicles
+    All,
+    /// identify the vehicle with the EID
+    Eid(Eid),
+    /// identify the vehicle with the VIN
+    Vin(String),
+}
+
+impl DiscoveryRequest {
+    fn payload(&self) -> Result<Payload, Error> {
+        match self {
+            Self::All => Ok(Payload::ReqVehicleId(request::VehicleID)),
+            Self::Eid(eid) => Ok(Payload::ReqVehicleWithEid(request::VehicleIDWithEID::new(
+                *eid,
+            ))),
+            Self::Vin(vin) => Ok(Payload::ReqVehicleWithVIN(request::VehicleIDWithVIN::new(
+                vin,
+            )?)),
+        }
+    }
+}
+
+/// The vehicle announcement/identification response and its sender.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct VehicleAnnouncement {
+    pub addr: SocketAddr,
+    pub vehicle: response::VehicleID,
+}
+
+impl VehicleAnnouncement {
+    #[inline]
+    pub fn ip(&self) -> IpAddr {
+        self.addr.ip()
+    }
+
+    /// The TCP_DATA address of the vehicle, the scope of an IPv6 link-local address is kept.
+    #[inline]
+    pub fn tcp_addr(&self) -> SocketAddr {
+        let mut addr = self.addr;
+        addr.set_port(TCP_SERVER_PORT);
+        addr
+    }
+}
+
+/// UDP socket for the vehicle identification requests and announcements.
+#[derive(Debug)]
+pub struct Discovery {
+    socket: UdpSocket,
+    version: Version,
+    timing: DoIpTiming,
+}
+
+impl Discovery {
+    /// Bind the socket to an ephemeral port for sending identification requests.
+    pub async fn new() -> Result<Self, Error> {
+        Self::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0)).await
+    }
+
+    /// Bind the socket to `UDP_SERVER_PORT` to receive vehicle announcements.
+    pub async fn listener() -> Result<Self, Error> {
+        Self::bind(SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            UDP_SERVER_PORT,
+        ))
+        .await
+    }
+
+    /// Bind the socket to `addr`, the socket bound to `[::]` receives IPv4 and IPv6.
+    pub async fn bind(addr: SocketAddr) -> Result<Self, Error> {
+        let socket = socket::bind_udp(addr)?;
+
+        Ok(Self {
+            socket,
+            version: Default::default(),
+            timing: Default::default(),
+        })
+    }
+
+    #[inline]
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// The protocol version of the requests, [`Version::Default`] by default.
+    #[inline]
+    pub fn set_version(&mut self, version: Version) {
+        self.version = version;
+    }
+
+    #[inline]
+    pub fn timing(&self) -> &DoIpTiming {
+        &self.timing
+    }
+
+    /// The responses are collected within A_DoIP_Ctrl,
+    /// and the announcements within A_Vehicle_Discovery_Timer.
+    #[inline]
+    pub fn set_timing(&mut self, timing: DoIpTiming) {
+        self.timing = timing;
+    }
+
+    /// Broadcast the identification request and collect the responses.
+    pub async fn broadcast(
+        &self,
+        request: DiscoveryRequest,
+    ) -> Result<Vec<VehicleAnnouncement>, Error> {
+        let target = SocketAddr::new(IpAddr::V4(Ipv4Addr::BROADCAST), UDP_SERVER_PORT);
+        self.identify(target, request).await
+    }
+
+    /// Multicast the identification request to all IPv6 nodes on the link
+    /// of the interface `scope_id` and collect the responses.
+    pub async fn multicast(
+        &self,
+        request: DiscoveryRequest,
+        scope_id: u32,
+    ) -> Result<Vec<VehicleAnnouncement>, Error> {
+        if self.local_addr()?.is_ipv4() {
+            return Err(Error::InvalidParam(
+                "IPv6 multicast needs an IPv6 socket".into(),
+            ));
+        }
+
+        let target = SocketAddrV6::new(IPV6_ALL_NODES, UDP_SERVER_PORT, 0, scope_id);
+        self.identify(target.into(), request).await
+    }
+
+    /// Send the identification request to `target` and collect the responses.
+    pub async fn identify(
+        &self,
+        target: SocketAddr,
+        request: DiscoveryRequest,
+    ) -> Result<Vec<VehicleAnnouncement>, Error> {
+        let data: Vec<_> = Message {
+            version: self.version,
+            payload: request.payload()?,
+        }
+        .into();
+        rsutil::trace!("ISO 13400-2 - sending {} to {}", hex::encode(&data), target);
+        let target = socket::target(self.local_addr()?, target);
+        self.socket.send_to(&data, target).await?;
+
+        self.collect(self.timing.ctrl).await
+    }
+
+    /// Listen for the vehicle announcements until timeout.
+    pub async fn listen(&self) -> Result<Vec<VehicleAnnouncement>, Error> {
+        self.collect(self.timing.vehicle_discovery).await
+    }
+
+    async fn collect(&self, timeout: u64) -> Result<Vec<VehicleAnnouncement>, Error> {
+        let deadline = Instant::now() + Duration::from_millis(timeout);
+        let mut results: Vec<VehicleAnnouncement> = Vec::new();
+        let mut buffer = vec![0; SIZE_OF_DATAGRAM];
+        while let Ok(ret) = timeout_at(deadline, self.socket.recv_from(&mut buffer)).await {
+            let (size, addr) = match ret {
+                Ok(v) => v,
+                // ICMP port unreachable of a previous datagram, keep receiving
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset
+                    ) =>
+                {
+                    rsutil::warn!("ISO 13400-2 - receiving vehicle announcement error: {}", e);
+                    continue;
+                }
+                // the socket is broken, keep the collected responses
+                Err(e) => {
+                    rsutil::warn!("ISO 13400-2 - receiving vehicle announcement error: {}", e);
+                    break;
+                }
+            };
+            let addr = socket::canonical(addr);
+            let vehicle = match Message::try_from(&buffer[..size]) {
+                Ok(Message {
+                    payload: Payload::RespVehicleId(v),
+                    ..
+                }) => v,
+                Ok(message) => {
+                    rsutil::debug!(
+                        "ISO 13400-2 - ignore {:?} from {}",
+                        message.payload.payload_type(),
+                        addr
+                    );
+                    continue;
+                }
+                Err(e) => {
+                    rsutil::warn!("ISO 13400-2 - invalid datagram from {}: {}", addr, e);
+                    continue;
+                }
+            };
+
+            if results
+                .iter()
+                .any(|v| v.vehicle.eid == vehicle.eid && v.vehicle.vin == vehicle.vin)
+            {
+                continue;
+            }
+            rsutil::debug!(
+                "ISO 13400-2 - vehicle {} of {} found at {}",
+                vehicle.vin,
+                vehicle.address,
+                addr
+            );
+            results.push(VehicleAnnouncement { addr, vehicle });
+        }
+
+        Ok(results)
+    }
+}
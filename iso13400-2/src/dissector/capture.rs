@@ -0,0 +1,243 @@
+//! Readers of the pcap and pcapng capture files.
+
+use crate::error::Error;
+use std::time::Duration;
+
+/// pcap magic number with microsecond timestamps
+const PCAP_MICROS: u32 = 0xA1B2_C3D4;
+/// pcap magic number with nanosecond timestamps
+const PCAP_NANOS: u32 = 0xA1B2_3C4D;
+const SIZE_OF_PCAP_HEADER: usize = 24;
+const SIZE_OF_PCAP_RECORD: usize = 16;
+
+const PCAPNG_SECTION_HEADER: u32 = 0x0A0D_0D0A;
+const PCAPNG_BYTE_ORDER: u32 = 0x1A2B_3C4D;
+const PCAPNG_INTERFACE: u32 = 0x0000_0001;
+const PCAPNG_PACKET: u32 = 0x0000_0002;
+const PCAPNG_SIMPLE_PACKET: u32 = 0x0000_0003;
+const PCAPNG_ENHANCED_PACKET: u32 = 0x0000_0006;
+/// option code of the interface timestamp resolution
+const PCAPNG_IF_TSRESOL: u16 = 9;
+
+/// A captured link layer frame.
+#[derive(Debug, Clone)]
+pub(crate) struct Frame<'a> {
+    /// capture time since UNIX epoch
+    pub(crate) timestamp: Duration,
+    pub(crate) link_type: u32,
+    pub(crate) data: &'a [u8],
+}
+
+/// Read all frames of a pcap or pcapng capture.
+pub(crate) fn frames(data: &[u8]) -> Result<Vec<Frame<'_>>, Error> {
+    let magic = Reader::new(data, false).u32(0)?;
+    match magic {
+        PCAPNG_SECTION_HEADER => pcapng(data),
+        _ => pcap(data),
+    }
+}
+
+fn pcap(data: &[u8]) -> Result<Vec<Frame<'_>>, Error> {
+    let (reader, nanos) = match Reader::new(data, false).u32(0)? {
+        PCAP_MICROS => (Reader::new(data, false), false),
+        PCAP_NANOS => (Reader::new(data, false), true),
+        _ => match Reader::new(data, true).u32(0)? {
+            PCAP_MICROS => (Reader::new(data, true), false),
+            PCAP_NANOS => (Reader::new(data, true), true),
+            magic => {
+                return Err(capture_error(format!(
+                    "unknown magic number {:#010X}",
+                    magic
+                )))
+            }
+        },
+    };
+    let link_type = reader.u32(20)?;
+
+    let mut frames = Vec::new();
+    let mut offset = SIZE_OF_PCAP_HEADER;
+    while offset < data.len() {
+        let seconds = reader.u32(offset)? as u64;
+        let fraction = reader.u32(offset + 4)?;
+        let length = reader.u32(offset + 8)? as usize;
+        offset += SIZE_OF_PCAP_RECORD;
+
+        let nanos = match nanos {
+            true => fraction,
+            false => fraction.saturating_mul(1_000),
+        };
+        frames.push(Frame {
+            timestamp: Duration::new(seconds, 0) + Duration::from_nanos(nanos as u64),
+            link_type,
+            data: reader.slice(offset, length)?,
+        });
+        offset += length;
+    }
+
+    Ok(frames)
+}
+
+/// The interface description of a pcapng section.
+#[derive(Debug, Copy, Clone)]
+struct Interface {
+    link_type: u32,
+    snap_len: u32,
+    /// `if_tsresol`, 10^-n seconds or 2^-n seconds if the MSB is set
+    resolution: u8,
+}
+
+fn pcapng(data: &[u8]) -> Result<Vec<Frame<'_>>, Error> {
+    let mut frames = Vec::new();
+    let mut interfaces = Vec::new();
+    let mut reader = Reader::new(data, false);
+    let mut offset = 0;
+    while offset < data.len() {
+        let block_type = reader.u32(offset)?;
+        if block_type == PCAPNG_SECTION_HEADER {
+            // the byte order of a section is defined by its header
+            reader = match Reader::new(data, false).u32(offset + 8)? {
+                PCAPNG_BYTE_ORDER => Reader::new(data, false),
+                _ => Reader::new(data, true),
+            };
+            if reader.u32(offset + 8)? != PCAPNG_BYTE_ORDER {
+                return Err(capture_error("invalid byte-order magic"));
+            }
+            interfaces.clear();
+        }
+
+        let length = reader.u32(offset + 4)? as usize;
+        if length < 12 || !length.is_multiple_of(4) {
+            return Err(capture_error(format!("invalid block length {}", length)));
+        }
+        let body = reader.slice(offset + 8, length - 12)?;
+        let body_reader = Reader::new(body, reader.big_endian);
+
+        match block_type {
+            PCAPNG_INTERFACE => interfaces.push(Interface {
+                link_type: body_reader.u16(0)? as u32,
+                snap_len: body_reader.u32(4)?,
+                resolution: interface_resolution(&body_reader, 8)?,
+            }),
+            PCAPNG_ENHANCED_PACKET | PCAPNG_PACKET => {
+                // the obsolete packet block has a 2 bytes interface ID and drops count
+                let id = match block_type {
+                    PCAPNG_PACKET => body_reader.u16(0)? as usize,
+                    _ => body_reader.u32(0)? as usize,
+                };
+                let interface = interfaces
+                    .get(id)
+                    .ok_or_else(|| capture_error(format!("unknown interface {}", id)))?;
+                let timestamp = ((body_reader.u32(4)? as u64) << 32) | body_reader.u32(8)? as u64;
+                let captured = body_reader.u32(12)? as usize;
+                frames.push(Frame {
+                    timestamp: timestamp_of(timestamp, interface.resolution),
+                    link_type: interface.link_type,
+                    data: body_reader.slice(20, captured)?,
+                });
+            }
+            PCAPNG_SIMPLE_PACKET => {
+                let interface = interfaces
+                    .first()
+                    .ok_or_else(|| capture_error("unknown interface 0"))?;
+                let mut captured = body_reader.u32(0)? as usize;
+                if interface.snap_len > 0 {
+                    captured = captured.min(interface.snap_len as usize);
+                }
+                frames.push(Frame {
+                    timestamp: Duration::ZERO,
+                    link_type: interface.link_type,
+                    data: body_reader.slice(4, captured)?,
+                });
+            }
+            _ => {}
+        }
+
+        offset += length;
+    }
+
+    Ok(frames)
+}
+
+/// Find `if_tsresol` in the options, microseconds by default.
+fn interface_resolution(reader: &Reader, mut offset: usize) -> Result<u8, Error> {
+    while offset + 4 <= reader.data.len() {
+        let code = reader.u16(offset)?;
+        let length = reader.u16(offset + 2)? as usize;
+        if code == 0 {
+            break;
+        }
+        if code == PCAPNG_IF_TSRESOL && length == 1 {
+            return Ok(reader.slice(offset + 4, 1)?[0]);
+        }
+        offset += 4 + length.div_ceil(4) * 4;
+    }
+
+    Ok(6)
+}
+
+fn timestamp_of(value: u64, resolution: u8) -> Duration {
+    let exponent = (resolution & 0x7F) as u32;
+    if resolution & 0x80 != 0 {
+        let seconds = value.checked_shr(exponent).unwrap_or_default();
+        let fraction = value & 1u64.checked_shl(exponent).map_or(u64::MAX, |v| v - 1);
+        let nanos = ((fraction as u128 * 1_000_000_000) >> exponent) as u64;
+        return Duration::from_secs(seconds) + Duration::from_nanos(nanos);
+    }
+
+    match 10u64.checked_pow(exponent) {
+        Some(unit) => {
+            let fraction = value % unit;
+            let nanos = match exponent {
+                0..=9 => fraction * 10u64.pow(9 - exponent),
+                _ => fraction / 10u64.pow(exponent - 9),
+            };
+            Duration::from_secs(value / unit) + Duration::from_nanos(nanos)
+        }
+        None => Duration::ZERO,
+    }
+}
+
+/// Bounds checked reader of the capture data.
+struct Reader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], big_endian: bool) -> Self {
+        Self { data, big_endian }
+    }
+
+    fn slice(&self, offset: usize, length: usize) -> Result<&'a [u8], Error> {
+        offset
+            .checked_add(length)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or_else(|| {
+                capture_error(format!(
+                    "truncated at offset {} with length {}",
+                    offset, length
+                ))
+            })
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, Error> {
+        let data = self.slice(offset, 2)?.try_into().unwrap();
+        Ok(match self.big_endian {
+            true => u16::from_be_bytes(data),
+            false => u16::from_le_bytes(data),
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, Error> {
+        let data = self.slice(offset, 4)?.try_into().unwrap();
+        Ok(match self.big_endian {
+            true => u32::from_be_bytes(data),
+            false => u32::from_le_bytes(data),
+        })
+    }
+}
+
+#[inline]
+fn capture_error<T: Into<String>>(message: T) -> Error {
+    Error::InvalidCapture(message.into())
+}
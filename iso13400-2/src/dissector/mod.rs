@@ -0,0 +1,343 @@
+//! Offline dissector of DoIP messages in pcap and pcapng captures.
+//!
+//! The UDP datagrams and TCP streams from or to the DoIP port are decoded into
+//! timestamped [`Event`]s. The TCP segments are reassembled per direction, and
+//! the diagnostic messages are decoded as UDS if a [`DidConfig`] is set.
+//!
+//! The messages secured by TLS can't be decoded.
+
+mod capture;
+mod packet;
+
+use crate::{
+    codec::{MessageDecoder, DEFAULT_VERSIONS},
+    error::Error,
+    Message, Payload, PayloadRegistry, TCP_SERVER_PORT,
+};
+use iso14229_1::{request::Request, response::Response, DidConfig};
+use packet::Header;
+use serde_json::{json, Value};
+use std::{collections::HashMap, io::Write, net::SocketAddr, path::Path, time::Duration};
+
+/// max. out-of-order segments buffered per TCP direction
+const MAX_PENDING_SEGMENTS: usize = 256;
+/// the bit of the positive response service identifiers
+const POSITIVE_OFFSET: u8 = 0x40;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Transport {
+    Udp,
+    Tcp,
+}
+
+/// The diagnostic message decoded as UDS.
+#[derive(Debug, Clone)]
+pub enum Uds {
+    Request(Request),
+    Response(Response),
+}
+
+#[derive(Debug, Clone)]
+pub enum Content {
+    Message {
+        message: Message,
+        /// the UDS of the diagnostic message if enabled, or the decoding error
+        uds: Option<Result<Uds, String>>,
+    },
+    /// the data which can't be decoded
+    Error(String),
+}
+
+/// A message or decoding error of the capture.
+#[derive(Debug, Clone)]
+pub struct Event {
+    /// capture time since UNIX epoch, the time of the last segment of a TCP message
+    pub timestamp: Duration,
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+    pub transport: Transport,
+    pub content: Content,
+}
+
+impl Event {
+    /// The event as a JSON object.
+    pub fn to_json(&self) -> Value {
+        let mut value = json!({
+            "timestamp": self.timestamp.as_secs_f64(),
+            "transport": match self.transport {
+                Transport::Udp => "udp",
+                Transport::Tcp => "tcp",
+            },
+            "src": self.src.to_string(),
+            "dst": self.dst.to_string(),
+        });
+
+        match &self.content {
+            Content::Message { message, uds } => {
+                value["version"] = format!("{:?}", message.version).into();
+                value["payload_type"] = format!("{:?}", message.payload.payload_type()).into();
+                value["payload"] = format!("{:?}", message.payload).into();
+                value["data"] = hex::encode(Vec::<u8>::from(message.clone())).into();
+                if let Some(uds) = uds {
+                    value["uds"] = match uds {
+                        Ok(Uds::Request(req)) => json!({
+                            "type": "request",
+                            "service": format!("{:?}", req.service()),
+                            "sub_function": req.sub_function().map(u8::from),
+                            "data": hex::encode(req.raw_data()),
+                        }),
+                        Ok(Uds::Response(resp)) => json!({
+                            "type": "response",
+                            "service": format!("{:?}", resp.service()),
+                            "negative": resp.is_negative(),
+                            "sub_function": resp.sub_function().map(|v| v.origin()),
+                            "data": hex::encode(resp.raw_data()),
+                        }),
+                        Err(e) => json!({ "error": e }),
+                    };
+                }
+            }
+            Content::Error(e) => value["error"] = e.as_str().into(),
+        }
+
+        value
+    }
+}
+
+/// Write the events as JSON lines.
+pub fn write_json_lines<W: Write>(events: &[Event], mut writer: W) -> Result<(), Error> {
+    for event in events {
+        serde_json::to_writer(&mut writer, &event.to_json()).map_err(std::io::Error::from)?;
+        writer.write_all(b"\n")?;
+    }
+
+    Ok(())
+}
+
+/// The reassembly state of a TCP direction.
+#[derive(Debug)]
+struct Stream {
+    /// the next expected sequence number
+    next: Option<u32>,
+    pending: Vec<(u32, Vec<u8>)>,
+    decoder: MessageDecoder,
+}
+
+impl Stream {
+    fn new(decoder: MessageDecoder) -> Self {
+        Self {
+            next: Default::default(),
+            pending: Default::default(),
+            decoder,
+        }
+    }
+
+    /// Pass the in-order data to the decoder, return `false` if segments are lost.
+    fn push(&mut self, seq: u32, syn: bool, payload: &[u8]) -> bool {
+        let seq = match syn {
+            true => {
+                self.pending.clear();
+                self.decoder.clear();
+                self.next = Some(seq.wrapping_add(1));
+                seq.wrapping_add(1)
+            }
+            false => seq,
+        };
+        if payload.is_empty() {
+            return true;
+        }
+
+        let mut next = *self.next.get_or_insert(seq);
+        self.pending.push((seq, payload.to_vec()));
+        let lost = self.pending.len() > MAX_PENDING_SEGMENTS;
+        if lost {
+            // skip the gap which is never filled
+            self.decoder.clear();
+            if let Some(&(seq, _)) = self
+                .pending
+                .iter()
+                .min_by_key(|(seq, _)| seq.wrapping_sub(next))
+            {
+                next = seq;
+            }
+        }
+
+        loop {
+            let mut progress = false;
+            self.pending.retain(|(seq, data)| {
+                let received = next.wrapping_sub(*seq);
+                // a future segment
+                if (received as i32) < 0 {
+                    return true;
+                }
+                // the retransmitted data is dropped
+                if (received as usize) < data.len() {
+                    self.decoder.extend(&data[received as usize..]);
+                    next = seq.wrapping_add(data.len() as u32);
+                    progress = true;
+                }
+                false
+            });
+            if !progress {
+                break;
+            }
+        }
+        self.next = Some(next);
+
+        !lost
+    }
+}
+
+/// The dissector of the DoIP traffic in captures.
+#[derive(Debug)]
+pub struct Dissector {
+    port: u16,
+    registry: PayloadRegistry,
+    uds: Option<DidConfig>,
+    streams: HashMap<(SocketAddr, SocketAddr), Stream>,
+}
+
+impl Default for Dissector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Dissector {
+    pub fn new() -> Self {
+        Self {
+            port: TCP_SERVER_PORT,
+            registry: Default::default(),
+            uds: Default::default(),
+            streams: Default::default(),
+        }
+    }
+
+    /// The UDP and TCP port of DoIP, 13400 by default.
+    #[inline]
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    #[inline]
+    pub fn set_port(&mut self, port: u16) {
+        self.port = port;
+    }
+
+    /// Accept the registered manufacturer-specific payload types.
+    #[inline]
+    pub fn set_registry(&mut self, registry: PayloadRegistry) {
+        self.registry = registry;
+    }
+
+    /// Decode the diagnostic messages as UDS with the configuration, disabled by `None`.
+    #[inline]
+    pub fn set_uds(&mut self, cfg: Option<DidConfig>) {
+        self.uds = cfg;
+    }
+
+    /// Decode all DoIP messages of a pcap or pcapng capture.
+    pub fn dissect(&mut self, capture: &[u8]) -> Result<Vec<Event>, Error> {
+        let frames = capture::frames(capture)?;
+        Ok(frames
+            .into_iter()
+            .flat_map(|frame| self.packet(frame.link_type, frame.timestamp, frame.data))
+            .collect())
+    }
+
+    /// Decode all DoIP messages of a pcap or pcapng capture file.
+    pub fn dissect_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Vec<Event>, Error> {
+        let capture = std::fs::read(path)?;
+        self.dissect(&capture)
+    }
+
+    /// Decode the frame of the link type(LINKTYPE_* of pcap).
+    ///
+    /// The TCP streams are kept between calls until they are closed.
+    pub fn packet(&mut self, link_type: u32, timestamp: Duration, data: &[u8]) -> Vec<Event> {
+        let Some(segment) = packet::parse(link_type, data) else {
+            return Default::default();
+        };
+        if segment.src.port() != self.port && segment.dst.port() != self.port {
+            return Default::default();
+        }
+
+        let mut results = Vec::new();
+        let transport = match segment.header {
+            Header::Udp => {
+                let mut decoder = self.decoder();
+                decoder.extend(segment.payload);
+                decode(&mut decoder, &mut results);
+                if decoder.buffered() > 0 {
+                    results.push(Err("incomplete message".into()));
+                }
+                Transport::Udp
+            }
+            Header::Tcp { seq, .. } => {
+                let key = (segment.src, segment.dst);
+                let decoder = self.decoder();
+                let stream = self
+                    .streams
+                    .entry(key)
+                    .or_insert_with(|| Stream::new(decoder));
+                if !stream.push(seq, segment.header.is_syn(), segment.payload) {
+                    results.push(Err("TCP segments lost".into()));
+                }
+                decode(&mut stream.decoder, &mut results);
+                if segment.header.is_closing() {
+                    self.streams.remove(&key);
+                }
+                Transport::Tcp
+            }
+        };
+
+        results
+            .into_iter()
+            .map(|ret| Event {
+                timestamp,
+                src: segment.src,
+                dst: segment.dst,
+                transport,
+                content: match ret {
+                    Ok(message) => {
+                        let uds = match (&self.uds, &message.payload) {
+                            (Some(cfg), Payload::Diagnostic(diag)) => Some(uds(diag.data(), cfg)),
+                            _ => None,
+                        };
+                        Content::Message { message, uds }
+                    }
+                    Err(e) => Content::Error(e),
+                },
+            })
+            .collect()
+    }
+
+    /// The decoder which accepts all standard versions and payload sizes.
+    fn decoder(&self) -> MessageDecoder {
+        let mut decoder = MessageDecoder::new(u32::MAX);
+        decoder.set_versions(&DEFAULT_VERSIONS);
+        decoder.set_registry(self.registry.clone());
+        decoder
+    }
+}
+
+fn decode(decoder: &mut MessageDecoder, results: &mut Vec<Result<Message, String>>) {
+    loop {
+        match decoder.decode() {
+            Ok(Some(message)) => results.push(Ok(message)),
+            Ok(None) => break,
+            Err(e) => results.push(Err(e.to_string())),
+        }
+    }
+}
+
+/// The service identifiers of the responses have bit 6 set.
+fn uds(data: &[u8], cfg: &DidConfig) -> Result<Uds, String> {
+    match data.first() {
+        Some(sid) if sid & POSITIVE_OFFSET != 0 => {
+            Response::try_from((data, cfg)).map(Uds::Response)
+        }
+        _ => Request::try_from((data, cfg)).map(Uds::Request),
+    }
+    .map_err(|e| e.to_string())
+}
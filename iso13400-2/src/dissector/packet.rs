@@ -0,0 +1,171 @@
+//! Parsers of the link, network and transport layers down to the UDP or TCP payload.
+//!
+//! The fragmented IP packets are not reassembled and skipped.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// LINKTYPE_NULL, BSD loopback
+const LINK_NULL: u32 = 0;
+const LINK_ETHERNET: u32 = 1;
+const LINK_RAW: u32 = 101;
+/// LINKTYPE_LINUX_SLL, Linux cooked capture v1
+const LINK_LINUX_SLL: u32 = 113;
+const LINK_IPV4: u32 = 228;
+const LINK_IPV6: u32 = 229;
+/// LINKTYPE_LINUX_SLL2, Linux cooked capture v2
+const LINK_LINUX_SLL2: u32 = 276;
+
+const ETHER_IPV4: u16 = 0x0800;
+const ETHER_IPV6: u16 = 0x86DD;
+const ETHER_VLAN: u16 = 0x8100;
+const ETHER_QINQ: u16 = 0x88A8;
+
+const IP_TCP: u8 = 6;
+const IP_UDP: u8 = 17;
+const IPV6_HOP_BY_HOP: u8 = 0;
+const IPV6_ROUTING: u8 = 43;
+const IPV6_FRAGMENT: u8 = 44;
+const IPV6_AUTH: u8 = 51;
+const IPV6_DESTINATION: u8 = 60;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_RST: u8 = 0x04;
+
+/// The transport layer header of a segment.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum Header {
+    Udp,
+    Tcp { seq: u32, flags: u8 },
+}
+
+impl Header {
+    #[inline]
+    pub(crate) fn is_syn(&self) -> bool {
+        matches!(self, Self::Tcp { flags, .. } if flags & TCP_SYN != 0)
+    }
+
+    /// The TCP connection is finished or reset.
+    #[inline]
+    pub(crate) fn is_closing(&self) -> bool {
+        matches!(self, Self::Tcp { flags, .. } if flags & (TCP_FIN | TCP_RST) != 0)
+    }
+}
+
+/// The UDP datagram or TCP segment of a captured frame.
+#[derive(Debug, Clone)]
+pub(crate) struct Segment<'a> {
+    pub(crate) src: SocketAddr,
+    pub(crate) dst: SocketAddr,
+    pub(crate) header: Header,
+    pub(crate) payload: &'a [u8],
+}
+
+/// Parse the frame of the link type, `None` if it isn't a UDP or TCP segment.
+pub(crate) fn parse(link_type: u32, data: &[u8]) -> Option<Segment<'_>> {
+    match link_type {
+        LINK_ETHERNET => {
+            let mut ether_type = u16::from_be_bytes(data.get(12..14)?.try_into().ok()?);
+            let mut offset = 14;
+            while matches!(ether_type, ETHER_VLAN | ETHER_QINQ) {
+                ether_type = u16::from_be_bytes(data.get(offset + 2..offset + 4)?.try_into().ok()?);
+                offset += 4;
+            }
+            ethernet(ether_type, data.get(offset..)?)
+        }
+        LINK_LINUX_SLL => {
+            let ether_type = u16::from_be_bytes(data.get(14..16)?.try_into().ok()?);
+            ethernet(ether_type, data.get(16..)?)
+        }
+        LINK_LINUX_SLL2 => {
+            let ether_type = u16::from_be_bytes(data.get(0..2)?.try_into().ok()?);
+            ethernet(ether_type, data.get(20..)?)
+        }
+        LINK_NULL => ip(data.get(4..)?),
+        LINK_RAW | LINK_IPV4 | LINK_IPV6 => ip(data),
+        _ => None,
+    }
+}
+
+fn ethernet(ether_type: u16, data: &[u8]) -> Option<Segment<'_>> {
+    match ether_type {
+        ETHER_IPV4 => ipv4(data),
+        ETHER_IPV6 => ipv6(data),
+        _ => None,
+    }
+}
+
+/// The IP packet without link layer, the version is read from the packet.
+fn ip(data: &[u8]) -> Option<Segment<'_>> {
+    match data.first()? >> 4 {
+        4 => ipv4(data),
+        6 => ipv6(data),
+        _ => None,
+    }
+}
+
+fn ipv4(data: &[u8]) -> Option<Segment<'_>> {
+    let header_len = ((data.first()? & 0x0F) as usize) * 4;
+    let total_len = u16::from_be_bytes(data.get(2..4)?.try_into().ok()?) as usize;
+    let fragment = u16::from_be_bytes(data.get(6..8)?.try_into().ok()?);
+    // more fragments or fragment offset
+    if fragment & 0x3FFF != 0 {
+        return None;
+    }
+
+    let protocol = *data.get(9)?;
+    let src = Ipv4Addr::from(<[u8; 4]>::try_from(data.get(12..16)?).ok()?);
+    let dst = Ipv4Addr::from(<[u8; 4]>::try_from(data.get(16..20)?).ok()?);
+    // the frame may be padded after the packet
+    let payload = data.get(header_len..total_len.min(data.len()))?;
+
+    transport(protocol, src.into(), dst.into(), payload)
+}
+
+fn ipv6(data: &[u8]) -> Option<Segment<'_>> {
+    let payload_len = u16::from_be_bytes(data.get(4..6)?.try_into().ok()?) as usize;
+    let mut next = *data.get(6)?;
+    let src = Ipv6Addr::from(<[u8; 16]>::try_from(data.get(8..24)?).ok()?);
+    let dst = Ipv6Addr::from(<[u8; 16]>::try_from(data.get(24..40)?).ok()?);
+    let mut payload = data.get(40..(40 + payload_len).min(data.len()))?;
+
+    loop {
+        let length = match next {
+            IPV6_HOP_BY_HOP | IPV6_ROUTING | IPV6_DESTINATION => {
+                (*payload.get(1)? as usize + 1) * 8
+            }
+            IPV6_AUTH => (*payload.get(1)? as usize + 2) * 4,
+            IPV6_FRAGMENT => return None,
+            _ => break,
+        };
+        next = *payload.first()?;
+        payload = payload.get(length..)?;
+    }
+
+    transport(next, src.into(), dst.into(), payload)
+}
+
+fn transport(protocol: u8, src: IpAddr, dst: IpAddr, data: &[u8]) -> Option<Segment<'_>> {
+    let src_port = u16::from_be_bytes(data.get(0..2)?.try_into().ok()?);
+    let dst_port = u16::from_be_bytes(data.get(2..4)?.try_into().ok()?);
+    let (header, payload) = match protocol {
+        IP_UDP => {
+            let length = u16::from_be_bytes(data.get(4..6)?.try_into().ok()?) as usize;
+            (Header::Udp, data.get(8..length.min(data.len()))?)
+        }
+        IP_TCP => {
+            let seq = u32::from_be_bytes(data.get(4..8)?.try_into().ok()?);
+            let offset = ((data.get(12)? >> 4) as usize) * 4;
+            let flags = *data.get(13)?;
+            (Header::Tcp { seq, flags }, data.get(offset..)?)
+        }
+        _ => return None,
+    };
+
+    Some(Segment {
+        src: SocketAddr::new(src, src_port),
+        dst: SocketAddr::new(dst, dst_port),
+        header,
+        payload,
+    })
+}
@@ -1,3 +1,7 @@
+use crate::{
+    response, ActiveCode, DiagnosticNegativeCode, HeaderNegativeCode, PayloadType, Version,
+};
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("ISO 13400-2 - input error: {0}")]
@@ -11,4 +15,48 @@ pub enum Error {
     InvalidVersion { version: u8, reverse: u8 },
     #[error("Iso 13400-2 - invalid payload type: {0}")]
     InvalidPayloadType(u16),
+    #[error("ISO 13400-2 - {payload_type:?} is not supported by version {version:?}")]
+    UnsupportedPayload {
+        version: Version,
+        payload_type: PayloadType,
+    },
+    #[error("ISO 13400-2 - invalid header: {code:?}, close socket: {close}")]
+    InvalidHeader {
+        code: HeaderNegativeCode,
+        close: bool,
+    },
+
+    #[error("ISO 13400-2 - I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("ISO 13400-2 - timeout when time({value}{unit})")]
+    Timeout { value: u64, unit: &'static str },
+    #[error("ISO 13400-2 - invalid capture: {0}")]
+    InvalidCapture(String),
+    #[error("ISO 13400-2 - TLS error: {0}")]
+    TlsError(String),
+    #[error("ISO 13400-2 - connection closed")]
+    ConnectionClosed,
+    #[error("ISO 13400-2 - header negative acknowledge: {0:?}")]
+    HeaderNegative(HeaderNegativeCode),
+    #[error("ISO 13400-2 - routing activation failed: {0:?}")]
+    RoutingActivation(ActiveCode),
+    #[error("ISO 13400-2 - routing is not activated")]
+    RoutingInactive,
+    #[error("ISO 13400-2 - {0}")]
+    DiagnosticNegative(DiagnosticNegativeCode),
+    #[error("ISO 13400-2 - unexpected payload type: {0:?}")]
+    UnexpectedPayload(PayloadType),
+}
+
+impl Error {
+    /// The generic DoIP header negative acknowledge to send for [`Error::InvalidHeader`]
+    /// and whether the socket must be closed after sending.
+    pub fn header_negative(&self) -> Option<(response::HeaderNegative, bool)> {
+        match *self {
+            Self::InvalidHeader { code, close } => {
+                Some((response::HeaderNegative::new(code), close))
+            }
+            _ => None,
+        }
+    }
 }
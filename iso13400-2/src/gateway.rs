@@ -0,0 +1,327 @@
+//! DoIP-to-CAN gateway which routes the diagnostic messages onto ISO-TP.
+//!
+//! The physical requests are relayed to the target by [`CanIsoTp`] and the response
+//! is sent back to the TCP_DATA socket which the request came from.
+//! The functional requests to a `VMSpecificFunctional` group address are sent to the
+//! functional CAN ID of the group, and the single frame responses of all members
+//! are collected within the response timeout.
+
+use crate::{
+    server::{DiagnosticHandler, Responder},
+    Diagnostic, DiagnosticNegativeCode, LogicAddress,
+};
+use iso15765_2::{
+    can::{Address, AddressType, CanIsoTp},
+    IsoTp, IsoTpError, IsoTpFrame, MAX_LENGTH_2004,
+};
+use rs_can::{CanDevice, CanFrame};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    sync::mpsc::{self, error::TrySendError, OwnedPermit},
+    time::{timeout, timeout_at, Instant},
+};
+use tokio_stream::{Stream, StreamExt};
+
+/// P2_CAN_Client(ms)
+const DEFAULT_TIMEOUT: u64 = 1_000;
+/// P2*_CAN_Client(ms)
+const DEFAULT_PENDING_TIMEOUT: u64 = 5_000;
+/// max. diagnostic messages waiting for routing
+const DEFAULT_QUEUE_SIZE: usize = 16;
+/// UDS NRC requestCorrectlyReceived-ResponsePending
+const RESPONSE_PENDING: u8 = 0x78;
+
+/// The CAN ECUs of a functional group.
+#[derive(Debug, Clone)]
+pub struct FunctionalGroup {
+    /// functional CAN ID of the group
+    pub fid: u32,
+    /// logical addresses of the members, which must be routed in [`GatewayConfig::routes`]
+    pub members: Vec<LogicAddress>,
+}
+
+/// The configuration of [`CanGateway`].
+#[derive(Debug, Clone)]
+pub struct GatewayConfig {
+    /// logical address of the CAN ECU → ISO-TP address
+    pub routes: HashMap<LogicAddress, Address>,
+    /// functional group logical address → CAN ECUs of the group
+    pub groups: HashMap<LogicAddress, FunctionalGroup>,
+    /// response timeout(ms)
+    pub timeout: u64,
+    /// response timeout(ms) after the response pending NRC
+    pub pending_timeout: u64,
+    /// max. size of the routed diagnostic message
+    pub max_data_size: usize,
+    /// max. diagnostic messages waiting for routing
+    pub queue_size: usize,
+}
+
+impl Default for GatewayConfig {
+    fn default() -> Self {
+        Self {
+            routes: Default::default(),
+            groups: Default::default(),
+            timeout: DEFAULT_TIMEOUT,
+            pending_timeout: DEFAULT_PENDING_TIMEOUT,
+            max_data_size: MAX_LENGTH_2004,
+            queue_size: DEFAULT_QUEUE_SIZE,
+        }
+    }
+}
+
+impl GatewayConfig {
+    /// Route the physical diagnostic messages of `target` by `address`.
+    pub fn add_route(&mut self, target: LogicAddress, address: Address) -> &mut Self {
+        self.routes.insert(target, address);
+        self
+    }
+
+    /// Route the functional diagnostic messages of `group` by `fid`.
+    pub fn add_group(
+        &mut self,
+        group: LogicAddress,
+        fid: u32,
+        members: Vec<LogicAddress>,
+    ) -> &mut Self {
+        self.groups.insert(group, FunctionalGroup { fid, members });
+        self
+    }
+}
+
+type Request = (Diagnostic, Responder);
+
+/// A [`DiagnosticHandler`] which relays the diagnostic messages to the CAN ECUs.
+///
+/// The ISO-TP channel must be started before the gateway is used.
+/// The place in the routing queue is reserved when the message is validated,
+/// so the message is negatively acknowledged with `OutOfMemory` if the queue is full.
+/// The places of the messages not handled are released by
+/// [`discard`](DiagnosticHandler::discard) and [`closed`](DiagnosticHandler::closed).
+pub struct CanGateway<D, C, F> {
+    isotp: CanIsoTp<D, C, F>,
+    config: Arc<GatewayConfig>,
+    sender: mpsc::Sender<Request>,
+    /// the places reserved for the acknowledged messages of each test equipment
+    permits: Mutex<HashMap<LogicAddress, Vec<OwnedPermit<Request>>>>,
+}
+
+impl<D, C, F> CanGateway<D, C, F>
+where
+    D: CanDevice<Channel = C, Frame = F> + Clone + Send + 'static,
+    C: Clone + Eq + Display + Send + Sync + 'static,
+    F: CanFrame<Channel = C> + Clone + Display + Send + 'static,
+{
+    pub async fn new(isotp: CanIsoTp<D, C, F>, config: GatewayConfig) -> Result<Self, IsoTpError> {
+        let stream = isotp.frame_stream().await?;
+        let config = Arc::new(config);
+        let (sender, receiver) = mpsc::channel(config.queue_size.max(1));
+        tokio::spawn(route_loop(isotp.clone(), config.clone(), stream, receiver));
+
+        Ok(Self {
+            isotp,
+            config,
+            sender,
+            permits: Default::default(),
+        })
+    }
+
+    #[inline]
+    pub fn config(&self) -> &GatewayConfig {
+        &self.config
+    }
+
+    /// Take a place reserved for `tester`.
+    fn take_permit(&self, tester: LogicAddress) -> Option<OwnedPermit<Request>> {
+        let mut permits = self.permits.lock().unwrap();
+        let permit = permits.get_mut(&tester).and_then(|v| v.pop());
+        if permits.get(&tester).is_some_and(|v| v.is_empty()) {
+            permits.remove(&tester);
+        }
+
+        permit
+    }
+}
+
+#[async_trait::async_trait]
+impl<D, C, F> DiagnosticHandler for CanGateway<D, C, F>
+where
+    D: CanDevice<Channel = C, Frame = F> + Clone + Send + 'static,
+    C: Clone + Eq + Display + Send + Sync + 'static,
+    F: CanFrame<Channel = C> + Clone + Display + Send + 'static,
+{
+    async fn validate(&self, diag: &Diagnostic) -> Result<(), DiagnosticNegativeCode> {
+        let target = diag.dst_addr();
+        if !self.config.routes.contains_key(&target) && !self.config.groups.contains_key(&target) {
+            return Err(DiagnosticNegativeCode::UnknownTargetAddress);
+        }
+        if diag.data().len() > self.config.max_data_size {
+            return Err(DiagnosticNegativeCode::DiagnosticMessageTooLarge);
+        }
+        if self.isotp.transmitter().is_closed() {
+            return Err(DiagnosticNegativeCode::TargetUnreachable);
+        }
+        // the place is taken by `handle` after the positive acknowledge
+        let permit = match self.sender.clone().try_reserve_owned() {
+            Ok(v) => v,
+            Err(TrySendError::Full(_)) => return Err(DiagnosticNegativeCode::OutOfMemory),
+            Err(TrySendError::Closed(_)) => return Err(DiagnosticNegativeCode::TargetUnreachable),
+        };
+        self.permits
+            .lock()
+            .unwrap()
+            .entry(diag.src_addr())
+            .or_default()
+            .push(permit);
+
+        Ok(())
+    }
+
+    async fn handle(&self, diag: Diagnostic, responder: Responder) {
+        match self.take_permit(diag.src_addr()) {
+            Some(permit) => {
+                permit.send((diag, responder));
+            }
+            None => rsutil::warn!(
+                "ISO 13400-2 - gateway dropped the diagnostic message without reserved place"
+            ),
+        }
+    }
+
+    async fn discard(&self, diag: &Diagnostic) {
+        // the place is released when the permit is dropped
+        let _ = self.take_permit(diag.src_addr());
+    }
+
+    async fn closed(&self, tester: LogicAddress) {
+        self.permits.lock().unwrap().remove(&tester);
+    }
+}
+
+async fn route_loop<D, C, F, S>(
+    isotp: CanIsoTp<D, C, F>,
+    config: Arc<GatewayConfig>,
+    mut stream: S,
+    mut receiver: mpsc::Receiver<Request>,
+) where
+    D: CanDevice<Channel = C, Frame = F> + Clone + Send + 'static,
+    C: Clone + Eq + Display + Send + Sync + 'static,
+    F: CanFrame<Channel = C> + Clone + Display + Send + 'static,
+    S: Stream<Item = F> + Unpin,
+{
+    while let Some((diag, responder)) = receiver.recv().await {
+        let target = diag.dst_addr();
+        let ret = match config.groups.get(&target) {
+            Some(group) => functional(&isotp, &config, &mut stream, group, &diag, &responder).await,
+            None => match config.routes.get(&target) {
+                Some(&address) => physical(&isotp, &config, address, &diag, &responder).await,
+                None => Ok(()),
+            },
+        };
+
+        if let Err(e) = ret {
+            rsutil::warn!("ISO 13400-2 - gateway failed to route to {}: {}", target, e);
+        }
+    }
+}
+
+async fn physical<D, C, F>(
+    isotp: &CanIsoTp<D, C, F>,
+    config: &GatewayConfig,
+    address: Address,
+    diag: &Diagnostic,
+    responder: &Responder,
+) -> Result<(), IsoTpError>
+where
+    D: CanDevice<Channel = C, Frame = F> + Clone + Send + 'static,
+    C: Clone + Eq + Display + Send + Sync + 'static,
+    F: CanFrame<Channel = C> + Clone + Display + Send + 'static,
+{
+    isotp.update_address(address).await;
+    isotp.transmit(AddressType::Physical, diag.data()).await?;
+
+    let mut timeout = config.timeout;
+    loop {
+        let data = isotp.wait_data(timeout).await?;
+        let pending = is_pending(&data);
+        if responder.respond(diag.dst_addr(), data.to_vec()).is_err() {
+            return Ok(());
+        }
+        if !pending {
+            return Ok(());
+        }
+        timeout = config.pending_timeout;
+    }
+}
+
+async fn functional<D, C, F, S>(
+    isotp: &CanIsoTp<D, C, F>,
+    config: &GatewayConfig,
+    stream: &mut S,
+    group: &FunctionalGroup,
+    diag: &Diagnostic,
+    responder: &Responder,
+) -> Result<(), IsoTpError>
+where
+    D: CanDevice<Channel = C, Frame = F> + Clone + Send + 'static,
+    C: Clone + Eq + Display + Send + Sync + 'static,
+    F: CanFrame<Channel = C> + Clone + Display + Send + 'static,
+    S: Stream<Item = F> + Unpin,
+{
+    // discard the frames received before the request
+    while let Ok(Some(_)) = timeout(Duration::ZERO, stream.next()).await {}
+
+    // all responses are passed to the frame stream while receiving on the functional ID
+    isotp
+        .update_address(Address {
+            tx_id: group.fid,
+            rx_id: group.fid,
+            fid: group.fid,
+        })
+        .await;
+    isotp.transmit(AddressType::Functional, diag.data()).await?;
+
+    let channel = isotp.get_channel();
+    let members = group
+        .members
+        .iter()
+        .filter_map(|member| Some((config.routes.get(member)?.rx_id, *member)))
+        .collect::<HashMap<_, _>>();
+    let mut deadline = Instant::now() + Duration::from_millis(config.timeout);
+    while let Ok(Some(frame)) = timeout_at(deadline, stream.next()).await {
+        if frame.channel() != channel {
+            continue;
+        }
+        let Some(&member) = members.get(&frame.id().into_bits()) else {
+            continue;
+        };
+
+        match IsoTpFrame::decode(frame.data()) {
+            Ok(IsoTpFrame::SingleFrame { data }) => {
+                if is_pending(&data) {
+                    deadline = Instant::now() + Duration::from_millis(config.pending_timeout);
+                }
+                if responder.respond(member, data).is_err() {
+                    break;
+                }
+            }
+            Ok(_) => rsutil::warn!(
+                "ISO 13400-2 - gateway ignored the multi-frame functional response of {}",
+                member
+            ),
+            Err(e) => rsutil::warn!("ISO 13400-2 - gateway received invalid frame: {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+#[inline]
+fn is_pending(data: &[u8]) -> bool {
+    matches!(data, [0x7F, _, RESPONSE_PENDING])
+}
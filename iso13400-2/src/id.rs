@@ -3,7 +3,7 @@ use crate::{
     {error::Error, utils},
 };
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 #[repr(transparent)]
 pub struct Id(pub(crate) u64);
 
@@ -46,3 +46,27 @@ impl From<Id> for Vec<u8> {
         result
     }
 }
+
+/// The EID/GID is serialized as a hex string of 6 bytes, e.g. `"001100110011"`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Id {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let data: Vec<_> = (*self).into();
+        serializer.serialize_str(&hex::encode(data))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Id {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = crate::utils::hex_bytes::deserialize(deserializer)?;
+        if data.len() != Self::length() {
+            return Err(serde::de::Error::invalid_length(
+                data.len(),
+                &"6 bytes of EID/GID",
+            ));
+        }
+
+        Self::try_from(data.as_slice()).map_err(serde::de::Error::custom)
+    }
+}
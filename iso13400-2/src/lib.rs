@@ -95,12 +95,30 @@
 //! Table 48 — Payload type routing activation response
 //!
 //! Table 49 — Routing activation response code values
+mod borrowed;
 mod common;
 mod constants;
 mod error;
 
+#[cfg(feature = "net")]
+pub mod client;
+pub mod codec;
+#[cfg(feature = "net")]
+pub mod discovery;
+#[cfg(feature = "dissector")]
+pub mod dissector;
+#[cfg(feature = "gateway")]
+pub mod gateway;
 pub mod request;
 pub mod response;
+#[cfg(feature = "net")]
+pub mod server;
+#[cfg(feature = "net")]
+mod socket;
+pub mod state;
+mod timing;
+#[cfg(feature = "tls")]
+pub mod tls;
 
 pub(crate) mod utils;
 
@@ -109,7 +127,9 @@ mod payload;
 
 pub type Eid = id::Id;
 pub type Gid = id::Id;
-pub use self::{common::*, constants::*, error::Error as Iso13400Error, payload::*};
+pub use self::{
+    borrowed::*, common::*, constants::*, error::Error as Iso13400Error, payload::*, timing::*,
+};
 
 /// It will be removed in a future version. Use [NodeType] instead
 #[deprecated(
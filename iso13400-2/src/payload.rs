@@ -1,7 +1,9 @@
 use crate::{constants::*, error::Error};
+use std::collections::HashMap;
 
 #[repr(u16)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PayloadType {
     RespHeaderNegative = HEADER_NEGATIVE,
     ReqVehicleId = UDP_REQ_VEHICLE_IDENTIFIER,
@@ -19,6 +21,8 @@ pub enum PayloadType {
     Diagnostic = TCP_DIAGNOSTIC,
     RespDiagPositive = TCP_RESP_DIAGNOSTIC_POSITIVE,
     RespDiagNegative = TCP_RESP_DIAGNOSTIC_NEGATIVE,
+    /// manufacturer-specific payload type registered in [`PayloadRegistry`]
+    Custom(u16),
 }
 
 impl TryFrom<u16> for PayloadType {
@@ -48,6 +52,130 @@ impl TryFrom<u16> for PayloadType {
 
 impl From<PayloadType> for u16 {
     fn from(val: PayloadType) -> Self {
-        val as u16
+        match val {
+            PayloadType::RespHeaderNegative => HEADER_NEGATIVE,
+            PayloadType::ReqVehicleId => UDP_REQ_VEHICLE_IDENTIFIER,
+            PayloadType::ReqVehicleWithEid => UDP_REQ_VEHICLE_ID_WITH_EID,
+            PayloadType::ReqVehicleWithVIN => UDP_REQ_VEHICLE_ID_WITH_VIN,
+            PayloadType::RespVehicleId => UDP_RESP_VEHICLE_IDENTIFIER,
+            PayloadType::ReqRoutingActive => TCP_REQ_ROUTING_ACTIVE,
+            PayloadType::RespRoutingActive => TCP_RESP_ROUTING_ACTIVE,
+            PayloadType::ReqAliveCheck => TCP_REQ_ALIVE_CHECK,
+            PayloadType::RespAliveCheck => TCP_RESP_ALIVE_CHECK,
+            PayloadType::ReqEntityStatus => UDP_REQ_ENTITY_STATUS,
+            PayloadType::RespEntityStatus => UDP_RESP_ENTITY_STATUS,
+            PayloadType::ReqDiagPowerMode => UDP_REQ_DIAGNOSTIC_POWER_MODE,
+            PayloadType::RespDiagPowerMode => UDP_RESP_DIAGNOSTIC_POWER_MODE,
+            PayloadType::Diagnostic => TCP_DIAGNOSTIC,
+            PayloadType::RespDiagPositive => TCP_RESP_DIAGNOSTIC_POSITIVE,
+            PayloadType::RespDiagNegative => TCP_RESP_DIAGNOSTIC_NEGATIVE,
+            PayloadType::Custom(v) => v,
+        }
+    }
+}
+
+impl PayloadType {
+    /// Whether the payload length from the generic header is valid for the payload type.
+    ///
+    /// Table 17 — Overview of DoIP payload types
+    pub fn is_valid_length(&self, length: usize) -> bool {
+        const SIZE_OF_OEM: usize = 4;
+        const SIZE_OF_VEHICLE_ID: usize =
+            LENGTH_OF_VIN + SIZE_OF_ADDRESS + SIZE_OF_ID + SIZE_OF_ID + 1;
+        const SIZE_OF_REQ_ACTIVE: usize = SIZE_OF_ADDRESS + 1 + 4;
+        const SIZE_OF_RESP_ACTIVE: usize = SIZE_OF_ADDRESS + SIZE_OF_ADDRESS + 1 + 4;
+        const SIZE_OF_ENTITY_STATUS: usize = 1 + 1 + 1;
+
+        match self {
+            Self::RespHeaderNegative | Self::RespDiagPowerMode => length == 1,
+            Self::ReqVehicleId
+            | Self::ReqAliveCheck
+            | Self::ReqEntityStatus
+            | Self::ReqDiagPowerMode => length == 0,
+            Self::ReqVehicleWithEid => length == SIZE_OF_ID,
+            Self::ReqVehicleWithVIN => length == LENGTH_OF_VIN,
+            // with optional VIN/GID sync. status
+            Self::RespVehicleId => length == SIZE_OF_VEHICLE_ID || length == SIZE_OF_VEHICLE_ID + 1,
+            // with optional OEM specific data
+            Self::ReqRoutingActive => {
+                length == SIZE_OF_REQ_ACTIVE || length == SIZE_OF_REQ_ACTIVE + SIZE_OF_OEM
+            }
+            Self::RespRoutingActive => {
+                length == SIZE_OF_RESP_ACTIVE || length == SIZE_OF_RESP_ACTIVE + SIZE_OF_OEM
+            }
+            Self::RespAliveCheck => length == SIZE_OF_ADDRESS,
+            // with optional max. data size
+            Self::RespEntityStatus => {
+                length == SIZE_OF_ENTITY_STATUS || length == SIZE_OF_ENTITY_STATUS + 4
+            }
+            Self::Diagnostic => length >= SIZE_OF_ADDRESS + SIZE_OF_ADDRESS,
+            // with ACK/NACK code
+            Self::RespDiagPositive | Self::RespDiagNegative => {
+                length > SIZE_OF_ADDRESS + SIZE_OF_ADDRESS
+            }
+            Self::Custom(_) => true,
+        }
+    }
+}
+
+/// A typed manufacturer-specific payload, the payload types 0xF000 ~ 0xFFFF are
+/// reserved for the manufacturer by Table 17.
+pub trait CustomPayload: Sized {
+    const PAYLOAD_TYPE: u16;
+
+    /// Decode the payload without the generic DoIP header.
+    fn decode(data: &[u8]) -> Result<Self, Error>;
+
+    /// Encode the payload without the generic DoIP header.
+    fn encode(&self) -> Vec<u8>;
+}
+
+/// Check the data of a registered payload type.
+type Validator = fn(&[u8]) -> Result<(), Error>;
+
+/// The manufacturer-specific payload types accepted besides the standard payload types.
+#[derive(Debug, Clone, Default)]
+pub struct PayloadRegistry {
+    validators: HashMap<u16, Validator>,
+}
+
+impl PayloadRegistry {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Register the typed payload, the data is checked by [`CustomPayload::decode`].
+    pub fn register<T: CustomPayload>(&mut self) -> Result<&mut Self, Error> {
+        self.insert(T::PAYLOAD_TYPE, |data| T::decode(data).map(|_| ()))
+    }
+
+    /// Register the payload type with arbitrary data.
+    pub fn register_raw(&mut self, payload_type: u16) -> Result<&mut Self, Error> {
+        self.insert(payload_type, |_| Ok(()))
+    }
+
+    #[inline]
+    pub fn contains(&self, payload_type: u16) -> bool {
+        self.validators.contains_key(&payload_type)
+    }
+
+    /// Check the data of the registered payload type.
+    pub fn validate(&self, payload_type: u16, data: &[u8]) -> Result<(), Error> {
+        match self.validators.get(&payload_type) {
+            Some(validator) => validator(data),
+            None => Err(Error::InvalidPayloadType(payload_type)),
+        }
+    }
+
+    fn insert(&mut self, payload_type: u16, validator: Validator) -> Result<&mut Self, Error> {
+        if !MANUFACTURER_PAYLOAD_TYPES.contains(&payload_type) {
+            return Err(Error::InvalidParam(format!(
+                "payload type {:#06X} is not a manufacturer-specific payload type",
+                payload_type
+            )));
+        }
+        self.validators.insert(payload_type, validator);
+
+        Ok(self)
     }
 }
@@ -3,6 +3,7 @@ use getset::{CopyGetters, Getters};
 
 /****** --- UDP --- ********/
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VehicleID; // 0x0001
 
 impl VehicleID {
@@ -32,6 +33,7 @@ impl From<VehicleID> for Vec<u8> {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, CopyGetters)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[get_copy = "pub"]
 pub struct VehicleIDWithEID {
     // 0x0002
@@ -71,6 +73,7 @@ impl From<VehicleIDWithEID> for Vec<u8> {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Getters)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[get = "pub"]
 pub struct VehicleIDWithVIN {
     // 0x0003
@@ -126,6 +129,7 @@ impl From<VehicleIDWithVIN> for Vec<u8> {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EntityStatus; // 0x4001
 
 impl EntityStatus {
@@ -155,6 +159,7 @@ impl From<EntityStatus> for Vec<u8> {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DiagnosticPowerMode; // 0x4003
 
 impl DiagnosticPowerMode {
@@ -187,6 +192,7 @@ impl From<DiagnosticPowerMode> for Vec<u8> {
 
 /****** --- TCP --- ********/
 #[derive(Debug, Clone, Eq, PartialEq, CopyGetters)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[get_copy = "pub"]
 pub struct RoutingActive {
     // 0x0005
@@ -267,6 +273,7 @@ impl From<RoutingActive> for Vec<u8> {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AliveCheck; // 0x0007
 
 impl AliveCheck {
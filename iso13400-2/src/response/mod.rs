@@ -3,6 +3,7 @@ use getset::{CopyGetters, Getters};
 use std::fmt::{Display, Formatter};
 
 #[derive(Debug, Clone, Eq, PartialEq, CopyGetters)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[get_copy = "pub"]
 pub struct HeaderNegative {
     pub(crate) code: HeaderNegativeCode,
@@ -47,6 +48,7 @@ impl From<HeaderNegative> for Vec<u8> {
 /// send response 3 times with interval 500ms
 /// the RoutingActive from client must be 0xE0 when further_act = 0x10.
 #[derive(Debug, Clone, Eq, PartialEq, Getters, CopyGetters)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VehicleID {
     // 0x0004
     #[get = "pub"]
@@ -162,6 +164,7 @@ impl From<VehicleID> for Vec<u8> {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, CopyGetters)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[get_copy = "pub"]
 pub struct EntityStatus {
     // 0x4002
@@ -253,6 +256,7 @@ impl Display for EntityStatus {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, CopyGetters)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[get_copy = "pub"]
 pub struct DiagnosticPowerMode {
     // 0x4004
@@ -293,6 +297,7 @@ impl From<DiagnosticPowerMode> for Vec<u8> {
 
 /****** --- TCP --- ********/
 #[derive(Debug, Clone, Eq, PartialEq, CopyGetters)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[get_copy = "pub"]
 pub struct RoutingActive {
     // 0x0006
@@ -388,6 +393,7 @@ impl From<RoutingActive> for Vec<u8> {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, CopyGetters)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[get_copy = "pub"]
 pub struct AliveCheck {
     // 0x0008
@@ -428,6 +434,7 @@ impl From<AliveCheck> for Vec<u8> {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Getters)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[get = "pub"]
 pub struct DiagnosticPositive {
     // 0x8002
@@ -439,6 +446,7 @@ pub struct DiagnosticPositive {
     pub(crate) code: DiagnosticPositiveCode,
     // #[getter(name = "previous_diagnostic_data")]
     #[getset(get = "pub")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::hex_bytes"))]
     pub(crate) pre_diag_data: Vec<u8>,
 }
 
@@ -514,6 +522,7 @@ impl Display for DiagnosticPositive {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Getters, CopyGetters)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DiagnosticNegative {
     // 0x8003
     #[getset(get_copy = "pub")]
@@ -524,6 +533,7 @@ pub struct DiagnosticNegative {
     pub(crate) code: DiagnosticNegativeCode,
     // #[getter(name = "previous_diagnostic_data")]
     #[getset(get = "pub")]
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::hex_bytes"))]
     pub(crate) pre_diag_data: Vec<u8>,
 }
 
@@ -0,0 +1,106 @@
+use super::Firewall;
+use crate::{
+    codec::{DEFAULT_MAX_PAYLOAD_SIZE, DEFAULT_VERSIONS},
+    constants::*,
+    error::Error,
+    DoIpTiming, Eid, FurtherAction, Gid, LogicAddress, NodeType, PayloadRegistry, PowerMode,
+    SyncStatus, Version,
+};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+/// The configuration of [`DoIpEntity`](crate::server::DoIpEntity).
+#[derive(Debug, Clone)]
+pub struct EntityConfig {
+    /// protocol version used by all messages sent by the entity
+    pub version: Version,
+    /// protocol versions accepted from the test equipment,
+    /// the vehicle identification requests with the default version are always accepted
+    pub versions: Vec<Version>,
+    /// logical address of the entity
+    pub address: LogicAddress,
+    pub vin: String,
+    pub eid: Eid,
+    pub gid: Gid,
+    pub node_type: NodeType,
+    pub further_act: FurtherAction,
+    pub sync_status: Option<SyncStatus>,
+    /// max. concurrent TCP_DATA sockets
+    pub max_sockets: u8,
+    /// max. size of a diagnostic message payload
+    pub max_data_size: u32,
+    /// manufacturer-specific payload types accepted by the entity
+    pub payloads: PayloadRegistry,
+    /// the diagnostic power mode answered by [`LiveStatus`](super::LiveStatus) without a source
+    pub power_mode: PowerMode,
+    /// logical addresses of the test equipment allowed to activate routing,
+    /// all client addresses are allowed if empty
+    pub whitelist: Vec<LogicAddress>,
+    /// the rules of the diagnostic messages routed by the entity, all messages allowed by default
+    pub firewall: Firewall,
+    /// TCP_DATA listener, dual-stack if bound to `[::]`
+    pub tcp_addr: SocketAddr,
+    /// UDP_DISCOVERY socket, dual-stack if bound to `[::]`
+    pub udp_addr: SocketAddr,
+    /// destination of the vehicle announcements, no announcement if `None`,
+    /// e.g. the broadcast address or [`IPV6_ALL_NODES`] with the scope of the interface
+    pub announce_addr: Option<SocketAddr>,
+    /// the vehicle announcements, inactivity and alive check timers,
+    /// and A_Processing_Time for validating a diagnostic message
+    pub timing: DoIpTiming,
+    /// the secured TCP_DATA socket, no TLS listener if `None`
+    #[cfg(feature = "tls")]
+    pub tls: Option<super::TlsConfig>,
+}
+
+impl EntityConfig {
+    pub fn new(address: LogicAddress, vin: &str, eid: Eid, gid: Gid) -> Result<Self, Error> {
+        if vin.len() != LENGTH_OF_VIN {
+            return Err(Error::InvalidParam(format!(
+                "length of vin must equal {}",
+                LENGTH_OF_VIN
+            )));
+        }
+
+        Ok(Self {
+            version: PROTOCOL_VERSION,
+            versions: DEFAULT_VERSIONS.to_vec(),
+            address,
+            vin: vin.to_owned(),
+            eid,
+            gid,
+            node_type: NodeType::Node,
+            further_act: FurtherAction::NoAction,
+            sync_status: None,
+            max_sockets: 1,
+            max_data_size: DEFAULT_MAX_PAYLOAD_SIZE,
+            payloads: Default::default(),
+            power_mode: PowerMode::Ready,
+            whitelist: Default::default(),
+            firewall: Default::default(),
+            tcp_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), TCP_SERVER_PORT),
+            udp_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), UDP_SERVER_PORT),
+            announce_addr: None,
+            timing: DoIpTiming::new(PROTOCOL_VERSION),
+            #[cfg(feature = "tls")]
+            tls: None,
+        })
+    }
+
+    /// Whether the test equipment is allowed to activate routing.
+    pub fn is_allowed(&self, addr: LogicAddress) -> bool {
+        if self.whitelist.is_empty() {
+            return matches!(addr, LogicAddress::Client(_));
+        }
+
+        self.whitelist.contains(&addr)
+    }
+
+    /// Whether the routing activation is only allowed on the secured socket.
+    #[inline]
+    pub fn tls_required(&self) -> bool {
+        #[cfg(feature = "tls")]
+        return self.tls.as_ref().is_some_and(|tls| tls.required);
+        #[cfg(not(feature = "tls"))]
+        false
+    }
+}
@@ -0,0 +1,358 @@
+use super::{
+    Activation, Command, DiagnosticHandler, EntityConfig, Registry, Responder,
+    RoutingActivationPolicy,
+};
+use crate::{
+    codec::MessageDecoder,
+    error::Error,
+    request,
+    state::{EntityConnection, EntityEvent, SocketState},
+    ActiveCode, Diagnostic, DiagnosticNegativeCode, LogicAddress, Message, Payload,
+    RoutingActiveType,
+};
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    sync::{mpsc, oneshot},
+    time::{sleep_until, timeout, Instant},
+};
+
+/// size of the TCP read buffer
+const SIZE_OF_BUFFER: usize = 4096;
+
+type Writer = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// The task of a TCP_DATA socket.
+///
+/// The socket state, the inactivity and the alive check timers are kept by the
+/// [`EntityConnection`] state machine, the task decides the routing activation
+/// across the sockets(Table 49) and passes the diagnostic messages to the handler.
+pub(crate) struct Connection {
+    id: u64,
+    config: Arc<EntityConfig>,
+    registry: Arc<Registry>,
+    handler: Arc<dyn DiagnosticHandler>,
+    policy: Arc<dyn RoutingActivationPolicy>,
+    commands: mpsc::UnboundedSender<Command>,
+    /// whether the socket is secured by TLS
+    secure: bool,
+    machine: EntityConnection,
+    /// the origin of the machine clock
+    origin: Instant,
+    /// the activation type of the routing activated on this socket
+    active: RoutingActiveType,
+    alive_waiters: Vec<oneshot::Sender<bool>>,
+}
+
+impl Connection {
+    pub(crate) fn new(
+        id: u64,
+        config: Arc<EntityConfig>,
+        registry: Arc<Registry>,
+        handler: Arc<dyn DiagnosticHandler>,
+        policy: Arc<dyn RoutingActivationPolicy>,
+        commands: mpsc::UnboundedSender<Command>,
+        secure: bool,
+    ) -> Self {
+        let machine = EntityConnection::new(config.address, config.version, config.timing, 0);
+        Self {
+            id,
+            config,
+            registry,
+            handler,
+            policy,
+            commands,
+            secure,
+            machine,
+            origin: Instant::now(),
+            active: Default::default(),
+            alive_waiters: Default::default(),
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    #[inline]
+    pub(crate) fn config(&self) -> &EntityConfig {
+        &self.config
+    }
+
+    /// Remove the socket which is never served.
+    #[cfg(feature = "tls")]
+    #[inline]
+    pub(crate) fn discard(self) {
+        self.registry.remove(self.id);
+    }
+
+    pub(crate) async fn run<S>(mut self, stream: S, mut receiver: mpsc::UnboundedReceiver<Command>)
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (mut reader, writer) = tokio::io::split(stream);
+        let mut writer: Writer = Box::new(writer);
+        let mut decoder = MessageDecoder::new(self.config.max_data_size);
+        decoder.set_versions(&self.config.versions);
+        decoder.set_registry(self.config.payloads.clone());
+        let mut buffer = vec![0; SIZE_OF_BUFFER];
+        while let Some(deadline) = self.machine.poll_timeout() {
+            let deadline = self.origin + Duration::from_millis(deadline);
+            let ret = tokio::select! {
+                ret = reader.read(&mut buffer) => match ret {
+                    Ok(0) => {
+                        self.machine.close();
+                        Ok(())
+                    }
+                    Ok(size) => {
+                        decoder.extend(&buffer[..size]);
+                        self.process(&mut decoder, &mut writer).await
+                    }
+                    Err(e) => Err(Error::IoError(e)),
+                },
+                Some(command) = receiver.recv() => self.command(command, &mut writer).await,
+                _ = sleep_until(deadline) => {
+                    self.machine.handle_timeout(self.now());
+                    self.drive(&mut writer).await
+                }
+            };
+
+            if let Err(e) = ret {
+                rsutil::warn!("ISO 13400-2 - socket {} error: {}", self.id, e);
+                break;
+            }
+        }
+
+        self.alive_waiters.drain(..).for_each(|waiter| {
+            let _ = waiter.send(false);
+        });
+        self.registry.remove(self.id);
+        if let Some(tester) = self.machine.tester() {
+            self.handler.closed(tester).await;
+        }
+        let _ = writer.shutdown().await;
+    }
+
+    /// The current time of the machine clock.
+    #[inline]
+    fn now(&self) -> u64 {
+        self.origin.elapsed().as_millis() as u64
+    }
+
+    /// Handle all complete messages until the socket is finalized.
+    async fn process(
+        &mut self,
+        decoder: &mut MessageDecoder,
+        writer: &mut Writer,
+    ) -> Result<(), Error> {
+        while self.machine.state() != SocketState::Finalized {
+            match decoder.decode() {
+                Ok(Some(message)) => self.machine.handle_message(self.now(), message),
+                Ok(None) => break,
+                Err(e) => self.machine.handle_error(&e),
+            }
+            self.drive(writer).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Send the messages and handle the events of the machine.
+    async fn drive(&mut self, writer: &mut Writer) -> Result<(), Error> {
+        self.flush(writer).await?;
+        while let Some(event) = self.machine.poll_event() {
+            match event {
+                EntityEvent::RoutingRequest(req) => {
+                    let Activation { code, user_def } = self.routing_activation(&req).await;
+                    self.machine.respond_routing(self.now(), code, user_def)?;
+                    self.flush(writer).await?;
+                }
+                EntityEvent::Diagnostic(diag) => self.diagnostic(diag, writer).await?,
+                EntityEvent::Custom { payload_type, data } => {
+                    if let Some(tester) = self.machine.tester() {
+                        let handler = self.handler.clone();
+                        let responder = Responder {
+                            tester,
+                            commands: self.commands.clone(),
+                        };
+                        tokio::spawn(async move {
+                            handler.handle_custom(payload_type, data, responder).await
+                        });
+                    }
+                }
+                EntityEvent::Alive(_) => self.alive_waiters.drain(..).for_each(|waiter| {
+                    let _ = waiter.send(true);
+                }),
+                EntityEvent::AliveTimeout => {
+                    rsutil::info!(
+                        "ISO 13400-2 - no alive check response on socket {}",
+                        self.id
+                    )
+                }
+                EntityEvent::Inactive => {
+                    rsutil::info!("ISO 13400-2 - socket {} inactive", self.id)
+                }
+                EntityEvent::Closed => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Send the messages queued by the machine.
+    async fn flush(&mut self, writer: &mut Writer) -> Result<(), Error> {
+        while let Some(message) = self.machine.poll_transmit() {
+            let data: Vec<_> = message.into();
+            rsutil::trace!(
+                "ISO 13400-2 - socket {} sending: {}",
+                self.id,
+                hex::encode(&data)
+            );
+            writer.write_all(&data).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn routing_activation(&mut self, req: &request::RoutingActive) -> Activation {
+        let src_addr = req.src_addr;
+        if !self.config.is_allowed(src_addr) {
+            return ActiveCode::SourceAddressUnknown.into();
+        }
+        if matches!(req.active, RoutingActiveType::Reserved(_)) {
+            return ActiveCode::Unsupported.into();
+        }
+        if !self.secure && self.config.tls_required() {
+            return ActiveCode::TLSRequired.into();
+        }
+        // the socket is reserved for the pending test equipment already
+        if self.machine.state() != SocketState::Pending {
+            if let Some(code) = self.reserve_socket(src_addr).await {
+                return code.into();
+            }
+        }
+
+        let activation = self.policy.activate(req, self.secure).await;
+        match activation.code {
+            ActiveCode::Success => {
+                rsutil::debug!(
+                    "ISO 13400-2 - routing activated for {} on socket {}",
+                    src_addr,
+                    self.id
+                );
+                self.active = req.active;
+            }
+            code if !code.closes_socket() => {
+                rsutil::debug!(
+                    "ISO 13400-2 - routing activation of {} pending on socket {}: {:?}",
+                    src_addr,
+                    self.id,
+                    code
+                );
+            }
+            code => {
+                rsutil::debug!(
+                    "ISO 13400-2 - routing activation of {} refused: {:?}",
+                    src_addr,
+                    code
+                );
+                return activation;
+            }
+        }
+        self.registry.register(self.id, src_addr);
+
+        activation
+    }
+
+    /// Table 49 socket handling, return the code if no socket is available for the tester.
+    async fn reserve_socket(&mut self, src_addr: LogicAddress) -> Option<ActiveCode> {
+        if let Some(other) = self.registry.find(src_addr, self.id) {
+            if !self.registry.alive_check(vec![other]).await.is_empty() {
+                return Some(ActiveCode::SocketInvalid);
+            }
+            self.registry.close(other);
+        }
+
+        let registered = self.registry.registered(self.id);
+        if registered.len() >= self.config.max_sockets as usize {
+            let alive = self.registry.alive_check(registered.clone()).await;
+            registered
+                .iter()
+                .filter(|id| !alive.contains(id))
+                .for_each(|&id| self.registry.close(id));
+            if alive.len() >= self.config.max_sockets as usize {
+                return Some(ActiveCode::Activated);
+            }
+        }
+
+        None
+    }
+
+    /// Acknowledge the diagnostic message of the test equipment and pass it to the handler.
+    async fn diagnostic(&mut self, diag: Diagnostic, writer: &mut Writer) -> Result<(), Error> {
+        let (src_addr, dst_addr) = (diag.src_addr, diag.dst_addr);
+        if let Err(code) = self.config.firewall.check(src_addr, dst_addr, self.active) {
+            self.machine.acknowledge(&diag, Err(code));
+            return self.flush(writer).await;
+        }
+
+        let processing_time = Duration::from_millis(self.config.timing.processing_time);
+        let ret = match timeout(processing_time, self.handler.validate(&diag)).await {
+            Ok(ret) => ret,
+            Err(_) => {
+                rsutil::warn!("ISO 13400-2 - diagnostic message validation timeout");
+                Err(DiagnosticNegativeCode::TargetUnreachable)
+            }
+        };
+        if let Err(code) = ret {
+            rsutil::debug!("ISO 13400-2 - diagnostic message rejected: {}", code);
+        }
+        self.machine.acknowledge(&diag, ret);
+        if let Err(e) = self.flush(writer).await {
+            if ret.is_ok() {
+                self.handler.discard(&diag).await;
+            }
+            return Err(e);
+        }
+
+        if ret.is_ok() {
+            let handler = self.handler.clone();
+            let responder = Responder {
+                tester: src_addr,
+                commands: self.commands.clone(),
+            };
+            tokio::spawn(async move { handler.handle(diag, responder).await });
+        }
+
+        Ok(())
+    }
+
+    async fn command(&mut self, command: Command, writer: &mut Writer) -> Result<(), Error> {
+        match command {
+            Command::Send(payload) => self.send(writer, payload).await,
+            Command::AliveCheck(waiter) => {
+                // nothing is sent if the alive check is outstanding
+                self.machine.alive_check(self.now());
+                self.alive_waiters.push(waiter);
+                self.flush(writer).await
+            }
+            Command::Close => {
+                self.machine.close();
+                Ok(())
+            }
+        }
+    }
+
+    async fn send(&self, writer: &mut Writer, payload: Payload) -> Result<(), Error> {
+        let data: Vec<_> = Message {
+            version: self.config.version,
+            payload,
+        }
+        .into();
+        rsutil::trace!(
+            "ISO 13400-2 - socket {} sending: {}",
+            self.id,
+            hex::encode(&data)
+        );
+        writer.write_all(&data).await?;
+
+        Ok(())
+    }
+}
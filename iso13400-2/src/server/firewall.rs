@@ -0,0 +1,189 @@
+use crate::{DiagnosticNegativeCode, LogicAddress, RoutingActiveType};
+use std::ops::RangeInclusive;
+
+/// The source of a diagnostic message, classified by the client address ranges of Table 13.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SourceClass {
+    /// all test equipment
+    Any,
+    /// 0x0E00 ~ 0x0FFF
+    Client,
+    /// 0x0E00 ~ 0x0E7F external legislated diagnostics test equipment
+    Legislated,
+    /// 0x0E80 ~ 0x0EFF external vehicle-manufacturer-/aftermarket-enhanced diagnostics test equipment
+    Enhanced,
+    /// 0x0F00 ~ 0x0F7F internal data collection/on-board diagnostic equipment
+    Internal,
+    /// 0x0F80 ~ 0x0FFF external prolonged data collection equipment
+    Prolonged,
+    Address(LogicAddress),
+}
+
+impl SourceClass {
+    pub fn matches(&self, addr: LogicAddress) -> bool {
+        let value = u16::from(addr);
+        match self {
+            Self::Any => true,
+            Self::Client => matches!(addr, LogicAddress::Client(_)),
+            Self::Legislated => (0x0E00..=0x0E7F).contains(&value),
+            Self::Enhanced => (0x0E80..=0x0EFF).contains(&value),
+            Self::Internal => (0x0F00..=0x0F7F).contains(&value),
+            Self::Prolonged => (0x0F80..=0x0FFF).contains(&value),
+            Self::Address(v) => *v == addr,
+        }
+    }
+}
+
+/// The target of a diagnostic message.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Target {
+    Any,
+    /// 0xE400 ~ 0xEFFF vehicle-manufacturer-defined functional group logical addresses
+    Functional,
+    Address(LogicAddress),
+    Range(RangeInclusive<u16>),
+}
+
+impl Target {
+    pub fn matches(&self, addr: LogicAddress) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Functional => matches!(addr, LogicAddress::VMSpecificFunctional(_)),
+            Self::Address(v) => *v == addr,
+            Self::Range(v) => v.contains(&u16::from(addr)),
+        }
+    }
+}
+
+/// The action of a matched rule.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Action {
+    Allow,
+    /// reject the message with the negative acknowledge code
+    Deny(DiagnosticNegativeCode),
+}
+
+/// A rule of the [`Firewall`].
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub source: SourceClass,
+    pub target: Target,
+    /// the routing activation types of the socket matched by the rule, all types if empty
+    pub activations: Vec<RoutingActiveType>,
+    pub action: Action,
+}
+
+impl Rule {
+    #[inline]
+    pub fn allow(source: SourceClass, target: Target) -> Self {
+        Self {
+            source,
+            target,
+            activations: Default::default(),
+            action: Action::Allow,
+        }
+    }
+
+    #[inline]
+    pub fn deny(source: SourceClass, target: Target, code: DiagnosticNegativeCode) -> Self {
+        Self {
+            source,
+            target,
+            activations: Default::default(),
+            action: Action::Deny(code),
+        }
+    }
+
+    /// Only match the sockets activated by one of the types.
+    #[inline]
+    pub fn with_activations(mut self, activations: Vec<RoutingActiveType>) -> Self {
+        self.activations = activations;
+        self
+    }
+
+    pub fn matches(
+        &self,
+        src_addr: LogicAddress,
+        dst_addr: LogicAddress,
+        active: RoutingActiveType,
+    ) -> bool {
+        self.source.matches(src_addr)
+            && self.target.matches(dst_addr)
+            && (self.activations.is_empty() || self.activations.contains(&active))
+    }
+}
+
+/// The filter of the diagnostic messages routed by the entity.
+///
+/// The rules are checked in order, the first matched rule decides the message
+/// and the default action decides the message without matched rule.
+/// The filter is checked before [`DiagnosticHandler::validate`](super::DiagnosticHandler::validate).
+#[derive(Debug, Clone)]
+pub struct Firewall {
+    pub rules: Vec<Rule>,
+    pub default: Action,
+}
+
+impl Default for Firewall {
+    #[inline]
+    fn default() -> Self {
+        Self::new(Action::Allow)
+    }
+}
+
+impl Firewall {
+    #[inline]
+    pub fn new(default: Action) -> Self {
+        Self {
+            rules: Default::default(),
+            default,
+        }
+    }
+
+    #[inline]
+    pub fn push(&mut self, rule: Rule) -> &mut Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Check the diagnostic message from `src_addr` to `dst_addr`
+    /// on the socket activated by `active`.
+    pub fn check(
+        &self,
+        src_addr: LogicAddress,
+        dst_addr: LogicAddress,
+        active: RoutingActiveType,
+    ) -> Result<(), DiagnosticNegativeCode> {
+        let (index, action) = self
+            .rules
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| rule.matches(src_addr, dst_addr, active))
+            .map(|(i, rule)| (Some(i), rule.action))
+            .unwrap_or((None, self.default));
+
+        match action {
+            Action::Allow => {
+                rsutil::debug!(
+                    "ISO 13400-2 - firewall allowed {} -> {}({:?}) by rule {:?}",
+                    src_addr,
+                    dst_addr,
+                    active,
+                    index
+                );
+                Ok(())
+            }
+            Action::Deny(code) => {
+                rsutil::info!(
+                    "ISO 13400-2 - firewall denied {} -> {}({:?}) by rule {:?}: {}",
+                    src_addr,
+                    dst_addr,
+                    active,
+                    index,
+                    code
+                );
+                Err(code)
+            }
+        }
+    }
+}
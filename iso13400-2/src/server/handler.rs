@@ -0,0 +1,69 @@
+use super::Command;
+use crate::{error::Error, Diagnostic, DiagnosticNegativeCode, LogicAddress, Payload};
+use tokio::sync::mpsc;
+
+/// The user handler of the diagnostic messages received by the entity.
+#[async_trait::async_trait]
+pub trait DiagnosticHandler: Send + Sync {
+    /// Check the diagnostic message before acknowledging.
+    ///
+    /// The positive acknowledge is sent when `Ok`, otherwise the negative acknowledge with the code.
+    async fn validate(&self, diag: &Diagnostic) -> Result<(), DiagnosticNegativeCode>;
+
+    /// Handle the acknowledged diagnostic message.
+    ///
+    /// The responses are sent by the responder, which can be kept for responding later.
+    async fn handle(&self, diag: Diagnostic, responder: Responder);
+
+    /// The validated diagnostic message is not handled because the positive acknowledge
+    /// isn't sent, e.g. the socket is closed. Release what `validate` reserved for it.
+    async fn discard(&self, diag: &Diagnostic) {
+        let _ = diag;
+    }
+
+    /// The TCP_DATA socket of `tester` is closed. Release what is reserved for it.
+    async fn closed(&self, tester: LogicAddress) {
+        let _ = tester;
+    }
+
+    /// Handle the manufacturer-specific payload registered in
+    /// [`EntityConfig::payloads`](super::EntityConfig::payloads), ignored by default.
+    async fn handle_custom(&self, payload_type: u16, data: Vec<u8>, responder: Responder) {
+        let _ = (data, responder);
+        rsutil::debug!("ISO 13400-2 - ignore payload {:#06X}", payload_type);
+    }
+}
+
+/// Send the diagnostic responses to the TCP_DATA socket the request came from.
+#[derive(Debug, Clone)]
+pub struct Responder {
+    pub(crate) tester: LogicAddress,
+    pub(crate) commands: mpsc::UnboundedSender<Command>,
+}
+
+impl Responder {
+    /// The logical address of the test equipment.
+    #[inline]
+    pub fn tester(&self) -> LogicAddress {
+        self.tester
+    }
+
+    /// Send the diagnostic response from `src_addr`.
+    pub fn respond(&self, src_addr: LogicAddress, data: Vec<u8>) -> Result<(), Error> {
+        let diag = Diagnostic::new(src_addr, self.tester, data);
+        self.send(Payload::Diagnostic(diag))
+    }
+
+    /// Send any payload to the TCP_DATA socket.
+    pub fn send(&self, payload: Payload) -> Result<(), Error> {
+        self.commands
+            .send(Command::Send(payload))
+            .map_err(|_| Error::ConnectionClosed)
+    }
+
+    /// Whether the TCP_DATA socket is closed.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.commands.is_closed()
+    }
+}
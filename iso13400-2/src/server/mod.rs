@@ -0,0 +1,523 @@
+//! DoIP entity(server) with the UDP and TCP_DATA socket handling.
+
+mod config;
+mod connection;
+mod firewall;
+mod handler;
+mod policy;
+mod status;
+#[cfg(feature = "tls")]
+mod tls;
+
+#[cfg(feature = "tls")]
+pub use self::tls::TlsConfig;
+pub use self::{
+    config::EntityConfig,
+    firewall::{Action, Firewall, Rule, SourceClass, Target},
+    handler::{DiagnosticHandler, Responder},
+    policy::{AcceptAll, Activation, RoutingActivationPolicy},
+    status::{
+        aggregate_power_mode, LiveStatus, PowerModeSource, SocketStatus, StatusProvider,
+        SubNodePowerModes,
+    },
+};
+
+use crate::{
+    codec::MessageDecoder, error::Error, response, socket, HeaderNegativeCode, LogicAddress,
+    Message, Payload, Version,
+};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    net::{TcpListener, UdpSocket},
+    sync::{mpsc, oneshot},
+    task::{JoinHandle, JoinSet},
+};
+
+/// max. size of a UDP datagram
+const SIZE_OF_DATAGRAM: usize = 0xFFFF;
+
+/// A TCP_DATA listener, the accepted sockets are secured if `acceptor` is present.
+struct Listener {
+    inner: TcpListener,
+    #[cfg(feature = "tls")]
+    acceptor: Option<tokio_rustls::TlsAcceptor>,
+}
+
+impl Listener {
+    #[inline]
+    fn secure(&self) -> bool {
+        #[cfg(feature = "tls")]
+        return self.acceptor.is_some();
+        #[cfg(not(feature = "tls"))]
+        false
+    }
+}
+
+/// Commands sent to the task of a TCP_DATA socket.
+#[derive(Debug)]
+pub(crate) enum Command {
+    Send(Payload),
+    AliveCheck(oneshot::Sender<bool>),
+    Close,
+}
+
+#[derive(Debug)]
+struct Handle {
+    peer: SocketAddr,
+    tester: Option<LogicAddress>,
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+/// All TCP_DATA sockets of the entity.
+#[derive(Debug, Default)]
+pub(crate) struct Registry {
+    next: AtomicU64,
+    connections: Mutex<HashMap<u64, Handle>>,
+}
+
+impl Registry {
+    fn add(&self, peer: SocketAddr, commands: mpsc::UnboundedSender<Command>) -> u64 {
+        let id = self.next.fetch_add(1, Ordering::Relaxed);
+        self.connections.lock().unwrap().insert(
+            id,
+            Handle {
+                peer,
+                tester: None,
+                commands,
+            },
+        );
+
+        id
+    }
+
+    pub(crate) fn remove(&self, id: u64) {
+        if let Some(handle) = self.connections.lock().unwrap().remove(&id) {
+            rsutil::debug!("ISO 13400-2 - socket of {} closed", handle.peer);
+        }
+    }
+
+    /// The count of the open TCP_DATA sockets.
+    pub(crate) fn len(&self) -> usize {
+        self.connections.lock().unwrap().len()
+    }
+
+    pub(crate) fn register(&self, id: u64, tester: LogicAddress) {
+        if let Some(handle) = self.connections.lock().unwrap().get_mut(&id) {
+            handle.tester = Some(tester);
+        }
+    }
+
+    /// The socket with routing activated for the tester except the socket `id`.
+    pub(crate) fn find(&self, tester: LogicAddress, id: u64) -> Option<u64> {
+        self.connections
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(&k, v)| k != id && v.tester == Some(tester))
+            .map(|(&k, _)| k)
+    }
+
+    /// All sockets with routing activated except the socket `id`.
+    pub(crate) fn registered(&self, id: u64) -> Vec<u64> {
+        self.connections
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(&k, v)| k != id && v.tester.is_some())
+            .map(|(&k, _)| k)
+            .collect()
+    }
+
+    pub(crate) fn close(&self, id: u64) {
+        if let Some(handle) = self.connections.lock().unwrap().get(&id) {
+            let _ = handle.commands.send(Command::Close);
+        }
+    }
+
+    fn close_all(&self) {
+        for handle in self.connections.lock().unwrap().values() {
+            let _ = handle.commands.send(Command::Close);
+        }
+    }
+
+    /// Run the alive check on the sockets, return the sockets which are alive.
+    pub(crate) async fn alive_check(&self, ids: Vec<u64>) -> Vec<u64> {
+        let receivers = {
+            let connections = self.connections.lock().unwrap();
+            ids.into_iter()
+                .filter_map(|id| {
+                    let handle = connections.get(&id)?;
+                    let (sender, receiver) = oneshot::channel();
+                    handle.commands.send(Command::AliveCheck(sender)).ok()?;
+                    Some((id, receiver))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut results = Vec::new();
+        for (id, receiver) in receivers {
+            if let Ok(true) = receiver.await {
+                results.push(id);
+            }
+        }
+
+        results
+    }
+}
+
+/// A DoIP entity which answers the UDP requests and serves the TCP_DATA sockets.
+pub struct DoIpEntity {
+    config: Arc<EntityConfig>,
+    registry: Arc<Registry>,
+    tcp_addr: SocketAddr,
+    udp_addr: SocketAddr,
+    #[cfg(feature = "tls")]
+    tls_addr: Option<SocketAddr>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl DoIpEntity {
+    /// Bind the TCP and UDP sockets and start serving.
+    ///
+    /// The routing of all test equipment passing the entity checks is activated.
+    #[inline]
+    pub async fn bind<H>(config: EntityConfig, handler: H) -> Result<Self, Error>
+    where
+        H: DiagnosticHandler + 'static,
+    {
+        Self::bind_with_policy(config, handler, AcceptAll).await
+    }
+
+    /// Bind the TCP and UDP sockets and start serving,
+    /// the routing activation requests are decided by the policy.
+    #[inline]
+    pub async fn bind_with_policy<H, P>(
+        config: EntityConfig,
+        handler: H,
+        policy: P,
+    ) -> Result<Self, Error>
+    where
+        H: DiagnosticHandler + 'static,
+        P: RoutingActivationPolicy + 'static,
+    {
+        Self::bind_with_status(config, handler, policy, LiveStatus::default()).await
+    }
+
+    /// Bind the TCP and UDP sockets and start serving, the routing activation requests
+    /// are decided by the policy and the status requests are answered by the provider.
+    pub async fn bind_with_status<H, P, S>(
+        config: EntityConfig,
+        handler: H,
+        policy: P,
+        status: S,
+    ) -> Result<Self, Error>
+    where
+        H: DiagnosticHandler + 'static,
+        P: RoutingActivationPolicy + 'static,
+        S: StatusProvider + 'static,
+    {
+        if config.tls_required() && config.version != Version::ISO13400_2_2019 {
+            return Err(Error::InvalidParam(format!(
+                "TLS is not supported by version {:?}",
+                config.version
+            )));
+        }
+
+        let listener = socket::bind_tcp(config.tcp_addr)?;
+        let tcp_addr = listener.local_addr()?;
+        let socket = socket::bind_udp(config.udp_addr)?;
+        let udp_addr = socket.local_addr()?;
+        rsutil::info!(
+            "ISO 13400-2 - entity {} listening on {}(TCP) and {}(UDP)",
+            config.address,
+            tcp_addr,
+            udp_addr
+        );
+
+        let config = Arc::new(config);
+        let registry = Arc::new(Registry::default());
+        let handler: Arc<dyn DiagnosticHandler> = Arc::new(handler);
+        let policy: Arc<dyn RoutingActivationPolicy> = Arc::new(policy);
+        let socket = Arc::new(socket);
+        let mut tasks = vec![tokio::spawn(udp_loop(
+            socket.clone(),
+            config.clone(),
+            registry.clone(),
+            Arc::new(status),
+        ))];
+        #[cfg(feature = "tls")]
+        let tls_addr = match &config.tls {
+            Some(tls) => {
+                let listener = socket::bind_tcp(tls.addr)?;
+                let tls_addr = listener.local_addr()?;
+                rsutil::info!("ISO 13400-2 - entity listening on {}(TLS)", tls_addr);
+                let listener = Listener {
+                    inner: listener,
+                    acceptor: Some(tokio_rustls::TlsAcceptor::from(tls.config.clone())),
+                };
+                tasks.push(tokio::spawn(accept_loop(
+                    listener,
+                    config.clone(),
+                    registry.clone(),
+                    handler.clone(),
+                    policy.clone(),
+                )));
+                Some(tls_addr)
+            }
+            None => None,
+        };
+        let listener = Listener {
+            inner: listener,
+            #[cfg(feature = "tls")]
+            acceptor: None,
+        };
+        tasks.push(tokio::spawn(accept_loop(
+            listener,
+            config.clone(),
+            registry.clone(),
+            handler,
+            policy,
+        )));
+        if let Some(target) = config.announce_addr {
+            tasks.push(tokio::spawn(announce(socket, config.clone(), target)));
+        }
+
+        Ok(Self {
+            config,
+            registry,
+            tcp_addr,
+            udp_addr,
+            #[cfg(feature = "tls")]
+            tls_addr,
+            tasks,
+        })
+    }
+
+    #[inline]
+    pub fn config(&self) -> &EntityConfig {
+        &self.config
+    }
+
+    #[inline]
+    pub fn tcp_addr(&self) -> SocketAddr {
+        self.tcp_addr
+    }
+
+    #[inline]
+    pub fn udp_addr(&self) -> SocketAddr {
+        self.udp_addr
+    }
+
+    /// The address of the secured TCP_DATA listener.
+    #[cfg(feature = "tls")]
+    #[inline]
+    pub fn tls_addr(&self) -> Option<SocketAddr> {
+        self.tls_addr
+    }
+
+    /// The count of the open TCP_DATA sockets.
+    #[inline]
+    pub fn open_sockets(&self) -> usize {
+        self.registry.len()
+    }
+
+    /// Close all sockets and stop serving.
+    pub fn stop(self) {}
+}
+
+impl Drop for DoIpEntity {
+    fn drop(&mut self) {
+        self.registry.close_all();
+        self.tasks.iter().for_each(|task| task.abort());
+    }
+}
+
+async fn accept_loop(
+    listener: Listener,
+    config: Arc<EntityConfig>,
+    registry: Arc<Registry>,
+    handler: Arc<dyn DiagnosticHandler>,
+    policy: Arc<dyn RoutingActivationPolicy>,
+) {
+    let mut connections = JoinSet::new();
+    loop {
+        tokio::select! {
+            ret = listener.inner.accept() => {
+                let (stream, peer) = match ret {
+                    Ok(v) => v,
+                    Err(e) => {
+                        rsutil::warn!("ISO 13400-2 - failed to accept: {}", e);
+                        continue;
+                    }
+                };
+                // one more socket than the max. is accepted for the alive check procedure
+                if registry.len() > config.max_sockets as usize {
+                    rsutil::warn!("ISO 13400-2 - no socket available for {}", peer);
+                    continue;
+                }
+                let peer = socket::canonical(peer);
+                let _ = stream.set_nodelay(true);
+                rsutil::debug!("ISO 13400-2 - socket of {} accepted", peer);
+
+                let (sender, receiver) = mpsc::unbounded_channel();
+                let id = registry.add(peer, sender.clone());
+                let connection = connection::Connection::new(
+                    id,
+                    config.clone(),
+                    registry.clone(),
+                    handler.clone(),
+                    policy.clone(),
+                    sender,
+                    listener.secure(),
+                );
+                #[cfg(feature = "tls")]
+                if let Some(acceptor) = listener.acceptor.clone() {
+                    connections.spawn(tls::serve(acceptor, connection, stream, receiver));
+                    continue;
+                }
+                connections.spawn(connection.run(stream, receiver));
+            }
+            Some(_) = connections.join_next() => {}
+        }
+    }
+}
+
+async fn udp_loop(
+    socket: Arc<UdpSocket>,
+    config: Arc<EntityConfig>,
+    registry: Arc<Registry>,
+    status: Arc<dyn StatusProvider>,
+) {
+    let mut buffer = vec![0; SIZE_OF_DATAGRAM];
+    loop {
+        let (size, addr) = match socket.recv_from(&mut buffer).await {
+            Ok(v) => v,
+            Err(e) => {
+                rsutil::warn!("ISO 13400-2 - UDP receive error: {}", e);
+                continue;
+            }
+        };
+
+        let mut decoder = MessageDecoder::new(config.max_data_size);
+        decoder.set_versions(&config.versions);
+        decoder.set_registry(config.payloads.clone());
+        decoder.extend(&buffer[..size]);
+        let payload = match decoder.decode() {
+            Ok(Some(message)) => {
+                udp_response(&config, &registry, status.as_ref(), message.payload).await
+            }
+            Ok(None) => Some(header_negative(HeaderNegativeCode::InvalidPayloadLength)),
+            Err(e) => match e.header_negative() {
+                Some((nack, _)) => Some(Payload::RespHeaderNegative(nack)),
+                None => {
+                    rsutil::warn!("ISO 13400-2 - invalid datagram from {}: {}", addr, e);
+                    None
+                }
+            },
+        };
+
+        if let Some(payload) = payload {
+            let data: Vec<_> = Message {
+                version: config.version,
+                payload,
+            }
+            .into();
+            if let Err(e) = socket.send_to(&data, addr).await {
+                rsutil::warn!("ISO 13400-2 - failed to respond to {}: {}", addr, e);
+            }
+        }
+    }
+}
+
+async fn udp_response(
+    config: &EntityConfig,
+    registry: &Registry,
+    status: &dyn StatusProvider,
+    payload: Payload,
+) -> Option<Payload> {
+    match payload {
+        Payload::ReqVehicleId(_) => Some(vehicle_id(config)),
+        Payload::ReqVehicleWithEid(v) if v.eid == config.eid => Some(vehicle_id(config)),
+        Payload::ReqVehicleWithVIN(v) if v.vin == config.vin => Some(vehicle_id(config)),
+        Payload::ReqEntityStatus(_) => {
+            let sockets = SocketStatus {
+                max_sockets: config.max_sockets,
+                open_sockets: registry.len().min(u8::MAX as usize) as u8,
+            };
+            let mut resp = status.entity_status(config, sockets).await;
+            // the max. data size is not defined in ISO 13400-2:2010
+            if config.version == Version::ISO13400_2_2010 {
+                resp.max_data_size = None;
+            }
+            Some(Payload::RespEntityStatus(resp))
+        }
+        Payload::ReqDiagPowerMode(_) => Some(Payload::RespDiagPowerMode(
+            response::DiagnosticPowerMode::new(status.power_mode(config).await),
+        )),
+        payload => {
+            rsutil::debug!(
+                "ISO 13400-2 - ignore UDP payload: {:?}",
+                payload.payload_type()
+            );
+            None
+        }
+    }
+}
+
+fn vehicle_id(config: &EntityConfig) -> Payload {
+    Payload::RespVehicleId(response::VehicleID {
+        vin: config.vin.clone(),
+        address: config.address,
+        eid: config.eid,
+        gid: config.gid,
+        further_act: config.further_act,
+        sync_status: config
+            .sync_status
+            .filter(|_| config.version != Version::ISO13400_2_2010),
+    })
+}
+
+#[inline]
+fn header_negative(code: HeaderNegativeCode) -> Payload {
+    Payload::RespHeaderNegative(response::HeaderNegative::new(code))
+}
+
+async fn announce(socket: Arc<UdpSocket>, config: Arc<EntityConfig>, target: SocketAddr) {
+    let data: Vec<_> = Message {
+        version: config.version,
+        payload: vehicle_id(&config),
+    }
+    .into();
+    let target = match socket.local_addr() {
+        Ok(local) => socket::target(local, target),
+        Err(_) => target,
+    };
+    let timing = config.timing;
+    tokio::time::sleep(Duration::from_millis(random_delay(timing.announce_wait))).await;
+    for i in 0..timing.announce_num {
+        if i > 0 {
+            tokio::time::sleep(Duration::from_millis(timing.announce_interval)).await;
+        }
+        if let Err(e) = socket.send_to(&data, target).await {
+            rsutil::warn!("ISO 13400-2 - failed to announce: {}", e);
+        }
+    }
+}
+
+/// A_DoIP_Announce_Wait, a random delay within `0..=max` to avoid the announcements
+/// of all entities at the same time.
+fn random_delay(max: u64) -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|v| v.subsec_nanos())
+        .unwrap_or_default();
+    nanos as u64 % (max + 1)
+}
@@ -0,0 +1,53 @@
+use crate::{request, ActiveCode};
+
+/// The decision of a routing activation request.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Activation {
+    /// the response code, the routing is only activated by [`ActiveCode::Success`]
+    pub code: ActiveCode,
+    /// the OEM specific data of the response
+    pub user_def: Option<u32>,
+}
+
+impl Activation {
+    #[inline]
+    pub fn new(code: ActiveCode, user_def: Option<u32>) -> Self {
+        Self { code, user_def }
+    }
+}
+
+impl From<ActiveCode> for Activation {
+    #[inline]
+    fn from(code: ActiveCode) -> Self {
+        Self::new(code, None)
+    }
+}
+
+/// The user policy of the routing activation requests received by the entity.
+///
+/// The policy is asked after the whitelist, TLS and socket handling are passed.
+/// [`ActiveCode::WithoutAuth`], [`ActiveCode::NeedConfirm`] and
+/// [`ActiveCode::VMSpecific`] keep the socket reserved for the test equipment
+/// and the policy is asked again by the next request of it.
+#[async_trait::async_trait]
+pub trait RoutingActivationPolicy: Send + Sync {
+    /// Decide the routing activation with the source address, the activation type
+    /// (e.g. [`RoutingActiveType::CentralSecurity`](crate::RoutingActiveType::CentralSecurity))
+    /// and the OEM specific data of the request.
+    ///
+    /// `secure` is `true` if the socket is secured by TLS. The socket is blocked until
+    /// the decision, a confirmation which takes longer than A_DoIP_Ctrl of the test equipment
+    /// should be answered by [`ActiveCode::NeedConfirm`] and decided by the retried request.
+    async fn activate(&self, req: &request::RoutingActive, secure: bool) -> Activation;
+}
+
+/// Activate the routing of all requests which pass the entity checks.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct AcceptAll;
+
+#[async_trait::async_trait]
+impl RoutingActivationPolicy for AcceptAll {
+    async fn activate(&self, _: &request::RoutingActive, _: bool) -> Activation {
+        ActiveCode::Success.into()
+    }
+}
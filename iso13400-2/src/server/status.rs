@@ -0,0 +1,148 @@
+use super::EntityConfig;
+use crate::{response, LogicAddress, PowerMode};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// The live socket numbers of the entity given to [`StatusProvider`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SocketStatus {
+    /// max. concurrent TCP_DATA sockets
+    pub max_sockets: u8,
+    /// currently open TCP_DATA sockets
+    pub open_sockets: u8,
+}
+
+/// The user provider of the DoIP entity status and diagnostic power mode responses.
+///
+/// The max. data size of the status is dropped by the entity for ISO 13400-2:2010.
+#[async_trait::async_trait]
+pub trait StatusProvider: Send + Sync {
+    /// Table 11 — DoIP entity status response
+    async fn entity_status(
+        &self,
+        config: &EntityConfig,
+        sockets: SocketStatus,
+    ) -> response::EntityStatus;
+
+    /// Table 9 — Diagnostic power mode information response
+    async fn power_mode(&self, config: &EntityConfig) -> PowerMode;
+}
+
+/// The source of the diagnostic power mode of [`LiveStatus`].
+pub trait PowerModeSource: Send + Sync {
+    fn power_mode(&self) -> PowerMode;
+}
+
+/// A fixed power mode.
+impl PowerModeSource for PowerMode {
+    #[inline]
+    fn power_mode(&self) -> PowerMode {
+        *self
+    }
+}
+
+impl<F> PowerModeSource for F
+where
+    F: Fn() -> PowerMode + Send + Sync,
+{
+    #[inline]
+    fn power_mode(&self) -> PowerMode {
+        self()
+    }
+}
+
+/// The power modes of the sub-nodes behind a gateway(e.g. the CAN sub-networks),
+/// updated by the owner and aggregated by [`aggregate_power_mode`].
+#[derive(Debug, Clone, Default)]
+pub struct SubNodePowerModes {
+    modes: Arc<Mutex<HashMap<LogicAddress, PowerMode>>>,
+}
+
+impl SubNodePowerModes {
+    pub fn set(&self, node: LogicAddress, mode: PowerMode) {
+        self.modes.lock().unwrap().insert(node, mode);
+    }
+
+    pub fn remove(&self, node: LogicAddress) {
+        self.modes.lock().unwrap().remove(&node);
+    }
+
+    pub fn get(&self, node: LogicAddress) -> Option<PowerMode> {
+        self.modes.lock().unwrap().get(&node).copied()
+    }
+}
+
+impl PowerModeSource for SubNodePowerModes {
+    fn power_mode(&self) -> PowerMode {
+        aggregate_power_mode(self.modes.lock().unwrap().values().copied())
+    }
+}
+
+/// The power mode of a gateway from the power modes of its sub-nodes.
+///
+/// The gateway is only ready if all sub-nodes supporting the power mode are ready,
+/// and the power mode is not supported if no sub-node supports it.
+pub fn aggregate_power_mode<I>(modes: I) -> PowerMode
+where
+    I: IntoIterator<Item = PowerMode>,
+{
+    let mut result = PowerMode::NotSupported;
+    for mode in modes {
+        match mode {
+            PowerMode::NotSupported => {}
+            PowerMode::Ready => {
+                if result == PowerMode::NotSupported {
+                    result = PowerMode::Ready;
+                }
+            }
+            // a reserved value is not trusted as ready
+            PowerMode::NotReady | PowerMode::Reserved(_) => return PowerMode::NotReady,
+        }
+    }
+
+    result
+}
+
+/// The status from the live socket counts and [`EntityConfig`],
+/// and the power mode from the source or [`EntityConfig::power_mode`] if none.
+#[derive(Default)]
+pub struct LiveStatus {
+    power_mode: Option<Box<dyn PowerModeSource>>,
+}
+
+impl LiveStatus {
+    #[inline]
+    pub fn with_power_mode<P>(source: P) -> Self
+    where
+        P: PowerModeSource + 'static,
+    {
+        Self {
+            power_mode: Some(Box::new(source)),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl StatusProvider for LiveStatus {
+    async fn entity_status(
+        &self,
+        config: &EntityConfig,
+        sockets: SocketStatus,
+    ) -> response::EntityStatus {
+        response::EntityStatus::new(
+            config.node_type,
+            sockets.max_sockets,
+            sockets.open_sockets,
+            Some(config.max_data_size),
+        )
+    }
+
+    async fn power_mode(&self, config: &EntityConfig) -> PowerMode {
+        match &self.power_mode {
+            Some(source) => source.power_mode(),
+            None => config.power_mode,
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use super::{connection::Connection, Command};
+use crate::constants::*;
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{net::TcpStream, sync::mpsc};
+use tokio_rustls::TlsAcceptor;
+
+/// The secured TCP_DATA socket configuration of the entity.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    /// `0.0.0.0:TLS_TCP_SERVER_PORT` by default
+    pub addr: SocketAddr,
+    pub config: Arc<rustls::ServerConfig>,
+    /// the routing activation on the unsecured socket is denied with
+    /// [`ActiveCode::TLSRequired`](crate::ActiveCode::TLSRequired) if `true`
+    pub required: bool,
+}
+
+impl TlsConfig {
+    pub fn new(config: Arc<rustls::ServerConfig>) -> Self {
+        Self {
+            addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), TLS_TCP_SERVER_PORT),
+            config,
+            required: false,
+        }
+    }
+}
+
+/// Run the TLS handshake within T_TCP_Initial_Inactivity, then serve the connection.
+pub(crate) async fn serve(
+    acceptor: TlsAcceptor,
+    connection: Connection,
+    stream: TcpStream,
+    receiver: mpsc::UnboundedReceiver<Command>,
+) {
+    let timeout = Duration::from_millis(connection.config().timing.initial_inactivity);
+    match tokio::time::timeout(timeout, acceptor.accept(stream)).await {
+        Ok(Ok(stream)) => connection.run(stream, receiver).await,
+        Ok(Err(e)) => {
+            rsutil::warn!("ISO 13400-2 - TLS handshake failed: {}", e);
+            connection.discard();
+        }
+        Err(_) => {
+            rsutil::warn!("ISO 13400-2 - TLS handshake timeout");
+            connection.discard();
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Dual-stack helpers of the UDP and TCP sockets.
+//!
+//! The sockets bound to the unspecified IPv6 address `[::]` accept IPv4 too,
+//! the IPv4 peers are seen as IPv4-mapped IPv6 addresses by them.
+
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{io, net::SocketAddr};
+use tokio::net::{TcpListener, UdpSocket};
+
+/// backlog of the TCP listener
+const SIZE_OF_BACKLOG: i32 = 1024;
+
+fn socket(addr: SocketAddr, ty: Type, protocol: Protocol) -> io::Result<Socket> {
+    let socket = Socket::new(Domain::for_address(addr), ty, Some(protocol))?;
+    if addr.is_ipv6() && addr.ip().is_unspecified() {
+        socket.set_only_v6(false)?;
+    }
+    socket.set_nonblocking(true)?;
+
+    Ok(socket)
+}
+
+/// Bind the UDP socket with broadcast enabled.
+pub(crate) fn bind_udp(addr: SocketAddr) -> io::Result<UdpSocket> {
+    let socket = socket(addr, Type::DGRAM, Protocol::UDP)?;
+    socket.set_broadcast(true)?;
+    socket.bind(&addr.into())?;
+
+    UdpSocket::from_std(socket.into())
+}
+
+pub(crate) fn bind_tcp(addr: SocketAddr) -> io::Result<TcpListener> {
+    let socket = socket(addr, Type::STREAM, Protocol::TCP)?;
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(SIZE_OF_BACKLOG)?;
+
+    TcpListener::from_std(socket.into())
+}
+
+/// The IPv4 target is mapped to IPv6 if sent by an IPv6 socket.
+pub(crate) fn target(local: SocketAddr, target: SocketAddr) -> SocketAddr {
+    match (local, target) {
+        (SocketAddr::V6(_), SocketAddr::V4(v)) => {
+            SocketAddr::new(v.ip().to_ipv6_mapped().into(), v.port())
+        }
+        _ => target,
+    }
+}
+
+/// The IPv4-mapped peer of a dual-stack socket as IPv4.
+pub(crate) fn canonical(addr: SocketAddr) -> SocketAddr {
+    match addr {
+        SocketAddr::V6(v) if v.ip().to_ipv4_mapped().is_some() => {
+            SocketAddr::new(v.ip().to_canonical(), v.port())
+        }
+        _ => addr,
+    }
+}
@@ -0,0 +1,251 @@
+use super::{earliest, Queue, SocketState};
+use crate::{
+    error::Error, request, response, ActiveCode, Diagnostic, DoIpTiming, HeaderNegativeCode,
+    LogicAddress, Message, Payload, RoutingActiveType, Version,
+};
+
+/// The events of [`ClientConnection`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ClientEvent {
+    RoutingActivated(response::RoutingActive),
+    /// pending for the authentication or confirmation, the request should be repeated
+    RoutingPending(response::RoutingActive),
+    RoutingRefused(response::RoutingActive),
+    /// no routing activation response within A_DoIP_Ctrl
+    RoutingTimeout,
+    DiagnosticAck(response::DiagnosticPositive),
+    DiagnosticNack(response::DiagnosticNegative),
+    /// no diagnostic message acknowledge within A_DoIP_Diagnostic_Message
+    DiagnosticTimeout,
+    /// diagnostic message from the DoIP entity
+    Diagnostic(Diagnostic),
+    /// manufacturer-specific payload from the DoIP entity
+    Custom {
+        payload_type: u16,
+        data: Vec<u8>,
+    },
+    HeaderNegative(HeaderNegativeCode),
+    /// the socket shall be closed
+    Closed,
+}
+
+/// The TCP_DATA socket of the external test equipment.
+///
+/// The alive check requests of the entity are answered by the machine.
+#[derive(Debug)]
+pub struct ClientConnection {
+    /// logical address of the external test equipment
+    address: LogicAddress,
+    timing: DoIpTiming,
+    state: SocketState,
+    entity: Option<LogicAddress>,
+    /// A_DoIP_Ctrl of the outstanding routing activation request
+    routing_deadline: Option<u64>,
+    /// A_DoIP_Diagnostic_Message of the outstanding diagnostic message
+    ack_deadline: Option<u64>,
+    queue: Queue<ClientEvent>,
+}
+
+impl ClientConnection {
+    pub fn new(address: LogicAddress, version: Version, timing: DoIpTiming) -> Self {
+        Self {
+            address,
+            timing,
+            state: Default::default(),
+            entity: Default::default(),
+            routing_deadline: Default::default(),
+            ack_deadline: Default::default(),
+            queue: Queue::new(version),
+        }
+    }
+
+    #[inline]
+    pub fn address(&self) -> LogicAddress {
+        self.address
+    }
+
+    #[inline]
+    pub fn state(&self) -> SocketState {
+        self.state
+    }
+
+    /// The logical address of the DoIP entity, `None` before routing is activated.
+    #[inline]
+    pub fn entity_address(&self) -> Option<LogicAddress> {
+        self.entity
+    }
+
+    /// Whether the routing activation response is outstanding.
+    #[inline]
+    pub fn is_routing_outstanding(&self) -> bool {
+        self.routing_deadline.is_some()
+    }
+
+    /// Whether the diagnostic message acknowledge is outstanding.
+    #[inline]
+    pub fn is_ack_outstanding(&self) -> bool {
+        self.ack_deadline.is_some()
+    }
+
+    /// Queue the routing activation request.
+    pub fn activate(
+        &mut self,
+        now: u64,
+        active: RoutingActiveType,
+        user_def: Option<u32>,
+    ) -> Result<(), Error> {
+        if self.state == SocketState::Finalized {
+            return Err(Error::ConnectionClosed);
+        }
+        if self.routing_deadline.is_some() {
+            return Err(Error::InvalidParam(
+                "routing activation is outstanding".into(),
+            ));
+        }
+
+        let request = request::RoutingActive::new(self.address, active, user_def);
+        self.queue.send(Payload::ReqRoutingActive(request));
+        self.routing_deadline = Some(now + self.timing.ctrl);
+
+        Ok(())
+    }
+
+    /// Queue the diagnostic message, only one message is acknowledged at a time.
+    pub fn send_diagnostic(
+        &mut self,
+        now: u64,
+        target: LogicAddress,
+        data: Vec<u8>,
+    ) -> Result<(), Error> {
+        if self.state != SocketState::Active {
+            return Err(Error::RoutingInactive);
+        }
+        if self.ack_deadline.is_some() {
+            return Err(Error::InvalidParam(
+                "diagnostic message acknowledge is outstanding".into(),
+            ));
+        }
+
+        let diag = Diagnostic::new(self.address, target, data);
+        self.queue.send(Payload::Diagnostic(diag));
+        self.ack_deadline = Some(now + self.timing.diagnostic_message);
+
+        Ok(())
+    }
+
+    /// Handle a message decoded from the socket.
+    pub fn handle_message(&mut self, _now: u64, message: Message) {
+        if self.state == SocketState::Finalized {
+            return;
+        }
+
+        match message.payload {
+            Payload::ReqAliveCheck(_) => {
+                rsutil::trace!("ISO 13400-2 - answer alive check request");
+                let resp = response::AliveCheck::new(self.address);
+                self.queue.send(Payload::RespAliveCheck(resp));
+            }
+            Payload::RespRoutingActive(resp) if self.routing_deadline.is_some() => {
+                self.routing_deadline = None;
+                self.routing_response(resp);
+            }
+            Payload::RespDiagPositive(resp) if self.ack_deadline.is_some() => {
+                self.ack_deadline = None;
+                self.queue.emit(ClientEvent::DiagnosticAck(resp));
+            }
+            Payload::RespDiagNegative(resp) if self.ack_deadline.is_some() => {
+                self.ack_deadline = None;
+                self.queue.emit(ClientEvent::DiagnosticNack(resp));
+            }
+            Payload::Diagnostic(diag) => self.queue.emit(ClientEvent::Diagnostic(diag)),
+            Payload::Custom { payload_type, data } => {
+                self.queue.emit(ClientEvent::Custom { payload_type, data })
+            }
+            Payload::RespHeaderNegative(resp) => {
+                self.queue.emit(ClientEvent::HeaderNegative(resp.code))
+            }
+            payload => rsutil::warn!(
+                "ISO 13400-2 - ignore unexpected payload: {:?}",
+                payload.payload_type()
+            ),
+        }
+    }
+
+    /// Handle the decoding error of [`MessageDecoder`](crate::codec::MessageDecoder),
+    /// the generic DoIP header negative acknowledge is queued.
+    pub fn handle_error(&mut self, error: &Error) {
+        if self.state != SocketState::Finalized && self.queue.header_negative(error) {
+            self.finalize();
+        }
+    }
+
+    /// Handle the expired timers.
+    pub fn handle_timeout(&mut self, now: u64) {
+        if self.routing_deadline.is_some_and(|v| v <= now) {
+            rsutil::debug!("ISO 13400-2 - routing activation timeout");
+            self.routing_deadline = None;
+            self.queue.emit(ClientEvent::RoutingTimeout);
+        }
+        if self.ack_deadline.is_some_and(|v| v <= now) {
+            rsutil::debug!("ISO 13400-2 - diagnostic message acknowledge timeout");
+            self.ack_deadline = None;
+            self.queue.emit(ClientEvent::DiagnosticTimeout);
+        }
+    }
+
+    /// Finalize the socket, e.g. when the peer closed it.
+    pub fn close(&mut self) {
+        if self.state != SocketState::Finalized {
+            self.finalize();
+        }
+    }
+
+    /// The deadline of the next timer.
+    #[inline]
+    pub fn poll_timeout(&self) -> Option<u64> {
+        earliest(&[self.routing_deadline, self.ack_deadline])
+    }
+
+    /// The next message to send.
+    #[inline]
+    pub fn poll_transmit(&mut self) -> Option<Message> {
+        self.queue.transmits.pop_front()
+    }
+
+    #[inline]
+    pub fn poll_event(&mut self) -> Option<ClientEvent> {
+        self.queue.events.pop_front()
+    }
+
+    fn routing_response(&mut self, resp: response::RoutingActive) {
+        let code = resp.active_code;
+        rsutil::debug!(
+            "ISO 13400-2 - routing activation of {} response: {:?}",
+            resp.src_addr,
+            code
+        );
+        match code {
+            ActiveCode::Success => {
+                self.state = SocketState::Active;
+                self.entity = Some(resp.src_addr);
+                self.queue.emit(ClientEvent::RoutingActivated(resp));
+            }
+            _ if !code.closes_socket() => {
+                self.state = SocketState::Pending;
+                self.queue.emit(ClientEvent::RoutingPending(resp));
+            }
+            _ => {
+                self.queue.emit(ClientEvent::RoutingRefused(resp));
+                self.finalize();
+            }
+        }
+    }
+
+    fn finalize(&mut self) {
+        self.state = SocketState::Finalized;
+        self.entity = None;
+        self.routing_deadline = None;
+        self.ack_deadline = None;
+        self.queue.emit(ClientEvent::Closed);
+    }
+}
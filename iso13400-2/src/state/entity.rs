@@ -0,0 +1,274 @@
+use super::{earliest, Queue, SocketState};
+use crate::{
+    error::Error, request, response, ActiveCode, Diagnostic, DiagnosticNegativeCode,
+    DiagnosticPositiveCode, DoIpTiming, LogicAddress, Message, Payload, Version,
+};
+
+/// The events of [`EntityConnection`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum EntityEvent {
+    /// decide by [`EntityConnection::respond_routing`]
+    RoutingRequest(request::RoutingActive),
+    /// diagnostic message of the test equipment, acknowledge by [`EntityConnection::acknowledge`]
+    Diagnostic(Diagnostic),
+    /// manufacturer-specific payload of the test equipment
+    Custom { payload_type: u16, data: Vec<u8> },
+    /// alive check response of the test equipment
+    Alive(LogicAddress),
+    /// no alive check response within T_TCP_Alive_Check
+    AliveTimeout,
+    /// T_TCP_Initial_Inactivity or T_TCP_General_Inactivity expired
+    Inactive,
+    /// the socket shall be closed
+    Closed,
+}
+
+/// The TCP_DATA socket of the DoIP entity.
+///
+/// Only the single socket is handled, the socket handling of Table 49 across
+/// the sockets(e.g. alive check of the other sockets) is left to the owner.
+#[derive(Debug)]
+pub struct EntityConnection {
+    address: LogicAddress,
+    timing: DoIpTiming,
+    state: SocketState,
+    /// the test equipment with routing activated or pending
+    tester: Option<LogicAddress>,
+    /// the routing activation request waiting for the decision
+    request: Option<request::RoutingActive>,
+    /// T_TCP_Initial_Inactivity or T_TCP_General_Inactivity
+    deadline: u64,
+    /// T_TCP_Alive_Check of the outstanding alive check request
+    alive_deadline: Option<u64>,
+    queue: Queue<EntityEvent>,
+}
+
+impl EntityConnection {
+    /// The socket accepted at `now` by the entity of logical address `address`.
+    pub fn new(address: LogicAddress, version: Version, timing: DoIpTiming, now: u64) -> Self {
+        Self {
+            address,
+            timing,
+            state: Default::default(),
+            tester: Default::default(),
+            request: Default::default(),
+            deadline: now + timing.initial_inactivity,
+            alive_deadline: Default::default(),
+            queue: Queue::new(version),
+        }
+    }
+
+    #[inline]
+    pub fn state(&self) -> SocketState {
+        self.state
+    }
+
+    /// The test equipment with routing activated or pending.
+    #[inline]
+    pub fn tester(&self) -> Option<LogicAddress> {
+        self.tester
+    }
+
+    /// Whether the alive check response is outstanding.
+    #[inline]
+    pub fn is_alive_outstanding(&self) -> bool {
+        self.alive_deadline.is_some()
+    }
+
+    /// Handle a message decoded from the socket.
+    pub fn handle_message(&mut self, now: u64, message: Message) {
+        if self.state == SocketState::Finalized {
+            return;
+        }
+        if self.state == SocketState::Active {
+            self.deadline = now + self.timing.general_inactivity;
+        }
+
+        match message.payload {
+            Payload::ReqRoutingActive(req) => self.routing_request(req),
+            Payload::RespAliveCheck(resp) => {
+                self.alive_deadline = None;
+                self.queue.emit(EntityEvent::Alive(resp.src_addr));
+            }
+            Payload::Diagnostic(diag) => {
+                if self.state != SocketState::Active || self.tester != Some(diag.src_addr) {
+                    let nack = response::DiagnosticNegative::new(
+                        diag.dst_addr,
+                        diag.src_addr,
+                        DiagnosticNegativeCode::InvalidSourceAddress,
+                        vec![],
+                    );
+                    self.queue.send(Payload::RespDiagNegative(nack));
+                    self.finalize();
+                    return;
+                }
+                self.queue.emit(EntityEvent::Diagnostic(diag));
+            }
+            Payload::Custom { payload_type, data } if self.state == SocketState::Active => {
+                self.queue.emit(EntityEvent::Custom { payload_type, data })
+            }
+            payload => rsutil::warn!(
+                "ISO 13400-2 - ignore TCP payload: {:?}",
+                payload.payload_type()
+            ),
+        }
+    }
+
+    /// Answer the outstanding routing activation request.
+    ///
+    /// [`ActiveCode::Success`] activates the routing, the codes which keep the socket
+    /// reserve it for the test equipment and the others finalize the socket.
+    pub fn respond_routing(
+        &mut self,
+        now: u64,
+        code: ActiveCode,
+        user_def: Option<u32>,
+    ) -> Result<(), Error> {
+        let req = self
+            .request
+            .take()
+            .ok_or_else(|| Error::InvalidParam("no routing activation request".into()))?;
+        self.send_routing(&req, code, user_def);
+        match code {
+            ActiveCode::Success => {
+                self.state = SocketState::Active;
+                self.tester = Some(req.src_addr);
+                self.deadline = now + self.timing.general_inactivity;
+            }
+            _ if !code.closes_socket() => {
+                self.state = SocketState::Pending;
+                self.tester = Some(req.src_addr);
+            }
+            _ => self.finalize(),
+        }
+
+        Ok(())
+    }
+
+    /// Queue the acknowledge of the diagnostic message.
+    pub fn acknowledge(&mut self, diag: &Diagnostic, ret: Result<(), DiagnosticNegativeCode>) {
+        let payload = match ret {
+            Ok(()) => Payload::RespDiagPositive(response::DiagnosticPositive::new(
+                diag.dst_addr,
+                diag.src_addr,
+                DiagnosticPositiveCode::Confirm,
+                vec![],
+            )),
+            Err(code) => Payload::RespDiagNegative(response::DiagnosticNegative::new(
+                diag.dst_addr,
+                diag.src_addr,
+                code,
+                vec![],
+            )),
+        };
+        self.queue.send(payload);
+    }
+
+    /// Queue the diagnostic message from `src_addr` to the test equipment.
+    pub fn send_diagnostic(&mut self, src_addr: LogicAddress, data: Vec<u8>) -> Result<(), Error> {
+        match (self.state, self.tester) {
+            (SocketState::Active, Some(tester)) => {
+                let diag = Diagnostic::new(src_addr, tester, data);
+                self.queue.send(Payload::Diagnostic(diag));
+                Ok(())
+            }
+            _ => Err(Error::RoutingInactive),
+        }
+    }
+
+    /// Queue the alive check request, nothing is sent if a request is outstanding.
+    pub fn alive_check(&mut self, now: u64) {
+        if self.state == SocketState::Finalized || self.alive_deadline.is_some() {
+            return;
+        }
+
+        self.queue.send(Payload::ReqAliveCheck(request::AliveCheck));
+        self.alive_deadline = Some(now + self.timing.alive_check);
+    }
+
+    /// Handle the decoding error of [`MessageDecoder`](crate::codec::MessageDecoder),
+    /// the generic DoIP header negative acknowledge is queued.
+    pub fn handle_error(&mut self, error: &Error) {
+        if self.state != SocketState::Finalized && self.queue.header_negative(error) {
+            self.finalize();
+        }
+    }
+
+    /// Handle the expired timers.
+    pub fn handle_timeout(&mut self, now: u64) {
+        if self.state == SocketState::Finalized {
+            return;
+        }
+
+        if self.alive_deadline.is_some_and(|v| v <= now) {
+            rsutil::info!("ISO 13400-2 - no alive check response");
+            self.queue.emit(EntityEvent::AliveTimeout);
+            self.finalize();
+        } else if self.deadline <= now {
+            rsutil::info!("ISO 13400-2 - socket inactive");
+            self.queue.emit(EntityEvent::Inactive);
+            self.finalize();
+        }
+    }
+
+    /// Finalize the socket, e.g. when the peer closed it.
+    pub fn close(&mut self) {
+        if self.state != SocketState::Finalized {
+            self.finalize();
+        }
+    }
+
+    /// The deadline of the next timer, `None` after the socket is finalized.
+    #[inline]
+    pub fn poll_timeout(&self) -> Option<u64> {
+        match self.state {
+            SocketState::Finalized => None,
+            _ => earliest(&[Some(self.deadline), self.alive_deadline]),
+        }
+    }
+
+    /// The next message to send.
+    #[inline]
+    pub fn poll_transmit(&mut self) -> Option<Message> {
+        self.queue.transmits.pop_front()
+    }
+
+    #[inline]
+    pub fn poll_event(&mut self) -> Option<EntityEvent> {
+        self.queue.events.pop_front()
+    }
+
+    fn routing_request(&mut self, req: request::RoutingActive) {
+        match self.tester {
+            // the socket is reserved for another test equipment
+            Some(tester) if tester != req.src_addr => {
+                self.send_routing(&req, ActiveCode::SourceAddressInvalid, None);
+                self.finalize();
+            }
+            Some(_) if self.state == SocketState::Active => {
+                self.send_routing(&req, ActiveCode::Success, None);
+            }
+            _ => {
+                self.request = Some(req.clone());
+                self.queue.emit(EntityEvent::RoutingRequest(req));
+            }
+        }
+    }
+
+    fn send_routing(
+        &mut self,
+        req: &request::RoutingActive,
+        code: ActiveCode,
+        user_def: Option<u32>,
+    ) {
+        let resp = response::RoutingActive::new(req.src_addr, self.address, code, user_def);
+        self.queue.send(Payload::RespRoutingActive(resp));
+    }
+
+    fn finalize(&mut self) {
+        self.state = SocketState::Finalized;
+        self.request = None;
+        self.alive_deadline = None;
+        self.queue.emit(EntityEvent::Closed);
+    }
+}
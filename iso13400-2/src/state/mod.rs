@@ -0,0 +1,82 @@
+//! Sans-IO state machines of a TCP_DATA socket.
+//!
+//! The machines are fed with the decoded messages and the current time in milliseconds
+//! of a monotonic clock chosen by the caller. The messages to send are taken by `poll_transmit`,
+//! the events by `poll_event`, and `poll_timeout` gives the deadline when `handle_timeout`
+//! shall be called next.
+//!
+//! Table 49 — Routing activation response code values
+
+mod client;
+mod entity;
+
+pub use self::{
+    client::{ClientConnection, ClientEvent},
+    entity::{EntityConnection, EntityEvent},
+};
+
+use crate::{error::Error, Message, Payload, Version};
+use std::collections::VecDeque;
+
+/// The state of a TCP_DATA socket.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum SocketState {
+    /// connected, routing is not activated
+    #[default]
+    Initialized,
+    /// routing activation pending for the authentication or confirmation
+    Pending,
+    /// routing is activated
+    Active,
+    /// the socket shall be closed
+    Finalized,
+}
+
+/// The messages to send and the events of a machine.
+#[derive(Debug)]
+struct Queue<E> {
+    version: Version,
+    transmits: VecDeque<Message>,
+    events: VecDeque<E>,
+}
+
+impl<E> Queue<E> {
+    fn new(version: Version) -> Self {
+        Self {
+            version,
+            transmits: Default::default(),
+            events: Default::default(),
+        }
+    }
+
+    #[inline]
+    fn send(&mut self, payload: Payload) {
+        self.transmits.push_back(Message {
+            version: self.version,
+            payload,
+        });
+    }
+
+    #[inline]
+    fn emit(&mut self, event: E) {
+        self.events.push_back(event);
+    }
+
+    /// Queue the generic DoIP header negative acknowledge of a decoding error,
+    /// return whether the socket must be closed.
+    fn header_negative(&mut self, error: &Error) -> bool {
+        match error.header_negative() {
+            Some((nack, close)) => {
+                self.send(Payload::RespHeaderNegative(nack));
+                close
+            }
+            None => true,
+        }
+    }
+}
+
+/// The earliest deadline of the timers.
+#[inline]
+fn earliest(timers: &[Option<u64>]) -> Option<u64> {
+    timers.iter().flatten().min().copied()
+}
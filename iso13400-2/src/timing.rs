@@ -0,0 +1,64 @@
+//! Table 12 — Timing and communication parameters
+
+use crate::Version;
+
+/// The timing and communication parameters of the client, entity and discovery.
+///
+/// All times are in milliseconds.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct DoIpTiming {
+    /// A_DoIP_Ctrl, timeout of the control responses(UDP and routing activation)
+    pub ctrl: u64,
+    /// A_DoIP_Announce_Wait, max. random delay before the first vehicle announcement
+    pub announce_wait: u64,
+    /// A_DoIP_Announce_Interval, time between the vehicle announcements
+    pub announce_interval: u64,
+    /// A_DoIP_Announce_Num, count of the vehicle announcements
+    pub announce_num: u32,
+    /// A_DoIP_Diagnostic_Message, timeout of the diagnostic message acknowledge
+    pub diagnostic_message: u64,
+    /// T_TCP_General_Inactivity, max. inactivity of a socket with routing activated
+    pub general_inactivity: u64,
+    /// T_TCP_Initial_Inactivity, max. time from connecting to the routing activation
+    pub initial_inactivity: u64,
+    /// T_TCP_Alive_Check, timeout of the alive check response
+    pub alive_check: u64,
+    /// A_Processing_Time, max. time of the entity to process a request before responding
+    pub processing_time: u64,
+    /// A_Vehicle_Discovery_Timer, time to wait for the vehicle announcements
+    pub vehicle_discovery: u64,
+}
+
+impl Default for DoIpTiming {
+    fn default() -> Self {
+        Self::new(Version::ISO13400_2_2012)
+    }
+}
+
+impl DoIpTiming {
+    /// The default values of the protocol version.
+    ///
+    /// ISO 13400-2:2010 requires the diagnostic message acknowledge within 50ms,
+    /// the later versions relaxed it to 2s.
+    pub const fn new(version: Version) -> Self {
+        let diagnostic_message = match version {
+            Version::ISO13400_2_2010 => 50,
+            _ => 2_000,
+        };
+
+        Self {
+            ctrl: 2_000,
+            announce_wait: 500,
+            announce_interval: 500,
+            announce_num: 3,
+            diagnostic_message,
+            general_inactivity: 300_000,
+            initial_inactivity: 2_000,
+            alive_check: 500,
+            processing_time: 2_000,
+            vehicle_discovery: 5_000,
+        }
+    }
+}
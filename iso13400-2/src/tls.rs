@@ -0,0 +1,91 @@
+//! TLS configurations of the secured DoIP connection(ISO 13400-2:2019).
+//!
+//! Table 29 — TLS authentication type
+//!
+//! Table 30 — TLS 1.2 version cipher suites
+//!
+//! Table 31 — TLS 1.3 version cipher suites
+//!
+//! The extensions of Table 32 ~ Table 37 are negotiated by rustls.
+//! The NULL and CCM cipher suites are not supported by rustls.
+
+use crate::error::Error;
+use rustls::{
+    crypto::{ring, CryptoProvider},
+    pki_types::{CertificateDer, PrivateKeyDer},
+    server::WebPkiClientVerifier,
+    RootCertStore, SupportedCipherSuite,
+};
+use std::sync::Arc;
+
+pub use rustls;
+pub use rustls::pki_types::ServerName;
+
+/// The cipher suites of Table 30 and Table 31 supported by rustls.
+pub static CIPHER_SUITES: &[SupportedCipherSuite] = &[
+    // TLS 1.3
+    ring::cipher_suite::TLS13_AES_128_GCM_SHA256,
+    ring::cipher_suite::TLS13_AES_256_GCM_SHA384,
+    ring::cipher_suite::TLS13_CHACHA20_POLY1305_SHA256,
+    // TLS 1.2
+    ring::cipher_suite::TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256,
+    ring::cipher_suite::TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384,
+    ring::cipher_suite::TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256,
+];
+
+/// The crypto provider restricted to [`CIPHER_SUITES`].
+pub fn crypto_provider() -> Arc<CryptoProvider> {
+    Arc::new(CryptoProvider {
+        cipher_suites: CIPHER_SUITES.to_vec(),
+        ..ring::default_provider()
+    })
+}
+
+/// The client configuration with TLS 1.2 and TLS 1.3.
+///
+/// The client is authenticated by the certificate chain and key if present.
+pub fn client_config(
+    roots: RootCertStore,
+    identity: Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>,
+) -> Result<rustls::ClientConfig, Error> {
+    let builder = rustls::ClientConfig::builder_with_provider(crypto_provider())
+        .with_safe_default_protocol_versions()
+        .map_err(tls_error)?
+        .with_root_certificates(roots);
+
+    match identity {
+        Some((certs, key)) => builder.with_client_auth_cert(certs, key).map_err(tls_error),
+        None => Ok(builder.with_no_client_auth()),
+    }
+}
+
+/// The entity configuration with TLS 1.2 and TLS 1.3.
+///
+/// The client certificate is required if `client_roots` is present.
+pub fn server_config(
+    certs: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+    client_roots: Option<RootCertStore>,
+) -> Result<rustls::ServerConfig, Error> {
+    let provider = crypto_provider();
+    let builder = rustls::ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(tls_error)?;
+
+    let builder = match client_roots {
+        Some(roots) => {
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .map_err(tls_error)?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    builder.with_single_cert(certs, key).map_err(tls_error)
+}
+
+#[inline]
+pub(crate) fn tls_error<E: std::fmt::Display>(e: E) -> Error {
+    Error::TlsError(e.to_string())
+}
@@ -17,3 +17,18 @@ pub(crate) fn data_len_check(
 
     Ok((actual, 0))
 }
+
+/// Serialize the binary data as a hex string.
+#[cfg(feature = "serde")]
+pub(crate) mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let data = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        hex::decode(data.as_ref()).map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,372 @@
+#[cfg(test)]
+mod tests {
+    use iso13400_2::{
+        client::{ClientConfig, ConnectionManager, DoIpClient, EntityKey},
+        request, response, ActiveCode, Diagnostic, DiagnosticNegativeCode, DiagnosticPositiveCode,
+        HeaderNegativeCode, Iso13400Error, LogicAddress, Message, Payload, RoutingActiveType,
+        Version,
+    };
+    use std::net::SocketAddr;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    const TESTER: u16 = 0x0E80;
+    const ENTITY: u16 = 0x1001;
+
+    async fn read(stream: &mut TcpStream) -> anyhow::Result<Message> {
+        let mut header = [0; 8];
+        stream.read_exact(&mut header).await?;
+        let length = u32::from_be_bytes(header[4..].try_into()?) as usize;
+        let mut data = header.to_vec();
+        data.resize(8 + length, 0);
+        stream.read_exact(&mut data[8..]).await?;
+
+        Ok(Message::try_from(data.as_slice())?)
+    }
+
+    async fn write(stream: &mut TcpStream, payload: Payload) -> anyhow::Result<()> {
+        let data: Vec<_> = Message {
+            version: Version::ISO13400_2_2012,
+            payload,
+        }
+        .into();
+        stream.write_all(&data).await?;
+
+        Ok(())
+    }
+
+    async fn routing_activation(stream: &mut TcpStream, code: ActiveCode) -> anyhow::Result<()> {
+        let message = read(stream).await?;
+        match message.payload {
+            Payload::ReqRoutingActive(req) => {
+                assert_eq!(req.src_addr(), LogicAddress::from(TESTER));
+                assert_eq!(req.active(), RoutingActiveType::Default);
+            }
+            _ => panic!("unexpected message: {:?}", message),
+        }
+        let resp = response::RoutingActive::new(
+            LogicAddress::from(TESTER),
+            LogicAddress::from(ENTITY),
+            code,
+            None,
+        );
+        write(stream, Payload::RespRoutingActive(resp)).await
+    }
+
+    async fn listen() -> anyhow::Result<(TcpListener, SocketAddr)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        Ok((listener, addr))
+    }
+
+    fn config() -> ClientConfig {
+        let mut config = ClientConfig::new(LogicAddress::from(TESTER));
+        config.version = Version::ISO13400_2_2012;
+        config.timing.ctrl = 500;
+        config.timing.diagnostic_message = 500;
+        config.diag_timeout = 500;
+        config
+    }
+
+    #[tokio::test]
+    async fn test_diagnostic() -> anyhow::Result<()> {
+        let (listener, addr) = listen().await?;
+        let entity = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await?;
+            routing_activation(&mut stream, ActiveCode::Success).await?;
+
+            // alive check must be answered by the client itself
+            write(&mut stream, Payload::ReqAliveCheck(request::AliveCheck)).await?;
+            let message = read(&mut stream).await?;
+            match message.payload {
+                Payload::RespAliveCheck(resp) => {
+                    assert_eq!(resp.src_addr(), LogicAddress::from(TESTER))
+                }
+                _ => panic!("unexpected message: {:?}", message),
+            }
+
+            let message = read(&mut stream).await?;
+            let diag = match message.payload {
+                Payload::Diagnostic(v) => v,
+                _ => panic!("unexpected message: {:?}", message),
+            };
+            assert_eq!(diag.src_addr(), LogicAddress::from(TESTER));
+            assert_eq!(diag.dst_addr(), LogicAddress::from(ENTITY));
+            assert_eq!(diag.data(), &vec![0x10, 0x01]);
+            let ack = response::DiagnosticPositive::new(
+                diag.dst_addr(),
+                diag.src_addr(),
+                DiagnosticPositiveCode::Confirm,
+                vec![],
+            );
+            write(&mut stream, Payload::RespDiagPositive(ack)).await?;
+            // the response of another node is ignored
+            let resp = Diagnostic::new(
+                LogicAddress::from(0x1002),
+                diag.src_addr(),
+                hex::decode("7F1011")?,
+            );
+            write(&mut stream, Payload::Diagnostic(resp)).await?;
+            let resp = Diagnostic::new(
+                diag.dst_addr(),
+                diag.src_addr(),
+                hex::decode("5001003201F4")?,
+            );
+            write(&mut stream, Payload::Diagnostic(resp)).await?;
+
+            let message = read(&mut stream).await?;
+            let diag = match message.payload {
+                Payload::Diagnostic(v) => v,
+                _ => panic!("unexpected message: {:?}", message),
+            };
+            let nack = response::DiagnosticNegative::new(
+                diag.dst_addr(),
+                diag.src_addr(),
+                DiagnosticNegativeCode::UnknownTargetAddress,
+                vec![],
+            );
+            write(&mut stream, Payload::RespDiagNegative(nack)).await?;
+
+            anyhow::Ok(stream)
+        });
+
+        let client = DoIpClient::connect_addr(addr, config()).await?;
+        assert!(matches!(
+            client
+                .send_diagnostic(LogicAddress::from(ENTITY), vec![0x10, 0x01])
+                .await,
+            Err(Iso13400Error::RoutingInactive)
+        ));
+        let resp = client
+            .routing_activation(RoutingActiveType::Default, None)
+            .await?;
+        assert_eq!(resp.active_code(), ActiveCode::Success);
+        assert_eq!(client.entity_address(), Some(LogicAddress::from(ENTITY)));
+
+        let resp = client
+            .diagnostic(LogicAddress::from(ENTITY), vec![0x10, 0x01])
+            .await?;
+        assert_eq!(resp.src_addr(), LogicAddress::from(ENTITY));
+        assert_eq!(resp.dst_addr(), LogicAddress::from(TESTER));
+        assert_eq!(resp.data(), &hex::decode("5001003201F4")?);
+
+        let ret = client
+            .send_diagnostic(LogicAddress::from(0x1002), vec![0x10, 0x01])
+            .await;
+        assert!(matches!(
+            ret,
+            Err(Iso13400Error::DiagnosticNegative(
+                DiagnosticNegativeCode::UnknownTargetAddress
+            ))
+        ));
+
+        let _stream = entity.await??;
+        client.close().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_routing_activation_failed() -> anyhow::Result<()> {
+        let (listener, addr) = listen().await?;
+        let entity = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await?;
+            routing_activation(&mut stream, ActiveCode::SourceAddressUnknown).await?;
+            anyhow::Ok(stream)
+        });
+
+        let client = DoIpClient::connect_addr(addr, config()).await?;
+        let ret = client
+            .routing_activation(RoutingActiveType::Default, None)
+            .await;
+        assert!(matches!(
+            ret,
+            Err(Iso13400Error::RoutingActivation(
+                ActiveCode::SourceAddressUnknown
+            ))
+        ));
+        assert_eq!(client.entity_address(), None);
+        let _stream = entity.await??;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_header_negative() -> anyhow::Result<()> {
+        let (listener, addr) = listen().await?;
+        let entity = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await?;
+            let _ = read(&mut stream).await?;
+            let nack = response::HeaderNegative::new(HeaderNegativeCode::UnknownPayloadTYpe);
+            write(&mut stream, Payload::RespHeaderNegative(nack)).await?;
+            anyhow::Ok(stream)
+        });
+
+        let client = DoIpClient::connect_addr(addr, config()).await?;
+        let ret = client
+            .routing_activation(RoutingActiveType::Default, None)
+            .await;
+        assert!(matches!(
+            ret,
+            Err(Iso13400Error::HeaderNegative(
+                HeaderNegativeCode::UnknownPayloadTYpe
+            ))
+        ));
+        let stream = entity.await??;
+
+        // no response at all
+        let ret = client
+            .routing_activation(RoutingActiveType::Default, None)
+            .await;
+        assert!(matches!(ret, Err(Iso13400Error::Timeout { .. })));
+
+        drop(stream);
+        let ret = client
+            .routing_activation(RoutingActiveType::Default, None)
+            .await;
+        assert!(matches!(ret, Err(Iso13400Error::ConnectionClosed)));
+
+        Ok(())
+    }
+
+    /// Acknowledge the diagnostic message and answer it with the positive response.
+    async fn answer_diagnostic(stream: &mut TcpStream) -> anyhow::Result<()> {
+        let message = read(stream).await?;
+        let diag = match message.payload {
+            Payload::Diagnostic(v) => v,
+            _ => panic!("unexpected message: {:?}", message),
+        };
+        let ack = response::DiagnosticPositive::new(
+            diag.dst_addr(),
+            diag.src_addr(),
+            DiagnosticPositiveCode::Confirm,
+            vec![],
+        );
+        write(stream, Payload::RespDiagPositive(ack)).await?;
+        let mut data = diag.data().clone();
+        data[0] |= 0x40;
+        let resp = Diagnostic::new(diag.dst_addr(), diag.src_addr(), data);
+        write(stream, Payload::Diagnostic(resp)).await
+    }
+
+    #[tokio::test]
+    async fn test_manager() -> anyhow::Result<()> {
+        const VIN: &str = "ABCDEF0123456789X";
+        let (listener, addr) = listen().await?;
+        let entity = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await?;
+            routing_activation(&mut stream, ActiveCode::Success).await?;
+            answer_diagnostic(&mut stream).await?;
+            // the entity closes the inactive socket
+            drop(stream);
+
+            let (mut stream, _) = listener.accept().await?;
+            routing_activation(&mut stream, ActiveCode::Success).await?;
+            answer_diagnostic(&mut stream).await?;
+            // the entity is reset by the request
+            let _ = read(&mut stream).await?;
+            drop(stream);
+
+            let (mut stream, _) = listener.accept().await?;
+            routing_activation(&mut stream, ActiveCode::Success).await?;
+            answer_diagnostic(&mut stream).await?;
+
+            anyhow::Ok(stream)
+        });
+
+        let manager = ConnectionManager::new(config());
+        let key = EntityKey::Ip(addr.ip());
+        manager.insert(key, VIN.into(), LogicAddress::from(ENTITY), addr);
+        assert_eq!(manager.vehicles(), vec![VIN.to_string()]);
+        assert_eq!(manager.resolve(VIN, LogicAddress::from(0x1010))?, key);
+        assert!(manager
+            .resolve("ABCDEF0123456789Y", LogicAddress::from(ENTITY))
+            .is_err());
+
+        let resp = manager
+            .diagnostic(VIN, LogicAddress::from(ENTITY), vec![0x10, 0x01])
+            .await?;
+        assert_eq!(resp.data(), &vec![0x50, 0x01]);
+
+        // reconnected and routing activated again
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let resp = manager
+            .diagnostic(VIN, LogicAddress::from(ENTITY), vec![0x22, 0xF1, 0x90])
+            .await?;
+        assert_eq!(resp.data(), &vec![0x62, 0xF1, 0x90]);
+
+        // not repeated on a new session
+        let ret = manager
+            .diagnostic(VIN, LogicAddress::from(ENTITY), vec![0x11, 0x01])
+            .await;
+        assert!(matches!(ret, Err(Iso13400Error::ConnectionClosed)));
+        let resp = manager
+            .diagnostic(VIN, LogicAddress::from(ENTITY), vec![0x3E, 0x00])
+            .await?;
+        assert_eq!(resp.data(), &vec![0x7E, 0x00]);
+
+        let _stream = entity.await??;
+        assert!(manager.remove(key).is_some());
+        assert!(manager.entities().is_empty());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "transport")]
+    #[tokio::test]
+    async fn test_transport() -> anyhow::Result<()> {
+        use iso13400_2::client::DoIpTransport;
+        use iso14229_1::transport::{Addressing, DiagTransport};
+        use std::sync::Arc;
+
+        const FUNCTIONAL: u16 = 0xE400;
+        let (listener, addr) = listen().await?;
+        let entity = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await?;
+            routing_activation(&mut stream, ActiveCode::Success).await?;
+            answer_diagnostic(&mut stream).await?;
+
+            let message = read(&mut stream).await?;
+            let diag = match message.payload {
+                Payload::Diagnostic(v) => v,
+                _ => panic!("unexpected message: {:?}", message),
+            };
+            assert_eq!(diag.dst_addr(), LogicAddress::from(FUNCTIONAL));
+            let ack = response::DiagnosticPositive::new(
+                diag.dst_addr(),
+                diag.src_addr(),
+                DiagnosticPositiveCode::Confirm,
+                vec![],
+            );
+            write(&mut stream, Payload::RespDiagPositive(ack)).await?;
+
+            anyhow::Ok(stream)
+        });
+
+        let client = DoIpClient::connect_addr(addr, config()).await?;
+        client
+            .routing_activation(RoutingActiveType::Default, None)
+            .await?;
+        let transport = DoIpTransport::new(Arc::new(client), LogicAddress::from(ENTITY));
+        assert!(transport
+            .send(Addressing::Functional, &[0x3E, 0x80])
+            .await
+            .is_err());
+
+        transport.send(Addressing::Physical, &[0x10, 0x03]).await?;
+        let pdu = transport.receive(500).await?;
+        assert_eq!(pdu.addressing, Addressing::Physical);
+        assert_eq!(pdu.data, vec![0x50, 0x03]);
+
+        let transport = transport.with_functional(LogicAddress::from(FUNCTIONAL));
+        transport
+            .send(Addressing::Functional, &[0x3E, 0x80])
+            .await?;
+
+        let _stream = entity.await??;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,536 @@
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use iso13400_2::{
+        codec::{MessageDecoder, MessageEncoder},
+        request, response, ActiveCode, CustomPayload, Diagnostic, DiagnosticNegativeCode,
+        DiagnosticPositiveCode, DiagnosticRef, Eid, FurtherAction, Gid, HeaderNegativeCode,
+        Iso13400Error, LogicAddress, Message, MessageRef, NodeType, Payload, PayloadRegistry,
+        PayloadType, PowerMode, RoutingActiveType, SyncStatus, Version,
+    };
+
+    /// manufacturer-specific payload with a 2 bytes counter
+    #[derive(Debug, PartialEq)]
+    struct Counter(u16);
+
+    impl CustomPayload for Counter {
+        const PAYLOAD_TYPE: u16 = 0xF001;
+
+        fn decode(data: &[u8]) -> Result<Self, Iso13400Error> {
+            match data {
+                &[hi, lo] => Ok(Self(u16::from_be_bytes([hi, lo]))),
+                _ => Err(Iso13400Error::InvalidPayloadLength {
+                    actual: data.len(),
+                    expected: 2,
+                }),
+            }
+        }
+
+        fn encode(&self) -> Vec<u8> {
+            self.0.to_be_bytes().to_vec()
+        }
+    }
+
+    fn diagnostic(data: Vec<u8>) -> Message {
+        Message {
+            version: Version::ISO13400_2_2012,
+            payload: Payload::Diagnostic(Diagnostic::new(
+                LogicAddress::from(0x0E00),
+                LogicAddress::from(0x0DFF),
+                data,
+            )),
+        }
+    }
+
+    fn assert_header_error(
+        ret: Result<Option<Message>, Iso13400Error>,
+        expect: (HeaderNegativeCode, bool),
+    ) {
+        match ret {
+            Err(Iso13400Error::InvalidHeader { code, close }) => assert_eq!((code, close), expect),
+            v => panic!("unexpected result: {:?}", v),
+        }
+    }
+
+    #[test]
+    fn test_chunks() -> anyhow::Result<()> {
+        let source = hex::decode("02fd8001000000070e000dff021001")?;
+        let mut data = source.clone();
+        data.extend(hex::decode("02fd000700000000")?); // alive check request
+        data.extend(&source);
+
+        let mut decoder = MessageDecoder::default();
+        let mut messages = Vec::new();
+        for chunk in data.chunks(3) {
+            decoder.extend(chunk);
+            while let Some(message) = decoder.decode()? {
+                messages.push(message);
+            }
+        }
+        assert_eq!(decoder.buffered(), 0);
+        assert_eq!(messages.len(), 3);
+        assert!(matches!(messages[1].payload, Payload::ReqAliveCheck(_)));
+        for message in [&messages[0], &messages[2]] {
+            match &message.payload {
+                Payload::Diagnostic(v) => {
+                    assert_eq!(v.src_addr(), LogicAddress::from(0x0E00));
+                    assert_eq!(v.dst_addr(), LogicAddress::from(0x0DFF));
+                    assert_eq!(v.data(), &hex::decode("021001")?);
+                }
+                _ => panic!("unexpected message: {:?}", message),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_message_too_large() -> anyhow::Result<()> {
+        let mut decoder = MessageDecoder::new(8);
+        decoder.extend(&hex::decode("02fd8001000000090e000dff")?);
+        assert_header_error(
+            decoder.decode(),
+            (HeaderNegativeCode::MessageTooLarge, false),
+        );
+        assert!(decoder.decode()?.is_none());
+
+        // the rest of the large message is discarded
+        decoder.extend(&hex::decode("0210010203")?);
+        decoder.extend(&hex::decode("02fd000700000000")?);
+        let message = decoder.decode()?.unwrap();
+        assert!(matches!(message.payload, Payload::ReqAliveCheck(_)));
+        assert_eq!(decoder.buffered(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_payload_type() -> anyhow::Result<()> {
+        let mut decoder = MessageDecoder::default();
+        decoder.extend(&hex::decode("02fdf00100000002aabb02fd000700000000")?);
+        assert_header_error(
+            decoder.decode(),
+            (HeaderNegativeCode::UnknownPayloadTYpe, false),
+        );
+        let message = decoder.decode()?.unwrap();
+        assert!(matches!(message.payload, Payload::ReqAliveCheck(_)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_close_socket() -> anyhow::Result<()> {
+        let mut decoder = MessageDecoder::default();
+        decoder.extend(&hex::decode("02fc000700000000")?);
+        assert_header_error(
+            decoder.decode(),
+            (HeaderNegativeCode::IncorrectPatternFormat, true),
+        );
+        assert_eq!(decoder.buffered(), 0);
+
+        // alive check request has no payload
+        decoder.extend(&hex::decode("02fd000700000001ff")?);
+        assert_header_error(
+            decoder.decode(),
+            (HeaderNegativeCode::InvalidPayloadLength, true),
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_header() -> anyhow::Result<()> {
+        let mut decoder = MessageDecoder::new(0x0FFF);
+        decoder.set_available_memory(0x0100);
+
+        let header = decoder.validate_header(&hex::decode("02fd00050000000b")?)?;
+        assert_eq!(header.version, Version::ISO13400_2_2012);
+        assert_eq!(header.payload_type, PayloadType::ReqRoutingActive);
+        assert_eq!(header.payload_len, 11);
+
+        for (hex, code, close) in [
+            (
+                "02fe00050000000b",
+                HeaderNegativeCode::IncorrectPatternFormat,
+                true,
+            ),
+            (
+                "02fd00090000000b",
+                HeaderNegativeCode::UnknownPayloadTYpe,
+                false,
+            ),
+            (
+                "02fd800100001000",
+                HeaderNegativeCode::MessageTooLarge,
+                false,
+            ),
+            ("02fd800100000800", HeaderNegativeCode::OutOfMemory, false),
+            (
+                "02fd000500000008",
+                HeaderNegativeCode::InvalidPayloadLength,
+                true,
+            ),
+            (
+                "02fd800100000003",
+                HeaderNegativeCode::InvalidPayloadLength,
+                true,
+            ),
+        ] {
+            let err = decoder.validate_header(&hex::decode(hex)?).unwrap_err();
+            let (nack, ret) = err.header_negative().unwrap();
+            assert_eq!((nack.code(), ret), (code, close), "{}", hex);
+        }
+        assert!(decoder.validate_header(&[0x02, 0xfd]).is_err());
+
+        // the invalid payload length is rejected before the payload is received
+        decoder.extend(&hex::decode("02fd000800000003")?);
+        assert_header_error(
+            decoder.decode(),
+            (HeaderNegativeCode::InvalidPayloadLength, true),
+        );
+        assert_eq!(decoder.buffered(), 0);
+
+        // the message is skipped when out of memory
+        decoder.extend(&hex::decode("02fd8001000001010e000dff")?);
+        decoder.extend(&[0x3e; 0x0101 - 4]);
+        decoder.extend(&hex::decode("02fd000700000000")?);
+        assert_header_error(decoder.decode(), (HeaderNegativeCode::OutOfMemory, false));
+        let message = decoder.decode()?.unwrap();
+        assert!(matches!(message.payload, Payload::ReqAliveCheck(_)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encoder() -> anyhow::Result<()> {
+        let encoder = MessageEncoder::new(8);
+        let mut buffer = BytesMut::new();
+        encoder.encode(diagnostic(hex::decode("021001")?), &mut buffer)?;
+        encoder.encode(
+            Message {
+                version: Version::ISO13400_2_2012,
+                payload: Payload::ReqAliveCheck(request::AliveCheck),
+            },
+            &mut buffer,
+        )?;
+        assert_eq!(
+            buffer.as_ref(),
+            hex::decode("02fd8001000000070e000dff02100102fd000700000000")?
+        );
+        assert!(encoder
+            .encode(diagnostic(hex::decode("0210010203")?), &mut buffer)
+            .is_err());
+        // the payload encoded beyond the max. size is discarded
+        let resp = response::RoutingActive::new(
+            LogicAddress::from(0x0E00),
+            LogicAddress::from(0x0DFF),
+            ActiveCode::Success,
+            None,
+        );
+        assert!(encoder
+            .encode(
+                Message {
+                    version: Version::ISO13400_2_2012,
+                    payload: Payload::RespRoutingActive(resp),
+                },
+                &mut buffer,
+            )
+            .is_err());
+        assert_eq!(buffer.len(), 23);
+
+        let mut decoder = MessageDecoder::new(encoder.max_payload_size());
+        decoder.extend(&buffer);
+        assert!(matches!(
+            decoder.decode()?.unwrap().payload,
+            Payload::Diagnostic(_)
+        ));
+        assert!(matches!(
+            decoder.decode()?.unwrap().payload,
+            Payload::ReqAliveCheck(_)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_payloads() -> anyhow::Result<()> {
+        let tester = LogicAddress::from(0x0E80);
+        let entity = LogicAddress::from(0x1001);
+        let payloads = vec![
+            Payload::RespHeaderNegative(response::HeaderNegative::new(
+                HeaderNegativeCode::UnknownPayloadTYpe,
+            )),
+            Payload::ReqVehicleId(request::VehicleID),
+            Payload::ReqVehicleWithEid(request::VehicleIDWithEID::new(Eid::new(0x001100110011)?)),
+            Payload::ReqVehicleWithVIN(request::VehicleIDWithVIN::new("WDD2040001A000001")?),
+            Payload::RespVehicleId(response::VehicleID::new(
+                "WDD2040001A000001".to_owned(),
+                entity,
+                Eid::new(0x001100110011)?,
+                Gid::new(0x110011001100)?,
+                FurtherAction::NoAction,
+                Some(SyncStatus::VINorGIDNotSync),
+            )?),
+            Payload::ReqRoutingActive(request::RoutingActive::new(
+                tester,
+                RoutingActiveType::Default,
+                Some(0x12345678),
+            )),
+            Payload::RespRoutingActive(response::RoutingActive::new(
+                tester,
+                entity,
+                ActiveCode::Success,
+                None,
+            )),
+            Payload::ReqAliveCheck(request::AliveCheck),
+            Payload::RespAliveCheck(response::AliveCheck::new(tester)),
+            Payload::ReqEntityStatus(request::EntityStatus),
+            Payload::RespEntityStatus(response::EntityStatus::new(
+                NodeType::Gateway,
+                2,
+                1,
+                Some(0x0FFF),
+            )),
+            Payload::ReqDiagPowerMode(request::DiagnosticPowerMode),
+            Payload::RespDiagPowerMode(response::DiagnosticPowerMode::new(PowerMode::Ready)),
+            Payload::RespDiagPositive(response::DiagnosticPositive::new(
+                entity,
+                tester,
+                DiagnosticPositiveCode::Confirm,
+                vec![0x22],
+            )),
+            Payload::RespDiagNegative(response::DiagnosticNegative::new(
+                entity,
+                tester,
+                DiagnosticNegativeCode::UnknownTargetAddress,
+                vec![],
+            )),
+        ];
+
+        // the same bytes as the owned encoding
+        let encoder = MessageEncoder::default();
+        for payload in payloads {
+            let message = Message {
+                version: Version::ISO13400_2_2012,
+                payload,
+            };
+            let mut dst = BytesMut::new();
+            encoder.encode(message.clone(), &mut dst)?;
+            assert_eq!(dst.to_vec(), Vec::<u8>::from(message));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_versions() -> anyhow::Result<()> {
+        let mut decoder = MessageDecoder::default();
+        decoder.set_versions(&[Version::ISO13400_2_2012]);
+
+        // the default version is accepted by the vehicle identification request only
+        decoder.extend(&hex::decode("ff00000100000000")?);
+        assert!(matches!(
+            decoder.decode()?,
+            Some(Message {
+                payload: Payload::ReqVehicleId(_),
+                ..
+            })
+        ));
+        decoder.extend(&hex::decode("ff00000700000000")?);
+        assert_header_error(
+            decoder.decode(),
+            (HeaderNegativeCode::IncorrectPatternFormat, true),
+        );
+
+        // unsupported version
+        decoder.extend(&hex::decode("03fc000700000000")?);
+        assert_header_error(
+            decoder.decode(),
+            (HeaderNegativeCode::IncorrectPatternFormat, true),
+        );
+        decoder.set_versions(&[Version::ISO13400_2_2012, Version::ISO13400_2_2019]);
+        decoder.extend(&hex::decode("03fc000700000000")?);
+        assert!(decoder.decode()?.is_some());
+
+        // TLS is required in ISO 13400-2:2019 only
+        decoder.extend(&hex::decode("02fd0006000000090e0010010700000000")?);
+        assert_header_error(
+            decoder.decode(),
+            (HeaderNegativeCode::InvalidPayloadLength, true),
+        );
+        decoder.extend(&hex::decode("03fc0006000000090e0010010700000000")?);
+        assert!(decoder.decode()?.is_some());
+
+        let resp = response::RoutingActive::new(
+            LogicAddress::from(0x0E00),
+            LogicAddress::from(0x1001),
+            ActiveCode::TLSRequired,
+            None,
+        );
+        let mut encoder = MessageEncoder::default();
+        let mut dst = BytesMut::new();
+        let ret = encoder.encode(
+            Message {
+                version: Version::ISO13400_2_2012,
+                payload: Payload::RespRoutingActive(resp.clone()),
+            },
+            &mut dst,
+        );
+        assert!(matches!(
+            ret,
+            Err(Iso13400Error::UnsupportedPayload {
+                version: Version::ISO13400_2_2012,
+                payload_type: PayloadType::RespRoutingActive,
+            })
+        ));
+        encoder.set_versions(&[Version::ISO13400_2_2012]);
+        let ret = encoder.encode(
+            Message {
+                version: Version::ISO13400_2_2019,
+                payload: Payload::RespRoutingActive(resp),
+            },
+            &mut dst,
+        );
+        assert!(matches!(ret, Err(Iso13400Error::InvalidParam(_))));
+        assert!(dst.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_custom_payload() -> anyhow::Result<()> {
+        let mut registry = PayloadRegistry::new();
+        registry.register::<Counter>()?.register_raw(0xF002)?;
+        assert!(registry.register_raw(0x8001).is_err());
+
+        let mut encoder = MessageEncoder::default();
+        let message = Message {
+            version: Version::ISO13400_2_2012,
+            payload: Payload::from_custom(&Counter(0x1234)),
+        };
+        let mut dst = BytesMut::new();
+        assert!(encoder.encode(message.clone(), &mut dst).is_err());
+        encoder.set_registry(registry.clone());
+        encoder.encode(message, &mut dst)?;
+        assert_eq!(hex::encode(&dst), "02fdf001000000021234");
+
+        // the payload type is unknown without the registry
+        assert!(matches!(
+            Message::try_from(dst.as_ref()),
+            Err(Iso13400Error::InvalidPayloadType(0xF001))
+        ));
+        let message = Message::try_from((dst.as_ref(), &registry))?;
+        assert_eq!(message.payload.payload_type(), PayloadType::Custom(0xF001));
+        let message = MessageRef::try_from(dst.as_ref())?;
+        assert_eq!(message.payload_type(), PayloadType::Custom(0xF001));
+        assert!(message.to_message(&PayloadRegistry::new()).is_err());
+        // only the manufacturer-specific payload types
+        let data = hex::decode("02fd700000000000")?;
+        assert!(matches!(
+            MessageRef::try_from(data.as_slice()),
+            Err(Iso13400Error::InvalidPayloadType(0x7000))
+        ));
+        assert!(registry.register_raw(0x7000).is_err());
+        let mut decoder = MessageDecoder::default();
+        decoder.extend(&dst);
+        assert_header_error(
+            decoder.decode(),
+            (HeaderNegativeCode::UnknownPayloadTYpe, false),
+        );
+        assert!(decoder.decode()?.is_none());
+        assert_eq!(decoder.buffered(), 0);
+
+        decoder.set_registry(registry);
+        decoder.extend(&dst);
+        decoder.extend(&hex::decode("02fdf0020000000301020302fdf00100000000")?);
+        let message = decoder.decode()?.unwrap();
+        assert_eq!(
+            message.payload.to_custom::<Counter>().transpose()?,
+            Some(Counter(0x1234))
+        );
+        match decoder.decode()?.unwrap().payload {
+            Payload::Custom { payload_type, data } => {
+                assert_eq!(payload_type, 0xF002);
+                assert_eq!(data, vec![0x01, 0x02, 0x03]);
+            }
+            v => panic!("unexpected payload: {:?}", v),
+        }
+        // the data of the typed payload is invalid
+        assert_header_error(
+            decoder.decode(),
+            (HeaderNegativeCode::InvalidPayloadLength, true),
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_borrowed() -> anyhow::Result<()> {
+        let user_data = (0..0x0400).map(|i| i as u8).collect::<Vec<_>>();
+        let data: Vec<_> = diagnostic(user_data.clone()).into();
+
+        let mut decoder = MessageDecoder::default();
+        decoder.extend(&data);
+        decoder.extend(&data[..10]);
+        let frame = decoder.decode_frame()?.unwrap();
+        assert!(decoder.decode_frame()?.is_none());
+        let message = MessageRef::try_from(frame.as_ref())?;
+        assert_eq!(message.version(), Version::ISO13400_2_2012);
+        assert_eq!(message.payload_type(), PayloadType::Diagnostic);
+        assert_eq!(message.as_bytes(), data.as_slice());
+
+        let diag = message.diagnostic().unwrap()?;
+        assert_eq!(diag.src_addr(), LogicAddress::from(0x0E00));
+        assert_eq!(diag.dst_addr(), LogicAddress::from(0x0DFF));
+        assert_eq!(diag.data(), user_data.as_slice());
+        // the user data is borrowed from the frame
+        assert_eq!(diag.data().as_ptr(), frame[12..].as_ptr());
+        assert!(matches!(
+            message.to_message(&PayloadRegistry::new())?.payload,
+            Payload::Diagnostic(v) if Diagnostic::from(diag) == v
+        ));
+
+        let alive: Vec<_> = Message {
+            version: Version::ISO13400_2_2012,
+            payload: Payload::ReqAliveCheck(request::AliveCheck),
+        }
+        .into();
+        assert!(MessageRef::try_from(alive.as_slice())?
+            .diagnostic()
+            .is_none());
+        assert!(matches!(
+            MessageRef::try_from(&data[..data.len() - 1]),
+            Err(Iso13400Error::InvalidPayloadLength { .. })
+        ));
+
+        let mut dst = BytesMut::new();
+        let encoder = MessageEncoder::default();
+        encoder.encode_diagnostic(Version::ISO13400_2_2012, diag, &mut dst)?;
+        assert_eq!(dst.as_ref(), data.as_slice());
+        dst.clear();
+        encoder.encode(diagnostic(user_data.clone()), &mut dst)?;
+        assert_eq!(dst.as_ref(), data.as_slice());
+        dst.clear();
+        message.encode(&mut dst);
+        assert_eq!(dst.as_ref(), data.as_slice());
+
+        let owned = Diagnostic::from(diag);
+        let diag = DiagnosticRef::from(&owned);
+        // the addresses are taken in the order of the wire
+        let other = DiagnosticRef::new(owned.src_addr(), owned.dst_addr(), owned.data());
+        assert_eq!(other.src_addr(), owned.src_addr());
+        assert_eq!(other.dst_addr(), owned.dst_addr());
+        assert_eq!(other, diag);
+        assert!(matches!(
+            encoder.encode_diagnostic(Version::Default, diag, &mut dst),
+            Err(Iso13400Error::UnsupportedPayload { .. })
+        ));
+        let encoder = MessageEncoder::new(0x0100);
+        assert!(matches!(
+            encoder.encode_diagnostic(Version::ISO13400_2_2012, diag, &mut dst),
+            Err(Iso13400Error::InvalidParam(_))
+        ));
+
+        Ok(())
+    }
+}
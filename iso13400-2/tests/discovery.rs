@@ -0,0 +1,164 @@
+#[cfg(test)]
+mod tests {
+    use iso13400_2::{
+        discovery::{Discovery, DiscoveryRequest, VehicleAnnouncement},
+        response, DoIpTiming, Eid, FurtherAction, Gid, Iso13400Error, LogicAddress, Message,
+        Payload, Version,
+    };
+    use std::net::SocketAddr;
+    use tokio::net::UdpSocket;
+
+    const VIN: &str = "WDD2040001A000001";
+
+    fn vehicle(vin: &str, address: u16, eid: u64) -> anyhow::Result<response::VehicleID> {
+        Ok(response::VehicleID::new(
+            vin.into(),
+            LogicAddress::from(address),
+            Eid::new(eid)?,
+            Gid::new(0x110011001100)?,
+            FurtherAction::NoAction,
+            None,
+        )?)
+    }
+
+    async fn announce(
+        socket: &UdpSocket,
+        target: SocketAddr,
+        vehicle: response::VehicleID,
+    ) -> anyhow::Result<()> {
+        let data: Vec<_> = Message {
+            version: Version::ISO13400_2_2012,
+            payload: Payload::RespVehicleId(vehicle),
+        }
+        .into();
+        socket.send_to(&data, target).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_identify() -> anyhow::Result<()> {
+        let entity = UdpSocket::bind("127.0.0.1:0").await?;
+        let entity_addr = entity.local_addr()?;
+        let task = tokio::spawn(async move {
+            let mut buffer = vec![0; 1024];
+            let (size, tester) = entity.recv_from(&mut buffer).await?;
+            let message = Message::try_from(&buffer[..size])?;
+            assert_eq!(message.version, Version::Default);
+            match message.payload {
+                Payload::ReqVehicleWithVIN(v) => assert_eq!(v.vin(), VIN),
+                _ => panic!("unexpected message: {:?}", message),
+            }
+
+            // the same entity responds 3 times, another entity once
+            for _ in 0..3 {
+                announce(&entity, tester, vehicle(VIN, 0x1001, 0x001100110011)?).await?;
+            }
+            announce(&entity, tester, vehicle(VIN, 0x1002, 0x001100110012)?).await?;
+            entity.send_to(&[0x02, 0xFD], tester).await?;
+
+            anyhow::Ok(())
+        });
+
+        let mut discovery = Discovery::bind("127.0.0.1:0".parse()?).await?;
+        discovery.set_timing(DoIpTiming {
+            ctrl: 300,
+            ..Default::default()
+        });
+        let results = discovery
+            .identify(entity_addr, DiscoveryRequest::Vin(VIN.into()))
+            .await?;
+        task.await??;
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].addr, entity_addr);
+        assert_eq!(results[0].vehicle.address(), LogicAddress::from(0x1001));
+        assert_eq!(results[1].vehicle.address(), LogicAddress::from(0x1002));
+        assert!(discovery
+            .identify(entity_addr, DiscoveryRequest::Vin("too short".into()))
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_listen() -> anyhow::Result<()> {
+        let mut discovery = Discovery::bind("127.0.0.1:0".parse()?).await?;
+        discovery.set_timing(DoIpTiming {
+            vehicle_discovery: 300,
+            ..Default::default()
+        });
+        let target = discovery.local_addr()?;
+
+        let entity = UdpSocket::bind("127.0.0.1:0").await?;
+        let entity_addr = entity.local_addr()?;
+        let task = tokio::spawn(async move {
+            for _ in 0..3 {
+                announce(&entity, target, vehicle(VIN, 0x1001, 0x001100110011)?).await?;
+            }
+            anyhow::Ok(())
+        });
+
+        let results = discovery.listen().await?;
+        task.await??;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].ip(), entity_addr.ip());
+        assert_eq!(results[0].vehicle.vin(), VIN);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dual_stack() -> anyhow::Result<()> {
+        let mut discovery = Discovery::bind("[::]:0".parse()?).await?;
+        discovery.set_timing(DoIpTiming {
+            ctrl: 300,
+            ..Default::default()
+        });
+
+        for (addr, eid) in [("127.0.0.1:0", 0x001100110011), ("[::1]:0", 0x001100110012)] {
+            let entity = UdpSocket::bind(addr).await?;
+            let entity_addr = entity.local_addr()?;
+            let task = tokio::spawn(async move {
+                let mut buffer = vec![0; 1024];
+                let (_, tester) = entity.recv_from(&mut buffer).await?;
+                announce(&entity, tester, vehicle(VIN, 0x1001, eid)?).await?;
+                anyhow::Ok(())
+            });
+
+            let results = discovery
+                .identify(entity_addr, DiscoveryRequest::All)
+                .await?;
+            task.await??;
+            assert_eq!(results.len(), 1);
+            // the IPv4 entity is not seen as IPv4-mapped IPv6 address
+            assert_eq!(results[0].addr, entity_addr);
+            assert_eq!(results[0].vehicle.eid(), Eid::new(eid)?);
+        }
+
+        let discovery = Discovery::bind("127.0.0.1:0".parse()?).await?;
+        assert!(matches!(
+            discovery.multicast(DiscoveryRequest::All, 0).await,
+            Err(Iso13400Error::InvalidParam(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_link_local() -> anyhow::Result<()> {
+        let announcement = VehicleAnnouncement {
+            addr: "[fe80::1%2]:49152".parse()?,
+            vehicle: vehicle(VIN, 0x1001, 0x001100110011)?,
+        };
+        let addr = announcement.tcp_addr();
+        assert_eq!(addr, "[fe80::1%2]:13400".parse()?);
+        match addr {
+            SocketAddr::V6(v) => assert_eq!(v.scope_id(), 2),
+            _ => panic!("unexpected address: {}", addr),
+        }
+
+        Ok(())
+    }
+}
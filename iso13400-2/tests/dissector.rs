@@ -0,0 +1,271 @@
+#![cfg(feature = "dissector")]
+
+#[cfg(test)]
+mod tests {
+    use iso13400_2::{
+        dissector::{write_json_lines, Content, Dissector, Transport, Uds},
+        Iso13400Error, Payload, PayloadType,
+    };
+    use iso14229_1::Service;
+    use std::{
+        net::{IpAddr, SocketAddr},
+        time::Duration,
+    };
+
+    const TCP_SYN: u8 = 0x02;
+    const TCP_ACK: u8 = 0x10;
+    const TCP_PSH: u8 = 0x08;
+
+    fn ethernet(ether_type: u16, packet: Vec<u8>) -> Vec<u8> {
+        let mut data = vec![0x02, 0, 0, 0, 0, 0x01, 0x02, 0, 0, 0, 0, 0x02];
+        data.extend(ether_type.to_be_bytes());
+        data.extend(packet);
+        data
+    }
+
+    fn ip(src: SocketAddr, dst: SocketAddr, protocol: u8, segment: Vec<u8>) -> Vec<u8> {
+        match (src.ip(), dst.ip()) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) => {
+                let mut data = vec![0x45, 0];
+                data.extend(((20 + segment.len()) as u16).to_be_bytes());
+                data.extend([0, 0, 0x40, 0, 64, protocol, 0, 0]);
+                data.extend(src.octets());
+                data.extend(dst.octets());
+                data.extend(segment);
+                ethernet(0x0800, data)
+            }
+            (IpAddr::V6(src), IpAddr::V6(dst)) => {
+                let mut data = vec![0x60, 0, 0, 0];
+                data.extend((segment.len() as u16).to_be_bytes());
+                data.extend([protocol, 64]);
+                data.extend(src.octets());
+                data.extend(dst.octets());
+                data.extend(segment);
+                ethernet(0x86DD, data)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn tcp(src: SocketAddr, dst: SocketAddr, seq: u32, flags: u8, payload: &str) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend(src.port().to_be_bytes());
+        data.extend(dst.port().to_be_bytes());
+        data.extend(seq.to_be_bytes());
+        data.extend([0, 0, 0, 0, 0x50, flags, 0xFF, 0xFF, 0, 0, 0, 0]);
+        data.extend(hex::decode(payload).unwrap());
+        ip(src, dst, 6, data)
+    }
+
+    fn udp(src: SocketAddr, dst: SocketAddr, payload: &str) -> Vec<u8> {
+        let payload = hex::decode(payload).unwrap();
+        let mut data = Vec::new();
+        data.extend(src.port().to_be_bytes());
+        data.extend(dst.port().to_be_bytes());
+        data.extend(((8 + payload.len()) as u16).to_be_bytes());
+        data.extend([0, 0]);
+        data.extend(payload);
+        ip(src, dst, 17, data)
+    }
+
+    /// pcap in little endian with microsecond timestamps
+    fn pcap(frames: &[(u64, Vec<u8>)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend(0xA1B2C3D4u32.to_le_bytes());
+        data.extend(2u16.to_le_bytes());
+        data.extend(4u16.to_le_bytes());
+        data.extend([0; 8]);
+        data.extend(0xFFFFu32.to_le_bytes());
+        data.extend(1u32.to_le_bytes());
+        for (micros, frame) in frames {
+            data.extend(((micros / 1_000_000) as u32).to_le_bytes());
+            data.extend(((micros % 1_000_000) as u32).to_le_bytes());
+            data.extend((frame.len() as u32).to_le_bytes());
+            data.extend((frame.len() as u32).to_le_bytes());
+            data.extend(frame);
+        }
+        data
+    }
+
+    fn pcapng_block(block_type: u32, body: Vec<u8>) -> Vec<u8> {
+        let length = (12 + body.len().div_ceil(4) * 4) as u32;
+        let mut data = Vec::new();
+        data.extend(block_type.to_be_bytes());
+        data.extend(length.to_be_bytes());
+        data.extend(&body);
+        data.resize(length as usize - 4, 0);
+        data.extend(length.to_be_bytes());
+        data
+    }
+
+    /// pcapng in big endian with nanosecond timestamps
+    fn pcapng(frames: &[(u64, Vec<u8>)]) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend(0x1A2B3C4Du32.to_be_bytes());
+        header.extend([0, 1, 0, 0]);
+        header.extend(u64::MAX.to_be_bytes());
+        let mut data = pcapng_block(0x0A0D0D0A, header);
+
+        let mut interface = vec![0, 1, 0, 0, 0, 0, 0xFF, 0xFF];
+        // if_tsresol: 10^-9
+        interface.extend([0, 9, 0, 1, 9, 0, 0, 0]);
+        interface.extend([0; 4]);
+        data.extend(pcapng_block(1, interface));
+
+        for (nanos, frame) in frames {
+            let mut packet = Vec::new();
+            packet.extend(0u32.to_be_bytes());
+            packet.extend(((nanos >> 32) as u32).to_be_bytes());
+            packet.extend((*nanos as u32).to_be_bytes());
+            packet.extend((frame.len() as u32).to_be_bytes());
+            packet.extend((frame.len() as u32).to_be_bytes());
+            packet.extend(frame);
+            data.extend(pcapng_block(6, packet));
+        }
+        data
+    }
+
+    #[test]
+    fn test_tcp_reassembly() -> anyhow::Result<()> {
+        let tester: SocketAddr = "10.0.0.2:50000".parse()?;
+        let entity: SocketAddr = "10.0.0.1:13400".parse()?;
+        let routing = "02fd0005000000070e000000000000";
+        let diag = "02fd8001000000060e0010011003";
+
+        let capture = pcap(&[
+            (1_000_001, tcp(tester, entity, 1000, TCP_SYN, "")),
+            (1_100_000, tcp(tester, entity, 1001, TCP_PSH, routing)),
+            // out of order
+            (
+                1_200_000,
+                tcp(tester, entity, 1001 + 15 + 5, TCP_PSH, &diag[10..]),
+            ),
+            (
+                1_300_000,
+                tcp(tester, entity, 1001 + 15, TCP_PSH, &diag[..10]),
+            ),
+            // retransmission
+            (
+                1_400_000,
+                tcp(tester, entity, 1001 + 15, TCP_PSH, &diag[..10]),
+            ),
+            (
+                1_500_000,
+                tcp(
+                    entity,
+                    tester,
+                    5000,
+                    TCP_ACK | TCP_PSH,
+                    "02fd80020000000510010e0000\
+                     02fd80010000000a10010e005003003201f4",
+                ),
+            ),
+            // not DoIP
+            (
+                1_600_000,
+                tcp(tester, "10.0.0.1:80".parse()?, 1, TCP_PSH, "02fd"),
+            ),
+        ]);
+
+        let mut dissector = Dissector::new();
+        dissector.set_uds(Some(Default::default()));
+        let events = dissector.dissect(&capture)?;
+        assert_eq!(events.len(), 4);
+        assert!(events.iter().all(|v| v.transport == Transport::Tcp));
+
+        assert_eq!(events[0].timestamp, Duration::from_micros(1_100_000));
+        assert_eq!(events[0].src, tester);
+        match &events[0].content {
+            Content::Message { message, uds } => {
+                assert_eq!(
+                    message.payload.payload_type(),
+                    PayloadType::ReqRoutingActive
+                );
+                assert!(uds.is_none());
+            }
+            v => panic!("unexpected content: {:?}", v),
+        }
+
+        assert_eq!(events[1].timestamp, Duration::from_micros(1_300_000));
+        match &events[1].content {
+            Content::Message {
+                message,
+                uds: Some(Ok(Uds::Request(req))),
+            } => {
+                assert!(matches!(message.payload, Payload::Diagnostic(_)));
+                assert_eq!(req.service(), Service::SessionCtrl);
+            }
+            v => panic!("unexpected content: {:?}", v),
+        }
+
+        assert_eq!(events[2].src, entity);
+        match &events[2].content {
+            Content::Message { message, uds } => {
+                assert_eq!(
+                    message.payload.payload_type(),
+                    PayloadType::RespDiagPositive
+                );
+                assert!(uds.is_none());
+            }
+            v => panic!("unexpected content: {:?}", v),
+        }
+        match &events[3].content {
+            Content::Message {
+                uds: Some(Ok(Uds::Response(resp))),
+                ..
+            } => {
+                assert_eq!(resp.service(), Service::SessionCtrl);
+                assert!(!resp.is_negative());
+            }
+            v => panic!("unexpected content: {:?}", v),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_lines() -> anyhow::Result<()> {
+        let tester: SocketAddr = "[fe80::2]:50001".parse()?;
+        let entity: SocketAddr = "[ff02::1]:13400".parse()?;
+        let capture = pcapng(&[
+            (
+                1_700_000_000_123_456_789,
+                udp(tester, entity, "ff00000100000000"),
+            ),
+            (
+                1_700_000_001_000_000_000,
+                udp(tester, entity, "02ff000100000000"),
+            ),
+        ]);
+
+        let events = Dissector::new().dissect(&capture)?;
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[0].timestamp,
+            Duration::new(1_700_000_000, 123_456_789)
+        );
+        assert_eq!(events[0].transport, Transport::Udp);
+        assert!(matches!(events[1].content, Content::Error(_)));
+
+        let mut output = Vec::new();
+        write_json_lines(&events, &mut output)?;
+        let lines = String::from_utf8(output)?;
+        let lines = lines
+            .lines()
+            .map(serde_json::from_str::<serde_json::Value>)
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["transport"], "udp");
+        assert_eq!(lines[0]["src"], "[fe80::2]:50001");
+        assert_eq!(lines[0]["payload_type"], "ReqVehicleId");
+        assert_eq!(lines[0]["data"], "ff00000100000000");
+        assert!(lines[1]["error"].is_string());
+
+        assert!(matches!(
+            Dissector::new().dissect(&[0x00, 0x01, 0x02, 0x03]),
+            Err(Iso13400Error::InvalidCapture(_))
+        ));
+
+        Ok(())
+    }
+}
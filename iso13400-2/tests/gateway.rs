@@ -0,0 +1,209 @@
+#![cfg(feature = "gateway")]
+
+#[cfg(test)]
+mod tests {
+    use iso13400_2::{
+        client::{ClientConfig, DoIpClient},
+        gateway::{CanGateway, GatewayConfig},
+        server::{DiagnosticHandler, DoIpEntity, EntityConfig},
+        Diagnostic, DiagnosticNegativeCode, Eid, Gid, Iso13400Error, LogicAddress,
+        RoutingActiveType, Version,
+    };
+    use iso15765_2::{
+        can::{Address, AddressType, CanIsoTp, CannelloniDevice, CannelloniFrame},
+        IsoTp,
+    };
+    use rs_can::{CanFrame, CanId, DeviceBuilder};
+    use std::net::SocketAddr;
+
+    const CHANNEL: u8 = 0;
+    const VIN: &str = "WDD2040001A000001";
+    const ENTITY: u16 = 0x1001;
+    const ECU1: u16 = 0x1010;
+    const ECU2: u16 = 0x1011;
+    const GROUP: u16 = 0xE400;
+    const TESTER: u16 = 0x0E80;
+
+    fn device_pair() -> anyhow::Result<(CannelloniDevice, CannelloniDevice)> {
+        // bind to port 0 and read back the local addresses
+        let any: SocketAddr = "127.0.0.1:0".parse()?;
+
+        let mut builder = DeviceBuilder::new();
+        builder.add_config(CHANNEL, CannelloniDevice::channel_config(any, any));
+        let device_a = builder.build::<CannelloniDevice>()?;
+        let addr_a = device_a.local_addr(CHANNEL)?;
+
+        let mut builder = DeviceBuilder::new();
+        builder.add_config(CHANNEL, CannelloniDevice::channel_config(any, addr_a));
+        let device_b = builder.build::<CannelloniDevice>()?;
+        let addr_b = device_b.local_addr(CHANNEL)?;
+
+        device_a.set_remote_addr(CHANNEL, addr_b)?;
+
+        Ok((device_a, device_b))
+    }
+
+    fn entity_config() -> anyhow::Result<EntityConfig> {
+        let mut config = EntityConfig::new(
+            LogicAddress::from(ENTITY),
+            VIN,
+            Eid::new(0x001100110011)?,
+            Gid::new(0x110011001100)?,
+        )?;
+        config.version = Version::ISO13400_2_2012;
+        config.tcp_addr = "127.0.0.1:0".parse()?;
+        config.udp_addr = "127.0.0.1:0".parse()?;
+        Ok(config)
+    }
+
+    fn client_config() -> ClientConfig {
+        let mut config = ClientConfig::new(LogicAddress::from(TESTER));
+        config.version = Version::ISO13400_2_2012;
+        config.timing.diagnostic_message = 500;
+        config.diag_timeout = 2_000;
+        config
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_gateway() -> anyhow::Result<()> {
+        let (device_a, device_b) = device_pair()?;
+        let ecu1 = Address {
+            tx_id: 0x7E8,
+            rx_id: 0x7E0,
+            fid: 0x7DF,
+        };
+        let ecu2 = Address {
+            tx_id: 0x7E9,
+            rx_id: 0x7E1,
+            fid: 0x7DF,
+        };
+
+        let mut isotp = CanIsoTp::new(device_a, CHANNEL, Address::default(), false).await;
+        isotp.start(100).await;
+        let mut config = GatewayConfig::default();
+        config
+            .add_route(
+                LogicAddress::from(ECU1),
+                Address {
+                    tx_id: ecu1.rx_id,
+                    rx_id: ecu1.tx_id,
+                    fid: ecu1.fid,
+                },
+            )
+            .add_route(
+                LogicAddress::from(ECU2),
+                Address {
+                    tx_id: ecu2.rx_id,
+                    rx_id: ecu2.tx_id,
+                    fid: ecu2.fid,
+                },
+            )
+            .add_group(
+                LogicAddress::from(GROUP),
+                0x7DF,
+                vec![LogicAddress::from(ECU1), LogicAddress::from(ECU2)],
+            );
+        config.timeout = 300;
+        let gateway = CanGateway::new(isotp, config).await?;
+        let entity = DoIpEntity::bind(entity_config()?, gateway).await?;
+
+        // ECU1 is served by ISO-TP, ECU2 answers the functional request by a raw frame
+        let mut server = CanIsoTp::new(device_b, CHANNEL, ecu1, true).await;
+        server.start(100).await;
+        let ecu = tokio::spawn(async move {
+            let data = server.wait_data(2_000).await?;
+            assert_eq!(data.as_ref(), &[0x22, 0xF1, 0x90]);
+            let mut resp = vec![0x62, 0xF1, 0x90];
+            resp.extend_from_slice(VIN.as_bytes());
+            server.transmit(AddressType::Physical, resp).await?;
+
+            let data = server.wait_data(2_000).await?;
+            assert_eq!(data.as_ref(), &[0x3E, 0x00]);
+            server.transmit(AddressType::Physical, [0x7E, 0x00]).await?;
+            let mut frame =
+                CannelloniFrame::new(CanId::from_bits(ecu2.tx_id, None), &[0x02, 0x7E, 0x00])
+                    .unwrap();
+            frame.set_channel(CHANNEL);
+            server.transmitter().send(frame).await?;
+
+            anyhow::Ok(server)
+        });
+
+        let client = DoIpClient::connect_addr(entity.tcp_addr(), client_config()).await?;
+        client
+            .routing_activation(RoutingActiveType::Default, None)
+            .await?;
+
+        // physical request with multi-frame response
+        let resp = client
+            .diagnostic(LogicAddress::from(ECU1), vec![0x22, 0xF1, 0x90])
+            .await?;
+        assert_eq!(resp.src_addr(), LogicAddress::from(ECU1));
+        assert_eq!(&resp.data()[..3], &[0x62, 0xF1, 0x90]);
+        assert_eq!(&resp.data()[3..], VIN.as_bytes());
+
+        // functional request answered by all members
+        let resp = client
+            .diagnostic(LogicAddress::from(GROUP), vec![0x3E, 0x00])
+            .await?;
+        assert_eq!(resp.src_addr(), LogicAddress::from(ECU1));
+        assert_eq!(resp.data(), &vec![0x7E, 0x00]);
+        let resp = client.receive_diagnostic(1_000).await?;
+        assert_eq!(resp.src_addr(), LogicAddress::from(ECU2));
+        assert_eq!(resp.data(), &vec![0x7E, 0x00]);
+        ecu.await??.stop().await;
+
+        let ret = client
+            .diagnostic(LogicAddress::from(0x1020), vec![0x10, 0x01])
+            .await;
+        assert!(matches!(
+            ret,
+            Err(Iso13400Error::DiagnosticNegative(
+                DiagnosticNegativeCode::UnknownTargetAddress
+            ))
+        ));
+        let ret = client
+            .diagnostic(LogicAddress::from(ECU1), vec![0x2E; 0x1000])
+            .await;
+        assert!(matches!(
+            ret,
+            Err(Iso13400Error::DiagnosticNegative(
+                DiagnosticNegativeCode::DiagnosticMessageTooLarge
+            ))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_queue_released() -> anyhow::Result<()> {
+        let (device_a, _device_b) = device_pair()?;
+        let mut isotp = CanIsoTp::new(device_a, CHANNEL, Address::default(), false).await;
+        isotp.start(100).await;
+        let mut config = GatewayConfig::default();
+        config.add_route(LogicAddress::from(ECU1), Address::default());
+        config.queue_size = 1;
+        let gateway = CanGateway::new(isotp, config).await?;
+
+        let diag = Diagnostic::new(
+            LogicAddress::from(TESTER),
+            LogicAddress::from(ECU1),
+            vec![0x10, 0x01],
+        );
+        gateway.validate(&diag).await.unwrap();
+        assert_eq!(
+            gateway.validate(&diag).await,
+            Err(DiagnosticNegativeCode::OutOfMemory)
+        );
+
+        // the socket is closed after validation
+        gateway.closed(LogicAddress::from(TESTER)).await;
+        gateway.validate(&diag).await.unwrap();
+
+        // the positive acknowledge isn't sent
+        gateway.discard(&diag).await;
+        gateway.validate(&diag).await.unwrap();
+
+        Ok(())
+    }
+}
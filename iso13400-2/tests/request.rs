@@ -175,12 +175,19 @@ fn test_diag() -> anyhow::Result<()> {
     let msg = Message::try_from(source.as_ref())?;
     assert_eq!(msg.version, Version::ISO13400_2_2012);
     match &msg.payload {
-        Payload::Diagnostic(v) => assert_eq!(*v, payload),
+        Payload::Diagnostic(v) => {
+            // the source address comes first on the wire
+            assert_eq!(v.src_addr(), LogicAddress::from(0x0E00));
+            assert_eq!(v.dst_addr(), LogicAddress::from(0x0DFF));
+            assert_eq!(*v, payload)
+        }
         _ => panic!("Wrong payload type"),
     }
 
     let data: Vec<_> = msg.into();
     assert_eq!(data, source);
+    assert_eq!(&data[8..10], &[0x0E, 0x00]);
+    assert_eq!(&data[10..12], &[0x0D, 0xFF]);
 
     Ok(())
 }
@@ -0,0 +1,131 @@
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use iso13400_2::{
+        request, response, ActiveCode, Diagnostic, DiagnosticNegativeCode, DiagnosticPositiveCode,
+        Eid, FurtherAction, Gid, HeaderNegativeCode, LogicAddress, Message, NodeType, Payload,
+        PowerMode, RoutingActiveType, SyncStatus, Version,
+    };
+    use serde_json::json;
+
+    const VIN: &str = "WDD2040001A000001";
+
+    fn payloads() -> anyhow::Result<Vec<Payload>> {
+        let tester = LogicAddress::from(0x0E80);
+        let entity = LogicAddress::from(0x1001);
+        Ok(vec![
+            Payload::RespHeaderNegative(response::HeaderNegative::new(
+                HeaderNegativeCode::UnknownPayloadTYpe,
+            )),
+            Payload::ReqVehicleId(request::VehicleID),
+            Payload::ReqVehicleWithEid(request::VehicleIDWithEID::new(Eid::new(0x001100110011)?)),
+            Payload::ReqVehicleWithVIN(request::VehicleIDWithVIN::new(VIN)?),
+            Payload::RespVehicleId(response::VehicleID::new(
+                VIN.to_owned(),
+                entity,
+                Eid::new(0x001100110011)?,
+                Gid::new(0x110011001100)?,
+                FurtherAction::VMSpecific(0x20),
+                Some(SyncStatus::VINorGIDNotSync),
+            )?),
+            Payload::ReqRoutingActive(request::RoutingActive::new(
+                tester,
+                RoutingActiveType::CentralSecurity,
+                Some(0x12345678),
+            )),
+            Payload::RespRoutingActive(response::RoutingActive::new(
+                tester,
+                entity,
+                ActiveCode::Reserved(0x20),
+                None,
+            )),
+            Payload::ReqAliveCheck(request::AliveCheck),
+            Payload::RespAliveCheck(response::AliveCheck::new(tester)),
+            Payload::ReqEntityStatus(request::EntityStatus),
+            Payload::ReqDiagPowerMode(request::DiagnosticPowerMode),
+            Payload::RespEntityStatus(response::EntityStatus::new(
+                NodeType::Gateway,
+                2,
+                1,
+                Some(0x0FFF),
+            )),
+            Payload::RespDiagPowerMode(response::DiagnosticPowerMode::new(PowerMode::Ready)),
+            Payload::Diagnostic(Diagnostic::new(tester, entity, vec![0x22, 0xF1, 0x90])),
+            Payload::RespDiagPositive(response::DiagnosticPositive::new(
+                entity,
+                tester,
+                DiagnosticPositiveCode::Confirm,
+                vec![0x22],
+            )),
+            Payload::RespDiagNegative(response::DiagnosticNegative::new(
+                entity,
+                tester,
+                DiagnosticNegativeCode::UnknownTargetAddress,
+                vec![],
+            )),
+            Payload::Custom {
+                payload_type: 0xF001,
+                data: vec![0xAB, 0xCD],
+            },
+        ])
+    }
+
+    #[test]
+    fn test_round_trip() -> anyhow::Result<()> {
+        for payload in payloads()? {
+            let message = Message {
+                version: Version::ISO13400_2_2019,
+                payload,
+            };
+            let text = serde_json::to_string(&message)?;
+            assert_eq!(serde_json::from_str::<Message>(&text)?, message, "{}", text);
+            let value = serde_json::to_value(&message)?;
+            assert_eq!(serde_json::from_value::<Message>(value)?, message);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_readable() -> anyhow::Result<()> {
+        let message = Message {
+            version: Version::ISO13400_2_2012,
+            payload: Payload::Diagnostic(Diagnostic::new(
+                LogicAddress::from(0x0E80),
+                LogicAddress::from(0x1001),
+                vec![0x22, 0xF1, 0x90],
+            )),
+        };
+        assert_eq!(
+            serde_json::to_value(&message)?,
+            json!({
+                "version": "ISO13400_2_2012",
+                "payload": {
+                    "type": "Diagnostic",
+                    "value": {
+                        "src_addr": "0x0E80",
+                        "dst_addr": "0x1001",
+                        "data": "22f190",
+                    },
+                },
+            })
+        );
+
+        let resp = response::VehicleID::new(
+            VIN.to_owned(),
+            LogicAddress::from(0x1001),
+            Eid::new(0x001100110011)?,
+            Gid::new(0x110011001100)?,
+            FurtherAction::NoAction,
+            None,
+        )?;
+        let value = serde_json::to_value(&resp)?;
+        assert_eq!(value["eid"], "001100110011");
+        assert_eq!(value["gid"], "110011001100");
+        assert_eq!(value["further_act"], "NoAction");
+
+        assert!(serde_json::from_value::<Eid>(json!("0011")).is_err());
+        assert!(serde_json::from_value::<LogicAddress>(json!("0xG000")).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,727 @@
+#[cfg(test)]
+mod tests {
+    use iso13400_2::{
+        client::{ClientConfig, DoIpClient},
+        discovery::{Discovery, DiscoveryRequest},
+        request,
+        server::{
+            aggregate_power_mode, AcceptAll, Action, Activation, DiagnosticHandler, DoIpEntity,
+            EntityConfig, Firewall, LiveStatus, Responder, RoutingActivationPolicy, Rule,
+            SourceClass, SubNodePowerModes, Target,
+        },
+        ActiveCode, Diagnostic, DiagnosticNegativeCode, DoIpTiming, Eid, Gid, HeaderNegativeCode,
+        Iso13400Error, LogicAddress, Message, Payload, PayloadRegistry, PowerMode,
+        RoutingActiveType, Version,
+    };
+    use std::{
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpStream, UdpSocket},
+    };
+
+    const VIN: &str = "WDD2040001A000001";
+    const ENTITY: u16 = 0x1001;
+    const TESTER: u16 = 0x0E80;
+
+    struct EchoHandler;
+
+    #[async_trait::async_trait]
+    impl DiagnosticHandler for EchoHandler {
+        async fn validate(&self, diag: &Diagnostic) -> Result<(), DiagnosticNegativeCode> {
+            match u16::from(diag.dst_addr()) {
+                ENTITY => Ok(()),
+                _ => Err(DiagnosticNegativeCode::UnknownTargetAddress),
+            }
+        }
+
+        async fn handle(&self, diag: Diagnostic, responder: Responder) {
+            let mut data = diag.data().clone();
+            data[0] += 0x40;
+            responder.respond(diag.dst_addr(), data).unwrap();
+        }
+
+        async fn handle_custom(&self, payload_type: u16, mut data: Vec<u8>, responder: Responder) {
+            data.reverse();
+            responder
+                .send(Payload::Custom { payload_type, data })
+                .unwrap();
+        }
+    }
+
+    /// The handler which reserves a place for every validated message.
+    #[derive(Default)]
+    struct ReservingHandler {
+        reserved: Arc<AtomicUsize>,
+        closed: Arc<Mutex<Vec<LogicAddress>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl DiagnosticHandler for ReservingHandler {
+        async fn validate(&self, _: &Diagnostic) -> Result<(), DiagnosticNegativeCode> {
+            self.reserved.fetch_add(1, Ordering::SeqCst);
+            // the test equipment closes the socket meanwhile
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Ok(())
+        }
+
+        async fn handle(&self, _: Diagnostic, _: Responder) {
+            self.reserved.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        async fn discard(&self, _: &Diagnostic) {
+            self.reserved.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        async fn closed(&self, tester: LogicAddress) {
+            self.closed.lock().unwrap().push(tester);
+        }
+    }
+
+    const SEED: u32 = 0x12345678;
+    const MASK: u32 = 0xA5A5A5A5;
+
+    /// The central security needs the key of the seed, the default activation is confirmed
+    /// on the second request.
+    #[derive(Default)]
+    struct SecurityPolicy {
+        confirmed: AtomicBool,
+    }
+
+    #[async_trait::async_trait]
+    impl RoutingActivationPolicy for SecurityPolicy {
+        async fn activate(&self, req: &request::RoutingActive, _: bool) -> Activation {
+            match req.active() {
+                RoutingActiveType::CentralSecurity => match req.user_def() {
+                    Some(key) if key == SEED ^ MASK => {
+                        Activation::new(ActiveCode::Success, Some(key))
+                    }
+                    _ => Activation::new(ActiveCode::WithoutAuth, Some(SEED)),
+                },
+                RoutingActiveType::Default => match self.confirmed.swap(true, Ordering::Relaxed) {
+                    true => ActiveCode::Success.into(),
+                    false => ActiveCode::NeedConfirm.into(),
+                },
+                _ => ActiveCode::VehicleRefused.into(),
+            }
+        }
+    }
+
+    fn config() -> anyhow::Result<EntityConfig> {
+        let mut config = EntityConfig::new(
+            LogicAddress::from(ENTITY),
+            VIN,
+            Eid::new(0x001100110011)?,
+            Gid::new(0x110011001100)?,
+        )?;
+        config.version = Version::ISO13400_2_2012;
+        config.tcp_addr = "127.0.0.1:0".parse()?;
+        config.udp_addr = "127.0.0.1:0".parse()?;
+        config.whitelist = vec![LogicAddress::from(TESTER), LogicAddress::from(TESTER + 1)];
+        config.max_data_size = 0x0FFF;
+        config.timing.alive_check = 200;
+        config.timing.initial_inactivity = 300;
+        config.payloads.register_raw(0xF001)?;
+        Ok(config)
+    }
+
+    fn client_config(tester: u16) -> ClientConfig {
+        let mut config = ClientConfig::new(LogicAddress::from(tester));
+        config.version = Version::ISO13400_2_2012;
+        config.timing.ctrl = 1_000;
+        config.timing.diagnostic_message = 500;
+        config.diag_timeout = 500;
+        config
+    }
+
+    async fn read(stream: &mut TcpStream) -> anyhow::Result<Option<Message>> {
+        let mut header = [0; 8];
+        if stream.read_exact(&mut header).await.is_err() {
+            return Ok(None);
+        }
+        let length = u32::from_be_bytes(header[4..].try_into()?) as usize;
+        let mut data = header.to_vec();
+        data.resize(8 + length, 0);
+        stream.read_exact(&mut data[8..]).await?;
+
+        let mut registry = PayloadRegistry::new();
+        registry.register_raw(0xF001)?;
+
+        Ok(Some(Message::try_from((data.as_slice(), &registry))?))
+    }
+
+    async fn write(stream: &mut TcpStream, payload: Payload) -> anyhow::Result<()> {
+        let data: Vec<_> = Message {
+            version: Version::ISO13400_2_2012,
+            payload,
+        }
+        .into();
+        stream.write_all(&data).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_udp() -> anyhow::Result<()> {
+        let entity = DoIpEntity::bind(config()?, EchoHandler).await?;
+
+        let mut discovery = Discovery::bind("127.0.0.1:0".parse()?).await?;
+        discovery.set_timing(DoIpTiming {
+            ctrl: 200,
+            ..Default::default()
+        });
+        let results = discovery
+            .identify(entity.udp_addr(), DiscoveryRequest::All)
+            .await?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].vehicle.vin(), VIN);
+        assert_eq!(results[0].vehicle.address(), LogicAddress::from(ENTITY));
+        let results = discovery
+            .identify(
+                entity.udp_addr(),
+                DiscoveryRequest::Eid(Eid::new(0x001100110012)?),
+            )
+            .await?;
+        assert!(results.is_empty());
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await?;
+        let mut buffer = vec![0; 1024];
+        for (payload, hex) in [
+            (
+                Payload::ReqEntityStatus(request::EntityStatus),
+                "02fd40020000000701010000000fff",
+            ),
+            (
+                Payload::ReqDiagPowerMode(request::DiagnosticPowerMode),
+                "02fd40040000000101",
+            ),
+        ] {
+            let data: Vec<_> = Message {
+                version: Version::ISO13400_2_2012,
+                payload,
+            }
+            .into();
+            socket.send_to(&data, entity.udp_addr()).await?;
+            let (size, _) = socket.recv_from(&mut buffer).await?;
+            assert_eq!(hex::encode(&buffer[..size]), hex);
+        }
+
+        // the default version is only accepted by the vehicle identification requests
+        let data: Vec<_> = Message {
+            version: Version::Default,
+            payload: Payload::ReqEntityStatus(request::EntityStatus),
+        }
+        .into();
+        socket.send_to(&data, entity.udp_addr()).await?;
+        let (size, _) = socket.recv_from(&mut buffer).await?;
+        assert_eq!(hex::encode(&buffer[..size]), "02fd00000000000100");
+
+        // incorrect pattern
+        socket
+            .send_to(&[0x02, 0x02, 0x40, 0x01, 0, 0, 0, 0], entity.udp_addr())
+            .await?;
+        let (size, _) = socket.recv_from(&mut buffer).await?;
+        assert_eq!(hex::encode(&buffer[..size]), "02fd00000000000100");
+
+        assert_eq!(entity.config().power_mode, PowerMode::Ready);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_diagnostic() -> anyhow::Result<()> {
+        let entity = DoIpEntity::bind(config()?, EchoHandler).await?;
+
+        let client = DoIpClient::connect_addr(entity.tcp_addr(), client_config(0x0E00)).await?;
+        let ret = client
+            .routing_activation(RoutingActiveType::Default, None)
+            .await;
+        assert!(matches!(
+            ret,
+            Err(Iso13400Error::RoutingActivation(
+                ActiveCode::SourceAddressUnknown
+            ))
+        ));
+
+        let client = DoIpClient::connect_addr(entity.tcp_addr(), client_config(TESTER)).await?;
+        client
+            .routing_activation(RoutingActiveType::Default, None)
+            .await?;
+        assert_eq!(client.entity_address(), Some(LogicAddress::from(ENTITY)));
+        assert_eq!(entity.open_sockets(), 1);
+
+        let resp = client
+            .diagnostic(LogicAddress::from(ENTITY), vec![0x10, 0x03])
+            .await?;
+        assert_eq!(resp.src_addr(), LogicAddress::from(ENTITY));
+        assert_eq!(resp.dst_addr(), LogicAddress::from(TESTER));
+        assert_eq!(resp.data(), &vec![0x50, 0x03]);
+
+        let ret = client
+            .diagnostic(LogicAddress::from(ENTITY + 1), vec![0x10, 0x03])
+            .await;
+        assert!(matches!(
+            ret,
+            Err(Iso13400Error::DiagnosticNegative(
+                DiagnosticNegativeCode::UnknownTargetAddress
+            ))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_alive_check() -> anyhow::Result<()> {
+        let entity = DoIpEntity::bind(config()?, EchoHandler).await?;
+
+        // the client answers the alive check, so the socket is kept
+        let client = DoIpClient::connect_addr(entity.tcp_addr(), client_config(TESTER)).await?;
+        client
+            .routing_activation(RoutingActiveType::Default, None)
+            .await?;
+        let other = DoIpClient::connect_addr(entity.tcp_addr(), client_config(TESTER + 1)).await?;
+        let ret = other
+            .routing_activation(RoutingActiveType::Default, None)
+            .await;
+        assert!(matches!(
+            ret,
+            Err(Iso13400Error::RoutingActivation(ActiveCode::Activated))
+        ));
+        client.close().await?;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(entity.open_sockets(), 0);
+
+        // the socket without alive check response is closed
+        let mut stream = TcpStream::connect(entity.tcp_addr()).await?;
+        let req = request::RoutingActive::new(
+            LogicAddress::from(TESTER),
+            RoutingActiveType::Default,
+            None,
+        );
+        write(&mut stream, Payload::ReqRoutingActive(req)).await?;
+        match read(&mut stream).await? {
+            Some(Message {
+                payload: Payload::RespRoutingActive(resp),
+                ..
+            }) => assert_eq!(resp.active_code(), ActiveCode::Success),
+            v => panic!("unexpected message: {:?}", v),
+        }
+
+        let other = DoIpClient::connect_addr(entity.tcp_addr(), client_config(TESTER + 1)).await?;
+        other
+            .routing_activation(RoutingActiveType::Default, None)
+            .await?;
+        assert!(matches!(
+            read(&mut stream).await?,
+            Some(Message {
+                payload: Payload::ReqAliveCheck(_),
+                ..
+            })
+        ));
+        assert!(read(&mut stream).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_closed_after_validation() -> anyhow::Result<()> {
+        let handler = ReservingHandler::default();
+        let (reserved, closed) = (handler.reserved.clone(), handler.closed.clone());
+        let entity = DoIpEntity::bind(config()?, handler).await?;
+
+        let mut stream = TcpStream::connect(entity.tcp_addr()).await?;
+        let req = request::RoutingActive::new(
+            LogicAddress::from(TESTER),
+            RoutingActiveType::Default,
+            None,
+        );
+        write(&mut stream, Payload::ReqRoutingActive(req)).await?;
+        assert!(read(&mut stream).await?.is_some());
+        let diag = Diagnostic::new(
+            LogicAddress::from(TESTER),
+            LogicAddress::from(ENTITY),
+            vec![0x10, 0x01],
+        );
+        write(&mut stream, Payload::Diagnostic(diag)).await?;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(reserved.load(Ordering::SeqCst), 1);
+        drop(stream);
+
+        // the place is released by `handle` or `discard`, and the handler is told of the closing
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(entity.open_sockets(), 0);
+        assert_eq!(reserved.load(Ordering::SeqCst), 0);
+        assert_eq!(*closed.lock().unwrap(), vec![LogicAddress::from(TESTER)]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_inactivity() -> anyhow::Result<()> {
+        let entity = DoIpEntity::bind(config()?, EchoHandler).await?;
+
+        let mut stream = TcpStream::connect(entity.tcp_addr()).await?;
+        let ret = tokio::time::timeout(Duration::from_millis(1_000), read(&mut stream)).await?;
+        assert!(ret?.is_none());
+        assert_eq!(entity.open_sockets(), 0);
+
+        // diagnostic message without routing activation
+        let mut stream = TcpStream::connect(entity.tcp_addr()).await?;
+        let diag = Diagnostic::new(
+            LogicAddress::from(TESTER),
+            LogicAddress::from(ENTITY),
+            vec![0x10, 0x01],
+        );
+        write(&mut stream, Payload::Diagnostic(diag)).await?;
+        match read(&mut stream).await? {
+            Some(Message {
+                payload: Payload::RespDiagNegative(resp),
+                ..
+            }) => assert_eq!(resp.code(), DiagnosticNegativeCode::InvalidSourceAddress),
+            v => panic!("unexpected message: {:?}", v),
+        }
+        assert!(read(&mut stream).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_custom_payload() -> anyhow::Result<()> {
+        let entity = DoIpEntity::bind(config()?, EchoHandler).await?;
+
+        let mut stream = TcpStream::connect(entity.tcp_addr()).await?;
+        let req = request::RoutingActive::new(
+            LogicAddress::from(TESTER),
+            RoutingActiveType::Default,
+            None,
+        );
+        write(&mut stream, Payload::ReqRoutingActive(req)).await?;
+        assert!(read(&mut stream).await?.is_some());
+
+        // unregistered payload type
+        let payload = Payload::Custom {
+            payload_type: 0xF002,
+            data: vec![0x01, 0x02],
+        };
+        write(&mut stream, payload).await?;
+        match read(&mut stream).await? {
+            Some(Message {
+                payload: Payload::RespHeaderNegative(resp),
+                ..
+            }) => assert_eq!(resp.code(), HeaderNegativeCode::UnknownPayloadTYpe),
+            v => panic!("unexpected message: {:?}", v),
+        }
+
+        let payload = Payload::Custom {
+            payload_type: 0xF001,
+            data: vec![0x01, 0x02],
+        };
+        write(&mut stream, payload).await?;
+        match read(&mut stream).await? {
+            Some(Message {
+                payload: Payload::Custom { payload_type, data },
+                ..
+            }) => {
+                assert_eq!(payload_type, 0xF001);
+                assert_eq!(data, vec![0x02, 0x01]);
+            }
+            v => panic!("unexpected message: {:?}", v),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_routing_policy() -> anyhow::Result<()> {
+        let entity =
+            DoIpEntity::bind_with_policy(config()?, EchoHandler, SecurityPolicy::default()).await?;
+
+        let mut config = client_config(TESTER);
+        config.confirm_interval = 10;
+        let client = DoIpClient::connect_addr(entity.tcp_addr(), config).await?;
+        let ret = client
+            .routing_activation(RoutingActiveType::WWHODB, None)
+            .await;
+        assert!(matches!(
+            ret,
+            Err(Iso13400Error::RoutingActivation(ActiveCode::VehicleRefused))
+        ));
+
+        // the socket is kept for the confirmation
+        let client = DoIpClient::connect_addr(entity.tcp_addr(), config).await?;
+        let ret = client
+            .routing_activation(RoutingActiveType::Default, None)
+            .await;
+        assert!(matches!(
+            ret,
+            Err(Iso13400Error::RoutingActivation(ActiveCode::NeedConfirm))
+        ));
+        assert_eq!(client.entity_address(), None);
+        let ret = client
+            .send_diagnostic(LogicAddress::from(ENTITY), vec![0x10, 0x03])
+            .await;
+        assert!(matches!(ret, Err(Iso13400Error::RoutingInactive)));
+
+        let mut requests = 0;
+        client
+            .routing_activation_with(RoutingActiveType::Default, None, |_| {
+                requests += 1;
+                None
+            })
+            .await?;
+        assert_eq!(requests, 0);
+        assert_eq!(client.entity_address(), Some(LogicAddress::from(ENTITY)));
+        assert_eq!(entity.open_sockets(), 1);
+
+        // the central security with the seed and key in the OEM specific data
+        let other = DoIpClient::connect_addr(entity.tcp_addr(), client_config(TESTER + 1)).await?;
+        let ret = other
+            .routing_activation(RoutingActiveType::CentralSecurity, None)
+            .await;
+        assert!(matches!(
+            ret,
+            Err(Iso13400Error::RoutingActivation(ActiveCode::Activated))
+        ));
+        client.close().await?;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let other = DoIpClient::connect_addr(entity.tcp_addr(), config).await?;
+        let resp = other
+            .routing_activation_with(RoutingActiveType::CentralSecurity, None, |resp| {
+                assert_eq!(resp.active_code(), ActiveCode::WithoutAuth);
+                let seed = resp.user_def()?;
+                Some((RoutingActiveType::CentralSecurity, Some(seed ^ MASK)))
+            })
+            .await?;
+        assert_eq!(resp.user_def(), Some(SEED ^ MASK));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dual_stack() -> anyhow::Result<()> {
+        let announcement = UdpSocket::bind("[::1]:0").await?;
+        let mut config = config()?;
+        config.tcp_addr = "[::]:0".parse()?;
+        config.udp_addr = "[::]:0".parse()?;
+        config.max_sockets = 2;
+        config.announce_addr = Some(announcement.local_addr()?);
+        config.timing.announce_wait = 0;
+        config.timing.announce_num = 1;
+        let entity = DoIpEntity::bind(config, EchoHandler).await?;
+
+        // the vehicle announcement over IPv6
+        let mut buffer = vec![0; 1024];
+        let (size, _) = announcement.recv_from(&mut buffer).await?;
+        match Message::try_from(&buffer[..size])?.payload {
+            Payload::RespVehicleId(v) => assert_eq!(v.vin(), VIN),
+            v => panic!("unexpected payload: {:?}", v),
+        }
+
+        let port = entity.tcp_addr().port();
+        let mut clients = Vec::new();
+        for (ip, tester) in [("127.0.0.1", TESTER), ("::1", TESTER + 1)] {
+            let ip = ip.parse()?;
+            let mut discovery = Discovery::bind(SocketAddr::new(ip, 0)).await?;
+            discovery.set_timing(DoIpTiming {
+                ctrl: 300,
+                ..Default::default()
+            });
+            let target = SocketAddr::new(ip, entity.udp_addr().port());
+            let results = discovery.identify(target, DiscoveryRequest::All).await?;
+            assert_eq!(results.len(), 1);
+            assert_eq!(results[0].addr, target);
+
+            let client =
+                DoIpClient::connect_addr(SocketAddr::new(ip, port), client_config(tester)).await?;
+            client
+                .routing_activation(RoutingActiveType::Default, None)
+                .await?;
+            let resp = client
+                .diagnostic(LogicAddress::from(ENTITY), vec![0x10, 0x03])
+                .await?;
+            assert_eq!(resp.data(), &vec![0x50, 0x03]);
+            clients.push(client);
+        }
+        assert_eq!(entity.open_sockets(), clients.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_firewall_rules() {
+        let mut firewall = Firewall::new(Action::Deny(DiagnosticNegativeCode::UnknownNetwork));
+        firewall
+            .push(Rule::deny(
+                SourceClass::Prolonged,
+                Target::Any,
+                DiagnosticNegativeCode::TargetUnreachable,
+            ))
+            .push(Rule::allow(SourceClass::Legislated, Target::Functional))
+            .push(Rule::allow(
+                SourceClass::Client,
+                Target::Address(LogicAddress::from(ENTITY)),
+            ));
+
+        let active = RoutingActiveType::Default;
+        let functional = LogicAddress::from(0xE400);
+        assert!(firewall
+            .check(LogicAddress::from(0x0E00), functional, active)
+            .is_ok());
+        assert_eq!(
+            firewall.check(LogicAddress::from(0x0E80), functional, active),
+            Err(DiagnosticNegativeCode::UnknownNetwork)
+        );
+        assert!(firewall
+            .check(
+                LogicAddress::from(0x0F00),
+                LogicAddress::from(ENTITY),
+                active
+            )
+            .is_ok());
+        assert_eq!(
+            firewall.check(
+                LogicAddress::from(0x0F80),
+                LogicAddress::from(ENTITY),
+                active
+            ),
+            Err(DiagnosticNegativeCode::TargetUnreachable)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_firewall() -> anyhow::Result<()> {
+        let mut config = config()?;
+        config.max_sockets = 2;
+        config
+            .firewall
+            .push(
+                Rule::allow(
+                    SourceClass::Enhanced,
+                    Target::Address(LogicAddress::from(ENTITY)),
+                )
+                .with_activations(vec![RoutingActiveType::CentralSecurity]),
+            )
+            .push(Rule::deny(
+                SourceClass::Client,
+                Target::Range(0x1000..=0x10FF),
+                DiagnosticNegativeCode::TargetUnreachable,
+            ));
+        let entity = DoIpEntity::bind(config, EchoHandler).await?;
+
+        let client = DoIpClient::connect_addr(entity.tcp_addr(), client_config(TESTER)).await?;
+        client
+            .routing_activation(RoutingActiveType::Default, None)
+            .await?;
+        let ret = client
+            .diagnostic(LogicAddress::from(ENTITY), vec![0x10, 0x03])
+            .await;
+        assert!(matches!(
+            ret,
+            Err(Iso13400Error::DiagnosticNegative(
+                DiagnosticNegativeCode::TargetUnreachable
+            ))
+        ));
+
+        let client = DoIpClient::connect_addr(entity.tcp_addr(), client_config(TESTER + 1)).await?;
+        client
+            .routing_activation(RoutingActiveType::CentralSecurity, None)
+            .await?;
+        let resp = client
+            .diagnostic(LogicAddress::from(ENTITY), vec![0x10, 0x03])
+            .await?;
+        assert_eq!(resp.data(), &vec![0x50, 0x03]);
+        // denied before the validation of the handler
+        let ret = client
+            .diagnostic(LogicAddress::from(ENTITY + 1), vec![0x10, 0x03])
+            .await;
+        assert!(matches!(
+            ret,
+            Err(Iso13400Error::DiagnosticNegative(
+                DiagnosticNegativeCode::TargetUnreachable
+            ))
+        ));
+        // the socket is kept open after the rejected messages
+        assert_eq!(entity.open_sockets(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregate_power_mode() {
+        use PowerMode::*;
+        assert_eq!(aggregate_power_mode([]), NotSupported);
+        assert_eq!(
+            aggregate_power_mode([NotSupported, NotSupported]),
+            NotSupported
+        );
+        assert_eq!(aggregate_power_mode([Ready, NotSupported, Ready]), Ready);
+        assert_eq!(
+            aggregate_power_mode([Ready, NotReady, NotSupported]),
+            NotReady
+        );
+        assert_eq!(aggregate_power_mode([Ready, Reserved(0x05)]), NotReady);
+    }
+
+    #[tokio::test]
+    async fn test_live_status() -> anyhow::Result<()> {
+        let modes = SubNodePowerModes::default();
+        modes.set(LogicAddress::from(0x1101), PowerMode::Ready);
+        modes.set(LogicAddress::from(0x1102), PowerMode::NotReady);
+        let mut config = config()?;
+        config.max_sockets = 2;
+        let entity = DoIpEntity::bind_with_status(
+            config,
+            EchoHandler,
+            AcceptAll,
+            LiveStatus::with_power_mode(modes.clone()),
+        )
+        .await?;
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await?;
+        let mut buffer = vec![0; 1024];
+        let mut request = async |payload| -> anyhow::Result<String> {
+            let data: Vec<_> = Message {
+                version: Version::ISO13400_2_2012,
+                payload,
+            }
+            .into();
+            socket.send_to(&data, entity.udp_addr()).await?;
+            let (size, _) = socket.recv_from(&mut buffer).await?;
+            Ok(hex::encode(&buffer[..size]))
+        };
+
+        let power_mode = || Payload::ReqDiagPowerMode(request::DiagnosticPowerMode);
+        assert_eq!(request(power_mode()).await?, "02fd40040000000100");
+        modes.set(LogicAddress::from(0x1102), PowerMode::Ready);
+        assert_eq!(request(power_mode()).await?, "02fd40040000000101");
+
+        let entity_status = || Payload::ReqEntityStatus(request::EntityStatus);
+        assert_eq!(
+            request(entity_status()).await?,
+            "02fd40020000000701020000000fff"
+        );
+        let client = DoIpClient::connect_addr(entity.tcp_addr(), client_config(TESTER)).await?;
+        client
+            .routing_activation(RoutingActiveType::Default, None)
+            .await?;
+        assert_eq!(
+            request(entity_status()).await?,
+            "02fd40020000000701020100000fff"
+        );
+        client.close().await?;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(
+            request(entity_status()).await?,
+            "02fd40020000000701020000000fff"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,286 @@
+#[cfg(test)]
+mod tests {
+    use iso13400_2::{
+        codec::MessageDecoder,
+        state::{ClientConnection, ClientEvent, EntityConnection, EntityEvent, SocketState},
+        ActiveCode, DiagnosticNegativeCode, DoIpTiming, HeaderNegativeCode, Iso13400Error,
+        LogicAddress, Message, Payload, RoutingActiveType, Version,
+    };
+
+    const ENTITY: u16 = 0x1001;
+    const TESTER: u16 = 0x0E80;
+    const VERSION: Version = Version::ISO13400_2_2012;
+
+    fn client() -> ClientConnection {
+        ClientConnection::new(LogicAddress::from(TESTER), VERSION, DoIpTiming::default())
+    }
+
+    fn entity(now: u64) -> EntityConnection {
+        EntityConnection::new(
+            LogicAddress::from(ENTITY),
+            VERSION,
+            DoIpTiming::default(),
+            now,
+        )
+    }
+
+    /// Deliver all queued messages of the client to the entity.
+    fn to_entity(now: u64, client: &mut ClientConnection, entity: &mut EntityConnection) {
+        while let Some(message) = client.poll_transmit() {
+            entity.handle_message(now, message);
+        }
+    }
+
+    /// Deliver all queued messages of the entity to the client.
+    fn to_client(now: u64, entity: &mut EntityConnection, client: &mut ClientConnection) {
+        while let Some(message) = entity.poll_transmit() {
+            client.handle_message(now, message);
+        }
+    }
+
+    fn activate(
+        now: u64,
+        client: &mut ClientConnection,
+        entity: &mut EntityConnection,
+    ) -> anyhow::Result<()> {
+        client.activate(now, RoutingActiveType::Default, None)?;
+        to_entity(now, client, entity);
+        assert!(matches!(
+            entity.poll_event(),
+            Some(EntityEvent::RoutingRequest(req)) if req.src_addr() == LogicAddress::from(TESTER)
+        ));
+        entity.respond_routing(now, ActiveCode::Success, None)?;
+        to_client(now, entity, client);
+        assert!(matches!(
+            client.poll_event(),
+            Some(ClientEvent::RoutingActivated(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_routing_activation() -> anyhow::Result<()> {
+        let timing = DoIpTiming::default();
+        let mut client = client();
+        let mut entity = entity(0);
+        assert_eq!(entity.poll_timeout(), Some(timing.initial_inactivity));
+
+        assert!(matches!(
+            client.send_diagnostic(0, LogicAddress::from(ENTITY), vec![0x10, 0x01]),
+            Err(Iso13400Error::RoutingInactive)
+        ));
+
+        client.activate(0, RoutingActiveType::Default, None)?;
+        assert!(client.is_routing_outstanding());
+        assert!(client
+            .activate(0, RoutingActiveType::Default, None)
+            .is_err());
+        assert_eq!(client.poll_timeout(), Some(timing.ctrl));
+        to_entity(10, &mut client, &mut entity);
+        assert!(matches!(
+            entity.poll_event(),
+            Some(EntityEvent::RoutingRequest(_))
+        ));
+
+        // pending for the confirmation
+        entity.respond_routing(10, ActiveCode::NeedConfirm, None)?;
+        assert_eq!(entity.state(), SocketState::Pending);
+        to_client(20, &mut entity, &mut client);
+        assert!(matches!(
+            client.poll_event(),
+            Some(ClientEvent::RoutingPending(resp)) if resp.active_code() == ActiveCode::NeedConfirm
+        ));
+        assert_eq!(client.state(), SocketState::Pending);
+
+        activate(30, &mut client, &mut entity)?;
+        assert_eq!(client.state(), SocketState::Active);
+        assert_eq!(client.entity_address(), Some(LogicAddress::from(ENTITY)));
+        assert_eq!(entity.state(), SocketState::Active);
+        assert_eq!(entity.tester(), Some(LogicAddress::from(TESTER)));
+        assert_eq!(entity.poll_timeout(), Some(30 + timing.general_inactivity));
+
+        // another test equipment on the registered socket
+        let mut other = ClientConnection::new(LogicAddress::from(TESTER + 1), VERSION, timing);
+        other.activate(40, RoutingActiveType::Default, None)?;
+        to_entity(40, &mut other, &mut entity);
+        to_client(40, &mut entity, &mut other);
+        assert!(matches!(
+            other.poll_event(),
+            Some(ClientEvent::RoutingRefused(resp))
+                if resp.active_code() == ActiveCode::SourceAddressInvalid
+        ));
+        assert_eq!(other.poll_event(), Some(ClientEvent::Closed));
+        assert_eq!(entity.poll_event(), Some(EntityEvent::Closed));
+        assert_eq!(entity.poll_timeout(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diagnostic() -> anyhow::Result<()> {
+        let timing = DoIpTiming::default();
+        let mut client = client();
+        let mut entity = entity(0);
+        activate(0, &mut client, &mut entity)?;
+
+        client.send_diagnostic(100, LogicAddress::from(ENTITY), vec![0x10, 0x03])?;
+        assert!(client.is_ack_outstanding());
+        assert!(client
+            .send_diagnostic(100, LogicAddress::from(ENTITY), vec![0x10, 0x03])
+            .is_err());
+        assert_eq!(client.poll_timeout(), Some(100 + timing.diagnostic_message));
+        to_entity(110, &mut client, &mut entity);
+        let diag = match entity.poll_event() {
+            Some(EntityEvent::Diagnostic(diag)) => diag,
+            event => panic!("unexpected event: {:?}", event),
+        };
+        assert_eq!(entity.poll_timeout(), Some(110 + timing.general_inactivity));
+
+        entity.acknowledge(&diag, Ok(()));
+        entity.send_diagnostic(LogicAddress::from(ENTITY), vec![0x50, 0x03])?;
+        to_client(120, &mut entity, &mut client);
+        assert!(matches!(
+            client.poll_event(),
+            Some(ClientEvent::DiagnosticAck(_))
+        ));
+        assert!(matches!(
+            client.poll_event(),
+            Some(ClientEvent::Diagnostic(diag)) if diag.data() == &vec![0x50, 0x03]
+        ));
+        assert!(!client.is_ack_outstanding());
+
+        client.send_diagnostic(200, LogicAddress::from(ENTITY + 1), vec![0x10, 0x03])?;
+        to_entity(200, &mut client, &mut entity);
+        let diag = match entity.poll_event() {
+            Some(EntityEvent::Diagnostic(diag)) => diag,
+            event => panic!("unexpected event: {:?}", event),
+        };
+        entity.acknowledge(&diag, Err(DiagnosticNegativeCode::UnknownTargetAddress));
+        to_client(210, &mut entity, &mut client);
+        assert!(matches!(
+            client.poll_event(),
+            Some(ClientEvent::DiagnosticNack(resp))
+                if resp.code() == DiagnosticNegativeCode::UnknownTargetAddress
+        ));
+
+        // no acknowledge
+        client.send_diagnostic(300, LogicAddress::from(ENTITY), vec![0x3E, 0x00])?;
+        client.handle_timeout(300 + timing.diagnostic_message - 1);
+        assert_eq!(client.poll_event(), None);
+        client.handle_timeout(300 + timing.diagnostic_message);
+        assert_eq!(client.poll_event(), Some(ClientEvent::DiagnosticTimeout));
+        assert_eq!(client.poll_timeout(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_source() -> anyhow::Result<()> {
+        let mut entity = entity(0);
+        let diag = iso13400_2::Diagnostic::new(
+            LogicAddress::from(TESTER),
+            LogicAddress::from(ENTITY),
+            vec![0x10, 0x01],
+        );
+        entity.handle_message(
+            0,
+            Message {
+                version: VERSION,
+                payload: Payload::Diagnostic(diag),
+            },
+        );
+        assert!(matches!(
+            entity.poll_transmit().map(|m| m.payload),
+            Some(Payload::RespDiagNegative(resp))
+                if resp.code() == DiagnosticNegativeCode::InvalidSourceAddress
+        ));
+        assert_eq!(entity.poll_event(), Some(EntityEvent::Closed));
+        assert_eq!(entity.state(), SocketState::Finalized);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_alive_check() -> anyhow::Result<()> {
+        let timing = DoIpTiming::default();
+        let mut client = client();
+        let mut entity = entity(0);
+        activate(0, &mut client, &mut entity)?;
+
+        entity.alive_check(100);
+        entity.alive_check(110);
+        assert!(entity.is_alive_outstanding());
+        assert_eq!(entity.poll_timeout(), Some(100 + timing.alive_check));
+        to_client(120, &mut entity, &mut client);
+        // answered by the client machine
+        to_entity(130, &mut client, &mut entity);
+        assert_eq!(
+            entity.poll_event(),
+            Some(EntityEvent::Alive(LogicAddress::from(TESTER)))
+        );
+        assert!(!entity.is_alive_outstanding());
+
+        entity.alive_check(200);
+        entity.handle_timeout(200 + timing.alive_check);
+        assert_eq!(entity.poll_event(), Some(EntityEvent::AliveTimeout));
+        assert_eq!(entity.poll_event(), Some(EntityEvent::Closed));
+        assert_eq!(entity.state(), SocketState::Finalized);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_inactivity() -> anyhow::Result<()> {
+        let timing = DoIpTiming::default();
+        let mut idle = entity(0);
+        idle.handle_timeout(timing.initial_inactivity - 1);
+        assert_eq!(idle.poll_event(), None);
+        idle.handle_timeout(timing.initial_inactivity);
+        assert_eq!(idle.poll_event(), Some(EntityEvent::Inactive));
+        assert_eq!(idle.poll_event(), Some(EntityEvent::Closed));
+
+        let mut tester = client();
+        let mut active = entity(0);
+        activate(0, &mut tester, &mut active)?;
+        active.handle_timeout(timing.initial_inactivity);
+        assert_eq!(active.poll_event(), None);
+        active.handle_timeout(timing.general_inactivity);
+        assert_eq!(active.poll_event(), Some(EntityEvent::Inactive));
+
+        let mut tester = client();
+        tester.activate(0, RoutingActiveType::Default, None)?;
+        tester.handle_timeout(timing.ctrl);
+        assert_eq!(tester.poll_event(), Some(ClientEvent::RoutingTimeout));
+        assert!(!tester.is_routing_outstanding());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_header_negative() -> anyhow::Result<()> {
+        let mut decoder = MessageDecoder::default();
+        let mut entity = entity(0);
+        decoder.extend(&[0x02, 0xFD, 0x70, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        let err = decoder.decode().unwrap_err();
+        entity.handle_error(&err);
+        assert!(matches!(
+            entity.poll_transmit().map(|m| m.payload),
+            Some(Payload::RespHeaderNegative(resp))
+                if resp.code() == HeaderNegativeCode::UnknownPayloadTYpe
+        ));
+        assert_eq!(entity.state(), SocketState::Initialized);
+
+        decoder.extend(&[0x02, 0x02, 0x80, 0x01, 0x00, 0x00, 0x00, 0x04]);
+        let err = decoder.decode().unwrap_err();
+        entity.handle_error(&err);
+        assert!(matches!(
+            entity.poll_transmit().map(|m| m.payload),
+            Some(Payload::RespHeaderNegative(resp))
+                if resp.code() == HeaderNegativeCode::IncorrectPatternFormat
+        ));
+        assert_eq!(entity.poll_event(), Some(EntityEvent::Closed));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,308 @@
+#[cfg(test)]
+mod tests {
+    use iso13400_2::{
+        client::{ClientConfig, DoIpClient},
+        codec::MessageDecoder,
+        discovery::{Discovery, DiscoveryRequest},
+        request, response,
+        server::{DiagnosticHandler, DoIpEntity, EntityConfig, Responder},
+        ActiveCode, Diagnostic, DiagnosticNegativeCode, DoIpTiming, Eid, Gid, Iso13400Error,
+        LogicAddress, Message, Payload, RoutingActiveType, Version,
+    };
+    use std::time::Duration;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt, DuplexStream},
+        net::{TcpStream, UdpSocket},
+        time::Instant,
+    };
+
+    const VIN: &str = "WDD2040001A000001";
+    const ENTITY: u16 = 0x1001;
+    const TESTER: u16 = 0x0E80;
+
+    /// The handler which takes 10s to validate the diagnostic messages.
+    struct SlowHandler;
+
+    #[async_trait::async_trait]
+    impl DiagnosticHandler for SlowHandler {
+        async fn validate(&self, _: &Diagnostic) -> Result<(), DiagnosticNegativeCode> {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Ok(())
+        }
+
+        async fn handle(&self, _: Diagnostic, _: Responder) {}
+    }
+
+    fn entity_config() -> anyhow::Result<EntityConfig> {
+        let mut config = EntityConfig::new(
+            LogicAddress::from(ENTITY),
+            VIN,
+            Eid::new(0x001100110011)?,
+            Gid::new(0x110011001100)?,
+        )?;
+        config.version = Version::ISO13400_2_2012;
+        config.tcp_addr = "127.0.0.1:0".parse()?;
+        config.udp_addr = "127.0.0.1:0".parse()?;
+        Ok(config)
+    }
+
+    fn client_config(tester: u16) -> ClientConfig {
+        let mut config = ClientConfig::new(LogicAddress::from(tester));
+        config.version = Version::ISO13400_2_2012;
+        config
+    }
+
+    #[track_caller]
+    fn assert_elapsed(start: Instant, millis: u64) {
+        let elapsed = start.elapsed();
+        assert!(
+            elapsed >= Duration::from_millis(millis)
+                && elapsed < Duration::from_millis(millis + 10),
+            "elapsed {:?}, expect {}ms",
+            elapsed,
+            millis
+        );
+    }
+
+    /// Answer the routing activation requests from the second one, and ignore the others.
+    async fn fake_entity(mut stream: DuplexStream) -> anyhow::Result<()> {
+        let mut decoder = MessageDecoder::default();
+        let mut buffer = vec![0; 1024];
+        let mut requests = 0;
+        loop {
+            let size = stream.read(&mut buffer).await?;
+            if size == 0 {
+                return Ok(());
+            }
+            decoder.extend(&buffer[..size]);
+            while let Some(message) = decoder.decode()? {
+                let Payload::ReqRoutingActive(req) = message.payload else {
+                    continue;
+                };
+                requests += 1;
+                if requests < 2 {
+                    continue;
+                }
+                let resp = response::RoutingActive::new(
+                    req.src_addr(),
+                    LogicAddress::from(ENTITY),
+                    ActiveCode::Success,
+                    None,
+                );
+                let data: Vec<_> = Message {
+                    version: Version::ISO13400_2_2012,
+                    payload: Payload::RespRoutingActive(resp),
+                }
+                .into();
+                stream.write_all(&data).await?;
+            }
+        }
+    }
+
+    async fn read_closed(stream: &mut TcpStream) -> bool {
+        let mut buffer = vec![0; 1024];
+        loop {
+            match stream.read(&mut buffer).await {
+                Ok(0) | Err(_) => return true,
+                Ok(_) => continue,
+            }
+        }
+    }
+
+    #[test]
+    fn test_defaults() {
+        let timing = DoIpTiming::default();
+        assert_eq!(timing.ctrl, 2_000);
+        assert_eq!(timing.announce_num, 3);
+        assert_eq!(timing.general_inactivity, 300_000);
+        assert_eq!(timing.diagnostic_message, 2_000);
+
+        let timing = DoIpTiming::new(Version::ISO13400_2_2010);
+        assert_eq!(timing.diagnostic_message, 50);
+        assert_eq!(
+            DoIpTiming::new(Version::ISO13400_2_2019),
+            DoIpTiming::default()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() -> anyhow::Result<()> {
+        let timing: DoIpTiming = serde_json::from_str(r#"{ "ctrl": 1000, "alive_check": 200 }"#)?;
+        assert_eq!(timing.ctrl, 1_000);
+        assert_eq!(timing.alive_check, 200);
+        assert_eq!(timing.initial_inactivity, 2_000);
+
+        let value = serde_json::to_value(timing)?;
+        assert_eq!(serde_json::from_value::<DoIpTiming>(value)?, timing);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_client() -> anyhow::Result<()> {
+        let (stream, other) = tokio::io::duplex(1024);
+        tokio::spawn(fake_entity(other));
+
+        let mut config = client_config(TESTER);
+        config.timing = DoIpTiming::new(Version::ISO13400_2_2010);
+        let client = DoIpClient::from_stream(stream, "127.0.0.1:13400".parse()?, config);
+
+        // A_DoIP_Ctrl
+        let start = Instant::now();
+        let ret = client
+            .routing_activation(RoutingActiveType::Default, None)
+            .await;
+        assert!(matches!(ret, Err(Iso13400Error::Timeout { .. })));
+        assert_elapsed(start, 2_000);
+
+        client
+            .routing_activation(RoutingActiveType::Default, None)
+            .await?;
+
+        // A_DoIP_Diagnostic_Message of ISO 13400-2:2010
+        let start = Instant::now();
+        let ret = client
+            .send_diagnostic(LogicAddress::from(ENTITY), vec![0x10, 0x01])
+            .await;
+        assert!(matches!(ret, Err(Iso13400Error::Timeout { .. })));
+        assert_elapsed(start, 50);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_inactivity() -> anyhow::Result<()> {
+        let entity = DoIpEntity::bind(entity_config()?, SlowHandler).await?;
+
+        // T_TCP_Initial_Inactivity
+        let start = Instant::now();
+        let mut stream = TcpStream::connect(entity.tcp_addr()).await?;
+        assert!(read_closed(&mut stream).await);
+        assert_elapsed(start, 2_000);
+
+        // T_TCP_General_Inactivity
+        let client = DoIpClient::connect_addr(entity.tcp_addr(), client_config(TESTER)).await?;
+        client
+            .routing_activation(RoutingActiveType::Default, None)
+            .await?;
+        let start = Instant::now();
+        let ret = client.receive_diagnostic(600_000).await;
+        assert!(matches!(ret, Err(Iso13400Error::ConnectionClosed)));
+        assert_elapsed(start, 300_000);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_alive_check() -> anyhow::Result<()> {
+        let entity = DoIpEntity::bind(entity_config()?, SlowHandler).await?;
+
+        let mut stream = TcpStream::connect(entity.tcp_addr()).await?;
+        let req = request::RoutingActive::new(
+            LogicAddress::from(TESTER),
+            RoutingActiveType::Default,
+            None,
+        );
+        let data: Vec<_> = Message {
+            version: Version::ISO13400_2_2012,
+            payload: Payload::ReqRoutingActive(req),
+        }
+        .into();
+        stream.write_all(&data).await?;
+        let mut buffer = vec![0; 8 + 9];
+        stream.read_exact(&mut buffer).await?;
+
+        // T_TCP_Alive_Check, the socket without alive check response is closed
+        let other = DoIpClient::connect_addr(entity.tcp_addr(), client_config(TESTER + 1)).await?;
+        let start = Instant::now();
+        other
+            .routing_activation(RoutingActiveType::Default, None)
+            .await?;
+        assert_elapsed(start, 500);
+        assert!(read_closed(&mut stream).await);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_processing_time() -> anyhow::Result<()> {
+        let entity = DoIpEntity::bind(entity_config()?, SlowHandler).await?;
+
+        // the client waits longer than the entity processes
+        let mut config = client_config(TESTER);
+        config.timing.diagnostic_message = 3_000;
+        let client = DoIpClient::connect_addr(entity.tcp_addr(), config).await?;
+        client
+            .routing_activation(RoutingActiveType::Default, None)
+            .await?;
+
+        // A_Processing_Time
+        let start = Instant::now();
+        let ret = client
+            .send_diagnostic(LogicAddress::from(ENTITY), vec![0x10, 0x01])
+            .await;
+        assert!(matches!(
+            ret,
+            Err(Iso13400Error::DiagnosticNegative(
+                DiagnosticNegativeCode::TargetUnreachable
+            ))
+        ));
+        assert_elapsed(start, 2_000);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_announcement() -> anyhow::Result<()> {
+        let socket = UdpSocket::bind("127.0.0.1:0").await?;
+        let mut config = entity_config()?;
+        config.announce_addr = Some(socket.local_addr()?);
+
+        let start = Instant::now();
+        let _entity = DoIpEntity::bind(config, SlowHandler).await?;
+
+        // A_DoIP_Announce_Wait, A_DoIP_Announce_Interval and A_DoIP_Announce_Num
+        let mut buffer = vec![0; 1024];
+        let mut times = Vec::new();
+        while let Ok(ret) =
+            tokio::time::timeout(Duration::from_millis(2_000), socket.recv_from(&mut buffer)).await
+        {
+            ret?;
+            times.push(start.elapsed());
+        }
+        assert_eq!(times.len(), 3);
+        assert!(times[0] <= Duration::from_millis(500));
+        for v in times.windows(2) {
+            let interval = v[1] - v[0];
+            assert!(
+                interval >= Duration::from_millis(500) && interval < Duration::from_millis(510),
+                "interval {:?}",
+                interval
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_discovery() -> anyhow::Result<()> {
+        let silent = UdpSocket::bind("127.0.0.1:0").await?;
+        let discovery = Discovery::bind("127.0.0.1:0".parse()?).await?;
+
+        // A_DoIP_Ctrl
+        let start = Instant::now();
+        let results = discovery
+            .identify(silent.local_addr()?, DiscoveryRequest::All)
+            .await?;
+        assert!(results.is_empty());
+        assert_elapsed(start, 2_000);
+
+        // A_Vehicle_Discovery_Timer
+        let start = Instant::now();
+        assert!(discovery.listen().await?.is_empty());
+        assert_elapsed(start, 5_000);
+
+        Ok(())
+    }
+}
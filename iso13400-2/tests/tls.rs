@@ -0,0 +1,160 @@
+#![cfg(feature = "tls")]
+
+#[cfg(test)]
+mod tests {
+    use iso13400_2::{
+        client::{ClientConfig, DoIpClient, TlsSettings},
+        server::{DiagnosticHandler, DoIpEntity, EntityConfig, Responder, TlsConfig},
+        tls::{
+            self,
+            rustls::{
+                pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+                RootCertStore,
+            },
+            ServerName,
+        },
+        ActiveCode, Diagnostic, DiagnosticNegativeCode, Eid, Gid, Iso13400Error, LogicAddress,
+        RoutingActiveType, Version,
+    };
+    use std::sync::Arc;
+
+    const VIN: &str = "WDD2040001A000001";
+    const ENTITY: u16 = 0x1001;
+    const TESTER: u16 = 0x0E80;
+
+    struct EchoHandler;
+
+    #[async_trait::async_trait]
+    impl DiagnosticHandler for EchoHandler {
+        async fn validate(&self, _: &Diagnostic) -> Result<(), DiagnosticNegativeCode> {
+            Ok(())
+        }
+
+        async fn handle(&self, diag: Diagnostic, responder: Responder) {
+            let mut data = diag.data().clone();
+            data[0] += 0x40;
+            responder.respond(diag.dst_addr(), data).unwrap();
+        }
+    }
+
+    fn certs(name: &str) -> anyhow::Result<Vec<CertificateDer<'static>>> {
+        let path = format!("{}/tests/certs/{}.pem", env!("CARGO_MANIFEST_DIR"), name);
+        Ok(CertificateDer::pem_file_iter(path)?.collect::<Result<_, _>>()?)
+    }
+
+    fn key(name: &str) -> anyhow::Result<PrivateKeyDer<'static>> {
+        let path = format!("{}/tests/certs/{}.key", env!("CARGO_MANIFEST_DIR"), name);
+        Ok(PrivateKeyDer::from_pem_file(path)?)
+    }
+
+    fn roots() -> anyhow::Result<RootCertStore> {
+        let mut roots = RootCertStore::empty();
+        for cert in certs("ca")? {
+            roots.add(cert)?;
+        }
+        Ok(roots)
+    }
+
+    fn entity_config(required: bool) -> anyhow::Result<EntityConfig> {
+        let mut config = EntityConfig::new(
+            LogicAddress::from(ENTITY),
+            VIN,
+            Eid::new(0x001100110011)?,
+            Gid::new(0x110011001100)?,
+        )?;
+        config.version = Version::ISO13400_2_2019;
+        config.tcp_addr = "127.0.0.1:0".parse()?;
+        config.udp_addr = "127.0.0.1:0".parse()?;
+        let server = tls::server_config(certs("server")?, key("server")?, Some(roots()?))?;
+        let mut tls = TlsConfig::new(Arc::new(server));
+        tls.addr = "127.0.0.1:0".parse()?;
+        tls.required = required;
+        config.tls = Some(tls);
+        Ok(config)
+    }
+
+    fn client_config() -> ClientConfig {
+        let mut config = ClientConfig::new(LogicAddress::from(TESTER));
+        config.version = Version::ISO13400_2_2019;
+        config.timing.ctrl = 1_000;
+        config.timing.diagnostic_message = 500;
+        config.diag_timeout = 500;
+        config
+    }
+
+    fn settings(port: u16, identity: bool) -> anyhow::Result<TlsSettings> {
+        let identity = match identity {
+            true => Some((certs("client")?, key("client")?)),
+            false => None,
+        };
+        let config = tls::client_config(roots()?, identity)?;
+        let mut settings = TlsSettings::new(Arc::new(config), ServerName::try_from("localhost")?);
+        settings.port = port;
+        Ok(settings)
+    }
+
+    #[tokio::test]
+    async fn test_diagnostic() -> anyhow::Result<()> {
+        let entity = DoIpEntity::bind(entity_config(false)?, EchoHandler).await?;
+        let tls_addr = entity.tls_addr().unwrap();
+
+        let client = DoIpClient::connect_tls(
+            tls_addr.ip(),
+            &settings(tls_addr.port(), true)?,
+            client_config(),
+        )
+        .await?;
+        client
+            .routing_activation(RoutingActiveType::Default, None)
+            .await?;
+        let resp = client
+            .diagnostic(LogicAddress::from(ENTITY), vec![0x10, 0x03])
+            .await?;
+        assert_eq!(resp.data(), &vec![0x50, 0x03]);
+        client.close().await?;
+
+        // the client certificate is required
+        let settings = settings(tls_addr.port(), false)?;
+        let ret = async {
+            let client = DoIpClient::connect_tls(tls_addr.ip(), &settings, client_config()).await?;
+            client
+                .routing_activation(RoutingActiveType::Default, None)
+                .await
+        }
+        .await;
+        assert!(ret.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tls_required() -> anyhow::Result<()> {
+        let entity = DoIpEntity::bind(entity_config(true)?, EchoHandler).await?;
+        let tls_addr = entity.tls_addr().unwrap();
+
+        let client = DoIpClient::connect_addr(entity.tcp_addr(), client_config()).await?;
+        let ret = client
+            .routing_activation(RoutingActiveType::Default, None)
+            .await;
+        assert!(matches!(
+            ret,
+            Err(Iso13400Error::RoutingActivation(ActiveCode::TLSRequired))
+        ));
+
+        let (client, resp) = DoIpClient::connect_and_activate(
+            entity.tcp_addr(),
+            &settings(tls_addr.port(), true)?,
+            client_config(),
+            RoutingActiveType::Default,
+            None,
+        )
+        .await?;
+        assert_eq!(resp.active_code(), ActiveCode::Success);
+        let resp = client
+            .diagnostic(LogicAddress::from(ENTITY), vec![0x22, 0xF1, 0x90])
+            .await?;
+        assert_eq!(resp.data(), &vec![0x62, 0xF1, 0x90]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,331 @@
+//! Async UDS client(tester) over a [`DiagTransport`].
+
+use crate::{
+    error::Error,
+    request::{self, Request},
+    response::{self, Code, Response, SessionTiming},
+    transport::{Addressing, DiagTransport},
+    CommunicationCtrlType, DIDData, DTCReportType, DTCSettingType, DataIdentifier, DidConfig,
+    ECUResetType, ResponseData, RoutineCtrlType, Service, SessionType, TesterPresentType,
+    SUPPRESS_POSITIVE,
+};
+
+/// The UDS client sending the requests by [`DiagTransport`] and waiting for the matched responses.
+///
+/// The response is waited within P2, and within P2* after each NRC 0x78(ResponsePending).
+/// P2 and P2* are taken from the latest positive response of DiagnosticSessionControl.
+pub struct UdsClient<T> {
+    transport: T,
+    did_config: DidConfig,
+    timing: SessionTiming,
+}
+
+impl<T: DiagTransport> UdsClient<T> {
+    pub fn new(transport: T, did_config: DidConfig) -> Self {
+        Self {
+            transport,
+            did_config,
+            timing: Default::default(),
+        }
+    }
+
+    #[inline]
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+
+    #[inline]
+    pub fn did_config(&self) -> &DidConfig {
+        &self.did_config
+    }
+
+    /// Configure the data length of the data identifier.
+    #[inline]
+    pub fn add_did(&mut self, did: DataIdentifier, length: usize) {
+        self.did_config.insert(did, length);
+    }
+
+    #[inline]
+    pub fn timing(&self) -> &SessionTiming {
+        &self.timing
+    }
+
+    #[inline]
+    pub fn set_timing(&mut self, timing: SessionTiming) {
+        self.timing = timing;
+    }
+
+    /// Send the request and wait for the response.
+    ///
+    /// Return an error if the response is negative or doesn't match the request.
+    /// If the `SUPPRESS_POSITIVE` bit of the request is set, the negative response is still
+    /// waited within P2, and `None` is returned if there is no response.
+    pub async fn request(
+        &mut self,
+        addressing: Addressing,
+        request: Request,
+    ) -> Result<Option<Response>, Error> {
+        let service = request.service();
+        let sub_func = request.sub_function();
+        let data: Vec<_> = request.into();
+        rsutil::trace!("UDS - sending: {}", hex::encode(&data));
+        self.transport.send(addressing, &data).await?;
+
+        let suppress_positive = sub_func.is_some_and(|v| v.is_suppress_positive());
+        let expect = sub_func.map(|v| u8::from(v) & !SUPPRESS_POSITIVE);
+        self.receive(service, expect, suppress_positive).await
+    }
+
+    /// Send the request and parse the data of the response.
+    pub async fn request_data<R>(
+        &mut self,
+        addressing: Addressing,
+        request: Request,
+    ) -> Result<Option<R>, Error>
+    where
+        R: ResponseData,
+    {
+        match self.request(addressing, request).await? {
+            Some(response) => Ok(Some(response.data(&self.did_config)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Service 10
+    pub async fn session_ctrl(
+        &mut self,
+        session: SessionType,
+        suppress_positive: bool,
+    ) -> Result<Option<response::SessionCtrl>, Error> {
+        let sub_func = sub_function(session.into(), suppress_positive);
+        self.physical(Service::SessionCtrl, Some(sub_func), vec![])
+            .await
+    }
+
+    /// Service 11
+    pub async fn ecu_reset(
+        &mut self,
+        reset: ECUResetType,
+        suppress_positive: bool,
+    ) -> Result<Option<response::ECUReset>, Error> {
+        let sub_func = sub_function(reset.into(), suppress_positive);
+        self.physical(Service::ECUReset, Some(sub_func), vec![])
+            .await
+    }
+
+    /// Service 14
+    pub async fn clear_diagnostic_info(
+        &mut self,
+        info: request::ClearDiagnosticInfo,
+    ) -> Result<response::ClearDiagnosticInfo, Error> {
+        self.exchange(Service::ClearDiagnosticInfo, None, info.into())
+            .await
+    }
+
+    /// Service 19
+    pub async fn read_dtc_info(
+        &mut self,
+        report: DTCReportType,
+        info: request::DTCInfo,
+    ) -> Result<response::DTCInfo, Error> {
+        self.exchange(Service::ReadDTCInfo, Some(report.into()), info.into())
+            .await
+    }
+
+    /// Service 22
+    pub async fn read_did(
+        &mut self,
+        did: DataIdentifier,
+        others: Vec<DataIdentifier>,
+    ) -> Result<response::ReadDID, Error> {
+        let data = request::ReadDID::new(did, others);
+        self.exchange(Service::ReadDID, None, data.into()).await
+    }
+
+    /// Service 27, the seed is requested by the odd `level` and the key is sent by the even one.
+    pub async fn security_access(
+        &mut self,
+        level: u8,
+        data: Vec<u8>,
+    ) -> Result<response::SecurityAccess, Error> {
+        self.exchange(Service::SecurityAccess, Some(level), data)
+            .await
+    }
+
+    /// Service 28
+    pub async fn communication_ctrl(
+        &mut self,
+        ctrl_type: CommunicationCtrlType,
+        data: request::CommunicationCtrl,
+        suppress_positive: bool,
+    ) -> Result<Option<response::CommunicationCtrl>, Error> {
+        let sub_func = sub_function(ctrl_type.into(), suppress_positive);
+        self.physical(Service::CommunicationCtrl, Some(sub_func), data.into())
+            .await
+    }
+
+    /// Service 2E
+    pub async fn write_did(&mut self, data: DIDData) -> Result<response::WriteDID, Error> {
+        let data = request::WriteDID(data);
+        self.exchange(Service::WriteDID, None, data.into()).await
+    }
+
+    /// Service 31
+    pub async fn routine_ctrl(
+        &mut self,
+        ctrl_type: RoutineCtrlType,
+        data: request::RoutineCtrl,
+    ) -> Result<response::RoutineCtrl, Error> {
+        self.exchange(Service::RoutineCtrl, Some(ctrl_type.into()), data.into())
+            .await
+    }
+
+    /// Service 34
+    pub async fn request_download(
+        &mut self,
+        data: request::RequestDownload,
+    ) -> Result<response::RequestDownload, Error> {
+        self.exchange(Service::RequestDownload, None, data.into())
+            .await
+    }
+
+    /// Service 36
+    pub async fn transfer_data(
+        &mut self,
+        sequence: u8,
+        data: Vec<u8>,
+    ) -> Result<response::TransferData, Error> {
+        let data = request::TransferData { sequence, data };
+        self.exchange(Service::TransferData, None, data.into())
+            .await
+    }
+
+    /// Service 37
+    pub async fn request_transfer_exit(
+        &mut self,
+        data: Vec<u8>,
+    ) -> Result<response::RequestTransferExit, Error> {
+        self.exchange(Service::RequestTransferExit, None, data)
+            .await
+    }
+
+    /// Service 3E
+    pub async fn tester_present(
+        &mut self,
+        suppress_positive: bool,
+    ) -> Result<Option<response::TesterPresent>, Error> {
+        let sub_func = sub_function(TesterPresentType::Zero.into(), suppress_positive);
+        self.physical(Service::TesterPresent, Some(sub_func), vec![])
+            .await
+    }
+
+    /// Service 85
+    pub async fn ctrl_dtc_setting(
+        &mut self,
+        setting: DTCSettingType,
+        data: Vec<u8>,
+        suppress_positive: bool,
+    ) -> Result<Option<response::CtrlDTCSetting>, Error> {
+        let sub_func = sub_function(setting.into(), suppress_positive);
+        self.physical(Service::CtrlDTCSetting, Some(sub_func), data)
+            .await
+    }
+
+    /// Send the physical request, `None` if the positive response is suppressed.
+    async fn physical<R>(
+        &mut self,
+        service: Service,
+        sub_func: Option<u8>,
+        data: Vec<u8>,
+    ) -> Result<Option<R>, Error>
+    where
+        R: ResponseData,
+    {
+        let request = Request::new(service, sub_func, data, &self.did_config)?;
+        self.request_data(Addressing::Physical, request).await
+    }
+
+    /// Send the physical request of which the positive response can't be suppressed.
+    async fn exchange<R>(
+        &mut self,
+        service: Service,
+        sub_func: Option<u8>,
+        data: Vec<u8>,
+    ) -> Result<R, Error>
+    where
+        R: ResponseData,
+    {
+        self.physical(service, sub_func, data)
+            .await?
+            .ok_or(Error::ServiceError(service))
+    }
+
+    /// Wait for the response, `None` if the positive response is suppressed
+    /// and no response is received within P2.
+    ///
+    /// The final response is waited after NRC 0x78 even if the positive response is suppressed.
+    async fn receive(
+        &mut self,
+        service: Service,
+        sub_func: Option<u8>,
+        suppress_positive: bool,
+    ) -> Result<Option<Response>, Error> {
+        let mut timeout = self.timing.p2_ms();
+        let mut pending = false;
+        loop {
+            let pdu = match self.transport.receive(timeout).await {
+                Err(Error::Timeout { .. }) if suppress_positive && !pending => return Ok(None),
+                ret => ret?,
+            };
+            rsutil::trace!("UDS - received: {}", hex::encode(&pdu.data));
+            let response = Response::try_from((&pdu.data, &self.did_config))?;
+            if response.service() != service {
+                return Err(Error::UnexpectedResponse {
+                    expect: service,
+                    actual: response.service(),
+                });
+            }
+
+            if response.is_negative() {
+                match response.nrc_code()? {
+                    Code::RequestCorrectlyReceivedResponsePending => {
+                        rsutil::debug!("UDS - service `{}` response pending", service);
+                        timeout = self.timing.p2_star_ms();
+                        pending = true;
+                        continue;
+                    }
+                    code => return Err(Error::NRCError { service, code }),
+                }
+            }
+
+            let actual = response.sub_function().map(|v| v.origin());
+            if actual != sub_func {
+                return Err(Error::UnexpectedSubFunction {
+                    service,
+                    expect: sub_func.unwrap_or_default(),
+                    actual: actual.unwrap_or_default(),
+                });
+            }
+
+            if service == Service::SessionCtrl {
+                let session: response::SessionCtrl = response.data(&self.did_config)?;
+                self.timing = session.0;
+            }
+
+            return Ok(Some(response));
+        }
+    }
+}
+
+#[inline]
+fn sub_function(function: u8, suppress_positive: bool) -> u8 {
+    match suppress_positive {
+        true => function | SUPPRESS_POSITIVE,
+        false => function,
+    }
+}
@@ -22,7 +22,9 @@ rsutil::enum_extend!(
     pub enum ResponseOnEventType {
         StopResponseOnEvent = 0x00,
         OnDTCStatusChange = 0x01,
-        OnChangeOfDataIdentifier = 0x02,
+        #[cfg(any(feature = "std2006", feature = "std2013"))]
+        OnTimerInterrupt = 0x02,
+        OnChangeOfDataIdentifier = 0x03,
         ReportActivatedEvents = 0x04,
         StartResponseOnEvent = 0x05,
         ClearResponseOnEvent = 0x06,
@@ -72,3 +74,14 @@ impl From<EventType> for u8 {
         result
     }
 }
+
+impl TryFrom<u8> for EventType {
+    type Error = Error;
+    #[inline]
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(Self {
+            store_event: (value & POSITIVE_OFFSET) == POSITIVE_OFFSET,
+            event_type: ResponseOnEventType::try_from(value & !POSITIVE_OFFSET)?,
+        })
+    }
+}
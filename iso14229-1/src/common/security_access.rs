@@ -2,7 +2,7 @@
 
 use crate::{error::Error, utils, RequestData, ResponseData, Service};
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct SecurityAccessLevel(pub(crate) u8);
 
 impl SecurityAccessLevel {
@@ -3,7 +3,7 @@
 use crate::{error::Error, utils};
 
 #[repr(u8)]
-#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
 pub enum SessionType {
     #[default]
     Default = 0x01,
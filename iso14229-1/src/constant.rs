@@ -9,3 +9,5 @@ pub const P2_MAX: u16 = 50;
 pub const P2_STAR_MAX_MS: u32 = 5_000;
 /// p2* max value 500 * 10ms
 pub const P2_STAR_MAX: u16 = (P2_STAR_MAX_MS / 10) as u16;
+/// S3 server value 5000ms
+pub const S3_SERVER_MS: u64 = 5_000;
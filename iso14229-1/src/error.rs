@@ -1,4 +1,4 @@
-use crate::{DataIdentifier, Service};
+use crate::{response::Code, DataIdentifier, Service};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -29,23 +29,32 @@ pub enum Error {
     #[error("ISO 14229-1 - the service `{0}` is error")]
     ServiceError(Service),
 
-    // #[error("ISO 14229-1 - service `{service}` got an unexpected sub-function(expect: {expect}, actual: {actual})")]
-    // UnexpectedSubFunction { service: Service, expect: u8, actual: u8 },
+    #[error("ISO 14229-1 - service `{service}` got an unexpected sub-function(expect: {expect}, actual: {actual})")]
+    UnexpectedSubFunction {
+        service: Service,
+        expect: u8,
+        actual: u8,
+    },
 
-    // #[error("ISO 14229-1 - service `{expect}` got an unexpect response `{actual}`")]
-    // UnexpectedResponse { expect: Service, actual: Service },
+    #[error("ISO 14229-1 - service `{expect}` got an unexpect response `{actual}`")]
+    UnexpectedResponse { expect: Service, actual: Service },
 
     // #[error("ISO 14229-1 - block sequence number of response (0x{actual:02x}) does not match request block sequence number (0x{expect:02x})")]
     // UnexpectedTransferSequence { expect: u8, actual: u8 },
-
-    // #[error("ISO 14229-1 - service `{service}` got a NRC({code:?})")]
-    // NRCError { service: Service, code: Code },
+    #[error("ISO 14229-1 - service `{service}` got a NRC({code:?})")]
+    NRCError { service: Service, code: Code },
 
     // #[error("ISO 14229-1 - security algorithm error: {0}")]
     // SecurityAlgoError(String),
+    #[cfg(feature = "can")]
+    #[error("{0}")]
+    IsoTpError(#[from] iso15765_2::IsoTpError),
+
+    #[error("ISO 14229-1 - timeout when time({value}{unit})")]
+    Timeout { value: u64, unit: &'static str },
 
-    // #[error("{0}")]
-    // IsoTpError(IsoTpError),
+    #[error("ISO 14229-1 - transport error: {0}")]
+    TransportError(String),
     #[error("ISO 14229-1 - other error: {0}")]
     OtherError(String),
 
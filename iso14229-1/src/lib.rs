@@ -1,10 +1,16 @@
 #![allow(clippy::non_minimal_cfg)]
 
+#[cfg(feature = "client")]
+pub mod client;
 mod common;
 mod constant;
 mod error;
 pub mod request;
 pub mod response;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "transport")]
+pub mod transport;
 pub mod utils;
 
 pub use self::{common::*, constant::*, error::Error as Iso14229Error};
@@ -50,7 +56,7 @@ rsutil::enum_extend!(
         AccessTimingParam = 0x83, // ✅
         SecuredDataTrans = 0x84,    // ✅
         CtrlDTCSetting = 0x85,      // ✅
-        ResponseOnEvent = 0x86,     // ❌
+        ResponseOnEvent = 0x86,     // ✅
         LinkCtrl = 0x87,            // ✅
         NRC = 0x7F,
     },
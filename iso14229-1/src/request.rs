@@ -226,6 +226,7 @@ impl<T: AsRef<[u8]>> TryFrom<(Service, T, &DidConfig)> for Request {
             | Service::CtrlDTCSetting
             | Service::TesterPresent
             | Service::LinkCtrl
+            | Service::ResponseOnEvent
             | Service::DynamicalDefineDID => Self::new_sub_func(data, service, cfg),
             #[cfg(any(feature = "std2006", feature = "std2013"))]
             Service::AccessTimingParam => Self::new_sub_func(data, service, cfg),
@@ -245,8 +246,7 @@ impl<T: AsRef<[u8]>> TryFrom<(Service, T, &DidConfig)> for Request {
             | Service::TransferData
             | Service::RequestTransferExit
             | Service::WriteMemByAddr
-            | Service::SecuredDataTrans
-            | Service::ResponseOnEvent => Self::new(service, None, data, cfg),
+            | Service::SecuredDataTrans => Self::new(service, None, data, cfg),
             Service::NRC => Err(Error::OtherError("got an NRC service from request".into())),
         }
     }
@@ -3,7 +3,7 @@
 use crate::{
     error::Error,
     request::{Request, SubFunction},
-    DidConfig, EventType, RequestData, ResponseOnEventType, Service,
+    utils, DidConfig, EventType, RequestData, ResponseOnEventType, Service,
 };
 use bitfield_struct::bitfield;
 
@@ -78,52 +78,282 @@ impl Localization {
     }
 }
 
-#[repr(u8)]
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+/// The eventTypeRecord and serviceToRespondToRecord of each event type.
+///
+/// The serviceToRespondToRecord is the request of the service(e.g. 0x19 or 0x22)
+/// sent by the server when the event is triggered.
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum EventTypeParameter {
-    StopResponseOnEvent = 0x00,
+    StopResponseOnEvent,
     OnDTCStatusChange {
-        test_failed: u8,
-        service: Service,
-        sub_func: u8,
         dtc_status_mask: u8,
-    } = 0x01, // Comparison Parameter < Measured Value
+        service_to_respond: Request,
+    },
+    #[cfg(any(feature = "std2006", feature = "std2013"))]
+    OnTimerInterrupt {
+        timer_schedule: u8,
+        service_to_respond: Request,
+    },
     OnChangeOfDataIdentifier {
         did: u16,
-        service: Service,
-    } = 0x03, // Comparison Parameter > Measured Value
-    ReportActivatedEvents = 0x04,
-    StartResponseOnEvent = 0x05, //
-    ClearResponseOnEvent = 0x06,
+        service_to_respond: Request,
+    },
+    ReportActivatedEvents,
+    StartResponseOnEvent,
+    ClearResponseOnEvent,
     OnComparisonOfValues {
         did: u16,
         logic_id: ComparisonLogicID,
         comparison_ref: u32,
+        /// hysteresis value in percent
         hysteresis_value: u8,
         localization: Localization,
-        service: Service,
-        response_did: u16, //
-    } = 0x07, // C2
+        service_to_respond: Request,
+    },
     ReportMostRecentDtcOnStatusChange {
         report_type: u8,
-    } = 0x08, // C2
+    },
     ReportDTCRecordInformationOnDtcStatusChange {
         dtc_status_mask: u8,
         dtc_sub_func: u8,
         dtc_ext_data_record_num: u8,
-    } = 0x09, // C2
+    },
+}
+
+impl EventTypeParameter {
+    pub fn event_type(&self) -> ResponseOnEventType {
+        match self {
+            Self::StopResponseOnEvent => ResponseOnEventType::StopResponseOnEvent,
+            Self::OnDTCStatusChange { .. } => ResponseOnEventType::OnDTCStatusChange,
+            #[cfg(any(feature = "std2006", feature = "std2013"))]
+            Self::OnTimerInterrupt { .. } => ResponseOnEventType::OnTimerInterrupt,
+            Self::OnChangeOfDataIdentifier { .. } => ResponseOnEventType::OnChangeOfDataIdentifier,
+            Self::ReportActivatedEvents => ResponseOnEventType::ReportActivatedEvents,
+            Self::StartResponseOnEvent => ResponseOnEventType::StartResponseOnEvent,
+            Self::ClearResponseOnEvent => ResponseOnEventType::ClearResponseOnEvent,
+            Self::OnComparisonOfValues { .. } => ResponseOnEventType::OnComparisonOfValues,
+            Self::ReportMostRecentDtcOnStatusChange { .. } => {
+                ResponseOnEventType::ReportMostRecentDtcOnStatusChange
+            }
+            Self::ReportDTCRecordInformationOnDtcStatusChange { .. } => {
+                ResponseOnEventType::ReportDTCRecordInformationOnDtcStatusChange
+            }
+        }
+    }
+
+    /// Decode the records from `offset`.
+    ///
+    /// The serviceToRespondToRecord takes all the rest data when `last` is true,
+    /// otherwise the shortest valid request, e.g. in the list of the activated events.
+    ///
+    /// The record has no length on the wire, so a request with a variable length
+    /// (e.g. `22 F1 90 F1 91` reading two DIDs) is ambiguous if it isn't the last one:
+    /// it is cut to the shortest valid prefix (`22 F1 90`) and the rest is decoded as the next event.
+    pub(crate) fn decode(
+        event_type: ResponseOnEventType,
+        data: &[u8],
+        offset: &mut usize,
+        last: bool,
+        cfg: &DidConfig,
+    ) -> Result<Self, Error> {
+        let data_len = data.len();
+        match event_type {
+            ResponseOnEventType::StopResponseOnEvent => Ok(Self::StopResponseOnEvent),
+            ResponseOnEventType::OnDTCStatusChange => {
+                utils::data_length_check(data_len, *offset + 1, false)?;
+                let dtc_status_mask = data[*offset];
+                *offset += 1;
+                let service_to_respond = service_to_respond(data, offset, last, cfg)?;
+
+                Ok(Self::OnDTCStatusChange {
+                    dtc_status_mask,
+                    service_to_respond,
+                })
+            }
+            #[cfg(any(feature = "std2006", feature = "std2013"))]
+            ResponseOnEventType::OnTimerInterrupt => {
+                utils::data_length_check(data_len, *offset + 1, false)?;
+                let timer_schedule = data[*offset];
+                *offset += 1;
+                let service_to_respond = service_to_respond(data, offset, last, cfg)?;
+
+                Ok(Self::OnTimerInterrupt {
+                    timer_schedule,
+                    service_to_respond,
+                })
+            }
+            ResponseOnEventType::OnChangeOfDataIdentifier => {
+                utils::data_length_check(data_len, *offset + 2, false)?;
+                let did = u16::from_be_bytes([data[*offset], data[*offset + 1]]);
+                *offset += 2;
+                let service_to_respond = service_to_respond(data, offset, last, cfg)?;
+
+                Ok(Self::OnChangeOfDataIdentifier {
+                    did,
+                    service_to_respond,
+                })
+            }
+            ResponseOnEventType::ReportActivatedEvents => Ok(Self::ReportActivatedEvents),
+            ResponseOnEventType::StartResponseOnEvent => Ok(Self::StartResponseOnEvent),
+            ResponseOnEventType::ClearResponseOnEvent => Ok(Self::ClearResponseOnEvent),
+            ResponseOnEventType::OnComparisonOfValues => {
+                utils::data_length_check(data_len, *offset + 10, false)?;
+                let did = u16::from_be_bytes([data[*offset], data[*offset + 1]]);
+                *offset += 2;
+                let logic_id = ComparisonLogicID::try_from(data[*offset])?;
+                *offset += 1;
+                let comparison_ref = u32::from_be_bytes([
+                    data[*offset],
+                    data[*offset + 1],
+                    data[*offset + 2],
+                    data[*offset + 3],
+                ]);
+                *offset += 4;
+                let hysteresis_value = data[*offset];
+                *offset += 1;
+                let localization =
+                    Localization::from(u16::from_be_bytes([data[*offset], data[*offset + 1]]));
+                *offset += 2;
+                let service_to_respond = service_to_respond(data, offset, last, cfg)?;
+
+                Ok(Self::OnComparisonOfValues {
+                    did,
+                    logic_id,
+                    comparison_ref,
+                    hysteresis_value,
+                    localization,
+                    service_to_respond,
+                })
+            }
+            ResponseOnEventType::ReportMostRecentDtcOnStatusChange => {
+                utils::data_length_check(data_len, *offset + 1, false)?;
+                let report_type = data[*offset];
+                *offset += 1;
+
+                Ok(Self::ReportMostRecentDtcOnStatusChange { report_type })
+            }
+            ResponseOnEventType::ReportDTCRecordInformationOnDtcStatusChange => {
+                utils::data_length_check(data_len, *offset + 3, false)?;
+                let result = Self::ReportDTCRecordInformationOnDtcStatusChange {
+                    dtc_status_mask: data[*offset],
+                    dtc_sub_func: data[*offset + 1],
+                    dtc_ext_data_record_num: data[*offset + 2],
+                };
+                *offset += 3;
+
+                Ok(result)
+            }
+        }
+    }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+impl From<EventTypeParameter> for Vec<u8> {
+    fn from(v: EventTypeParameter) -> Self {
+        let mut result = Vec::new();
+
+        match v {
+            EventTypeParameter::StopResponseOnEvent
+            | EventTypeParameter::ReportActivatedEvents
+            | EventTypeParameter::StartResponseOnEvent
+            | EventTypeParameter::ClearResponseOnEvent => {}
+            EventTypeParameter::OnDTCStatusChange {
+                dtc_status_mask,
+                service_to_respond,
+            } => {
+                result.push(dtc_status_mask);
+                result.append(&mut service_to_respond.into());
+            }
+            #[cfg(any(feature = "std2006", feature = "std2013"))]
+            EventTypeParameter::OnTimerInterrupt {
+                timer_schedule,
+                service_to_respond,
+            } => {
+                result.push(timer_schedule);
+                result.append(&mut service_to_respond.into());
+            }
+            EventTypeParameter::OnChangeOfDataIdentifier {
+                did,
+                service_to_respond,
+            } => {
+                result.extend(did.to_be_bytes());
+                result.append(&mut service_to_respond.into());
+            }
+            EventTypeParameter::OnComparisonOfValues {
+                did,
+                logic_id,
+                comparison_ref,
+                hysteresis_value,
+                localization,
+                service_to_respond,
+            } => {
+                result.extend(did.to_be_bytes());
+                result.push(logic_id.into());
+                result.extend(comparison_ref.to_be_bytes());
+                result.push(hysteresis_value);
+                result.extend(u16::from(localization).to_be_bytes());
+                result.append(&mut service_to_respond.into());
+            }
+            EventTypeParameter::ReportMostRecentDtcOnStatusChange { report_type } => {
+                result.push(report_type);
+            }
+            EventTypeParameter::ReportDTCRecordInformationOnDtcStatusChange {
+                dtc_status_mask,
+                dtc_sub_func,
+                dtc_ext_data_record_num,
+            } => {
+                result.push(dtc_status_mask);
+                result.push(dtc_sub_func);
+                result.push(dtc_ext_data_record_num);
+            }
+        }
+
+        result
+    }
+}
+
+/// The data following the sub-function(eventType), which tells the event type.
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct ResponseOnEvent {
-    pub window_time: u8, // unit of window time is `s`(seconds)
+    /// eventWindowTime, e.g. 0x02(infinite time), 0x03(power cycle), 0x04(ignition cycle)
+    ///
+    /// It is only omitted by ReportActivatedEvents.
+    pub window_time: Option<u8>,
     pub param: EventTypeParameter,
 }
 
-#[allow(unused)]
+impl ResponseOnEvent {
+    /// Decode the eventWindowTime and the records from `offset`.
+    ///
+    /// The eventWindowTime of ReportActivatedEvents is optional.
+    pub(crate) fn decode(
+        event_type: ResponseOnEventType,
+        data: &[u8],
+        offset: &mut usize,
+        last: bool,
+        cfg: &DidConfig,
+    ) -> Result<Self, Error> {
+        let data_len = data.len();
+        let window_time =
+            if event_type == ResponseOnEventType::ReportActivatedEvents && *offset == data_len {
+                None
+            } else {
+                utils::data_length_check(data_len, *offset + 1, false)?;
+                let window_time = data[*offset];
+                *offset += 1;
+                Some(window_time)
+            };
+        let param = EventTypeParameter::decode(event_type, data, offset, last, cfg)?;
+
+        Ok(Self { window_time, param })
+    }
+}
+
 impl From<ResponseOnEvent> for Vec<u8> {
     fn from(v: ResponseOnEvent) -> Self {
-        unreachable!("This library does not yet support");
+        let mut result: Vec<_> = v.window_time.into_iter().collect();
+        result.append(&mut v.param.into());
+
+        result
     }
 }
 
@@ -131,28 +361,69 @@ impl RequestData for ResponseOnEvent {
     fn new_request<T: AsRef<[u8]>>(
         data: T,
         sub_func: Option<u8>,
-        _: &DidConfig,
+        cfg: &DidConfig,
     ) -> Result<Request, Error> {
         let data = data.as_ref();
         match sub_func {
-            Some(_) => Err(Error::SubFunctionError(Service::ResponseOnEvent)),
-            None => Ok(Request {
-                service: Service::ResponseOnEvent,
-                sub_func: None,
-                data: data.to_vec(),
-            }),
+            Some(sub_func) => {
+                let (suppress_positive, sub_func) = utils::peel_suppress_positive(sub_func);
+                let event_type = EventType::try_from(sub_func)?;
+
+                let mut offset = 0;
+                let _ = Self::decode(event_type.event_type, data, &mut offset, true, cfg)?;
+                utils::data_length_check(data.len(), offset, true)?;
+
+                Ok(Request {
+                    service: Service::ResponseOnEvent,
+                    sub_func: Some(SubFunction::new(sub_func, suppress_positive)),
+                    data: data.to_vec(),
+                })
+            }
+            None => Err(Error::SubFunctionError(Service::ResponseOnEvent)),
         }
     }
 }
 
 impl TryFrom<(&Request, &DidConfig)> for ResponseOnEvent {
     type Error = Error;
-    fn try_from((req, _): (&Request, &DidConfig)) -> Result<Self, Self::Error> {
+    fn try_from((req, cfg): (&Request, &DidConfig)) -> Result<Self, Self::Error> {
         let service = req.service();
-        if service != Service::ResponseOnEvent || req.sub_func.is_some() {
+        if service != Service::ResponseOnEvent || req.sub_func.is_none() {
             return Err(Error::ServiceError(service));
         }
 
-        Err(Error::NotImplement)
+        let event_type: EventType = req.sub_function().unwrap().function()?;
+        let mut offset = 0;
+        Self::decode(event_type.event_type, &req.data, &mut offset, true, cfg)
+    }
+}
+
+/// The serviceToRespondToRecord from `offset`.
+///
+/// See [`EventTypeParameter::decode`] for the ambiguity when `last` is false.
+fn service_to_respond(
+    data: &[u8],
+    offset: &mut usize,
+    last: bool,
+    cfg: &DidConfig,
+) -> Result<Request, Error> {
+    let data_len = data.len();
+    utils::data_length_check(data_len, *offset + 1, false)?;
+    if last {
+        let result = Request::try_from((&data[*offset..], cfg))?;
+        *offset = data_len;
+        return Ok(result);
+    }
+
+    let mut end = *offset + 1;
+    loop {
+        match Request::try_from((&data[*offset..end], cfg)) {
+            Ok(result) => {
+                *offset = end;
+                return Ok(result);
+            }
+            Err(e) if end >= data_len => return Err(e),
+            Err(_) => end += 1,
+        }
     }
 }
@@ -333,6 +333,7 @@ impl<T: AsRef<[u8]>> TryFrom<(Service, T, &DidConfig)> for Response {
             | Service::CtrlDTCSetting
             | Service::TesterPresent
             | Service::LinkCtrl
+            | Service::ResponseOnEvent
             | Service::DynamicalDefineDID => Self::new_sub_func(data, service, cfg),
             #[cfg(any(feature = "std2006", feature = "std2013"))]
             Service::AccessTimingParam => Self::new_sub_func(data, service, cfg),
@@ -352,8 +353,7 @@ impl<T: AsRef<[u8]>> TryFrom<(Service, T, &DidConfig)> for Response {
             | Service::TransferData
             | Service::RequestTransferExit
             | Service::WriteMemByAddr
-            | Service::SecuredDataTrans
-            | Service::ResponseOnEvent => Self::new(service, None, data, cfg),
+            | Service::SecuredDataTrans => Self::new(service, None, data, cfg),
             Service::NRC => {
                 let data = data.as_ref();
                 let data_len = data.len();
@@ -2,8 +2,9 @@
 
 use crate::{
     error::Error,
+    request,
     response::{Code, Response, SubFunction},
-    DidConfig, ResponseData, Service,
+    utils, DidConfig, EventType, ResponseData, ResponseOnEventType, Service,
 };
 use std::{collections::HashSet, sync::LazyLock};
 
@@ -16,41 +17,157 @@ pub static RESPONSE_ON_EVENT_NEGATIVES: LazyLock<HashSet<Code>> = LazyLock::new(
     ])
 });
 
+/// An event listed by the response of ReportActivatedEvents.
 #[derive(Debug, Clone, Eq, PartialEq)]
-pub struct ResponseOnEvent {
-    pub data: Vec<u8>,
+pub struct ActivatedEvent {
+    /// eventTypeOfActiveEvent, with the storageState bit
+    pub event_type: EventType,
+    pub event: request::ResponseOnEvent,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ResponseOnEvent {
+    /// numberOfIdentifiedEvents and the eventWindowTime and records of the request
+    Identified {
+        number: u8,
+        event: request::ResponseOnEvent,
+    },
+    /// the events of ReportActivatedEvents
+    ActivatedEvents(ActivatedEvents),
+}
+
+/// The numberOfActivatedEvents and the events of ReportActivatedEvents.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ActivatedEvents {
+    number: u8,
+    events: Vec<ActivatedEvent>,
+}
+
+impl ActivatedEvents {
+    pub fn new(events: Vec<ActivatedEvent>) -> Result<Self, Error> {
+        let number = u8::try_from(events.len()).map_err(|_| {
+            Error::InvalidParam("number of `activated events` is out of range".to_string())
+        })?;
+
+        Ok(Self { number, events })
+    }
+
+    #[inline]
+    pub fn number(&self) -> u8 {
+        self.number
+    }
+
+    #[inline]
+    pub fn events(&self) -> &[ActivatedEvent] {
+        &self.events
+    }
+}
+
+impl ResponseOnEvent {
+    /// Create the response of ReportActivatedEvents.
+    #[inline]
+    pub fn activated_events(events: Vec<ActivatedEvent>) -> Result<Self, Error> {
+        Ok(Self::ActivatedEvents(ActivatedEvents::new(events)?))
+    }
+
+    fn decode(
+        event_type: ResponseOnEventType,
+        data: &[u8],
+        cfg: &DidConfig,
+    ) -> Result<Self, Error> {
+        let data_len = data.len();
+        let mut offset = 0;
+        utils::data_length_check(data_len, offset + 1, false)?;
+        let number = data[offset];
+        offset += 1;
+
+        let result = match event_type {
+            ResponseOnEventType::ReportActivatedEvents => {
+                let mut events = Vec::new();
+                for i in 0..number {
+                    utils::data_length_check(data_len, offset + 1, false)?;
+                    let event_type = EventType::try_from(data[offset])?;
+                    offset += 1;
+                    let last = i + 1 == number;
+                    let event = request::ResponseOnEvent::decode(
+                        event_type.event_type(),
+                        data,
+                        &mut offset,
+                        last,
+                        cfg,
+                    )?;
+                    events.push(ActivatedEvent { event_type, event });
+                }
+
+                Self::ActivatedEvents(ActivatedEvents { number, events })
+            }
+            _ => {
+                let event =
+                    request::ResponseOnEvent::decode(event_type, data, &mut offset, true, cfg)?;
+                Self::Identified { number, event }
+            }
+        };
+        utils::data_length_check(data_len, offset, true)?;
+
+        Ok(result)
+    }
 }
 
 impl From<ResponseOnEvent> for Vec<u8> {
     fn from(v: ResponseOnEvent) -> Self {
-        v.data
+        match v {
+            ResponseOnEvent::Identified { number, event } => {
+                let mut result = vec![number];
+                result.append(&mut event.into());
+
+                result
+            }
+            ResponseOnEvent::ActivatedEvents(ActivatedEvents { number, events }) => {
+                let mut result = vec![number];
+                events.into_iter().for_each(|v| {
+                    result.push(v.event_type.into());
+                    result.append(&mut v.event.into());
+                });
+
+                result
+            }
+        }
     }
 }
 
-#[allow(unused_variables)]
 impl ResponseData for ResponseOnEvent {
     fn new_response<T: AsRef<[u8]>>(
         data: T,
         sub_func: Option<u8>,
-        _: &DidConfig,
+        cfg: &DidConfig,
     ) -> Result<Response, Error> {
         let data = data.as_ref();
         match sub_func {
-            Some(sub_func) => Err(Error::SubFunctionError(Service::ResponseOnEvent)),
-            None => Ok(Response {
-                service: Service::ResponseOnEvent,
-                negative: false,
-                sub_func: None,
-                data: data.to_vec(),
-            }),
+            Some(sub_func) => {
+                let event_type = EventType::try_from(sub_func)?;
+                let _ = Self::decode(event_type.event_type(), data, cfg)?;
+
+                Ok(Response {
+                    service: Service::ResponseOnEvent,
+                    negative: false,
+                    sub_func: Some(SubFunction::new(sub_func)),
+                    data: data.to_vec(),
+                })
+            }
+            None => Err(Error::SubFunctionError(Service::ResponseOnEvent)),
         }
     }
 }
 
-#[allow(unused_variables)]
 impl TryFrom<(&Response, &DidConfig)> for ResponseOnEvent {
     type Error = Error;
     fn try_from((resp, cfg): (&Response, &DidConfig)) -> Result<Self, Self::Error> {
-        Err(Error::NotImplement)
+        let service = resp.service();
+        if service != Service::ResponseOnEvent || resp.sub_func.is_none() {
+            return Err(Error::ServiceError(service));
+        }
+
+        let event_type: EventType = resp.sub_function().unwrap().function()?;
+        Self::decode(event_type.event_type(), &resp.data, cfg)
     }
 }
@@ -0,0 +1,433 @@
+//! UDS server(ECU) framework over a [`DiagTransport`].
+
+#![allow(clippy::non_minimal_cfg)]
+
+mod state;
+
+pub use self::state::{
+    AccessRule, AccessTable, ECUResetHandler, ServerState, SessionCtrlHandler, StateEvent,
+    TesterPresentHandler,
+};
+
+#[cfg(any(feature = "std2020"))]
+use crate::AuthenticationTask;
+#[cfg(any(feature = "std2013", feature = "std2020"))]
+use crate::ModeOfOperation;
+#[cfg(any(feature = "std2006", feature = "std2013"))]
+use crate::TimingParameterAccessType;
+use crate::{
+    error::Error,
+    request::Request,
+    response::{self, Code, Response, SessionTiming},
+    transport::{Addressing, DiagTransport, Pdu},
+    CommunicationCtrlType, DTCReportType, DTCSettingType, DefinitionType, DidConfig, ECUResetType,
+    EventType, LinkCtrlType, RoutineCtrlType, SecurityAccessLevel, Service, SessionType,
+    TesterPresentType, SUPPRESS_POSITIVE,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+/// The timeout(ms) of each receiving of [`UdsServer::serve`].
+const RECEIVE_TIMEOUT: u64 = 100;
+/// default margin(ms) before P2/P2* expiring when NRC 0x78 is sent
+const DEFAULT_PENDING_MARGIN: u64 = 10;
+
+/// The handler of one service registered to [`UdsServer`].
+///
+/// The request passed to the handler is decoded and has passed the checks of the server.
+/// The NRC returned shall be one of the service's `*_NEGATIVES` in [`response`],
+/// or 0x10(GeneralReject), 0x21(BusyRepeatRequest), or the vehicle manufacturer specific one.
+#[async_trait::async_trait]
+pub trait ServiceHandler: Send + Sync {
+    /// The supported sub-functions(without suppressPosRspMsgIndicationBit),
+    /// `None` if all are supported or the service has no sub-function.
+    fn sub_functions(&self) -> Option<Vec<u8>> {
+        None
+    }
+    /// Handle the request and return the data of the positive response following the sub-function.
+    async fn handle(&self, request: &Request) -> Result<Vec<u8>, Code>;
+    /// Called after the positive response is sent or suppressed,
+    /// e.g. to take the action which shall follow the response.
+    async fn responded(&self, _request: &Request) {}
+}
+
+/// The session and security checks of [`UdsServer`] before calling the handlers.
+pub trait AccessControl: Send + Sync {
+    /// NRC 0x7F if the service is not supported in the active session,
+    /// or 0x33 if the security access is required.
+    fn check_service(&self, _service: Service) -> Result<(), Code> {
+        Ok(())
+    }
+    /// NRC 0x7E if the sub-function is not supported in the active session,
+    /// or 0x33 if the security access is required.
+    fn check_sub_function(&self, _service: Service, _sub_func: u8) -> Result<(), Code> {
+        Ok(())
+    }
+    /// P2 and P2* of the active session.
+    fn timing(&self) -> SessionTiming {
+        Default::default()
+    }
+    /// Called when a request is received, e.g. to restart the S3 timer.
+    fn activity(&self) {}
+    /// Called periodically by [`UdsServer::serve`], e.g. to check the S3 timer.
+    fn poll(&self) {}
+}
+
+/// All services and sub-functions are allowed in any session.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct AllowAll;
+
+impl AccessControl for AllowAll {}
+
+/// The UDS server receiving the requests by [`DiagTransport`] and dispatching them to the handlers.
+///
+/// The NRCs are checked in the order of ISO 14229-1:
+/// 0x11(service not supported), 0x7F(service not supported in active session),
+/// 0x33(security of the service), 0x13(minimum length of the sub-function),
+/// 0x12(sub-function not supported), 0x7E(sub-function not supported in active session),
+/// 0x33(security of the sub-function), 0x13(message length or format),
+/// 0x31(invalid data of the request), then the NRC returned by the handler.
+///
+/// NRC 0x78 is sent when the handler doesn't finish before P2 expiring, and again before
+/// each P2* expiring. The positive response is not sent when suppressPosRspMsgIndicationBit
+/// is set unless NRC 0x78 has been sent, and the NRCs 0x11, 0x12, 0x31, 0x7E, 0x7F
+/// are not sent to the functional requests.
+pub struct UdsServer<T> {
+    transport: T,
+    did_config: DidConfig,
+    handlers: HashMap<Service, Box<dyn ServiceHandler>>,
+    access: Arc<dyn AccessControl>,
+    pending_margin: u64,
+}
+
+impl<T: DiagTransport> UdsServer<T> {
+    pub fn new(transport: T, did_config: DidConfig) -> Self {
+        Self {
+            transport,
+            did_config,
+            handlers: Default::default(),
+            access: Arc::new(AllowAll),
+            pending_margin: DEFAULT_PENDING_MARGIN,
+        }
+    }
+
+    /// The session and security checks, [`AllowAll`] by default.
+    #[inline]
+    pub fn with_access(mut self, access: Arc<dyn AccessControl>) -> Self {
+        self.access = access;
+        self
+    }
+
+    /// Send NRC 0x78 `margin`(ms) before P2/P2* expiring, 10ms by default.
+    #[inline]
+    pub fn with_pending_margin(mut self, margin: u64) -> Self {
+        self.pending_margin = margin;
+        self
+    }
+
+    /// Register the handler of the service, the previous one is replaced.
+    pub fn register<H>(&mut self, service: Service, handler: H)
+    where
+        H: ServiceHandler + 'static,
+    {
+        self.handlers.insert(service, Box::new(handler));
+    }
+
+    #[inline]
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    #[inline]
+    pub fn did_config(&self) -> &DidConfig {
+        &self.did_config
+    }
+
+    /// Serve the requests until the transport is failed, e.g. the tester is closed.
+    pub async fn serve(&self) -> Result<(), Error> {
+        loop {
+            self.access.poll();
+            match self.transport.receive(RECEIVE_TIMEOUT).await {
+                Ok(pdu) => self.process(pdu).await?,
+                Err(Error::Timeout { .. }) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Process one request and send the responses.
+    pub async fn process(&self, pdu: Pdu) -> Result<(), Error> {
+        let Pdu { addressing, data } = pdu;
+        rsutil::trace!("UDS - received: {}", hex::encode(&data));
+        let Some(&sid) = data.first() else {
+            return Ok(());
+        };
+        self.access.activity();
+
+        let (service, handler) = match Service::try_from(sid) {
+            Ok(service) if service != Service::NRC => match self.handlers.get(&service) {
+                Some(handler) => (service, handler),
+                None => {
+                    return self
+                        .negative(addressing, service, Code::ServiceNotSupported)
+                        .await
+                }
+            },
+            _ => {
+                rsutil::debug!("UDS - service {:02X} is not supported", sid);
+                if addressing == Addressing::Functional {
+                    return Ok(());
+                }
+                let data = [Service::NRC.into(), sid, Code::ServiceNotSupported.into()];
+                return self.send(&data).await;
+            }
+        };
+
+        let request = match self.check(service, handler.as_ref(), &data) {
+            Ok(v) => v,
+            Err(code) => return self.negative(addressing, service, code).await,
+        };
+        let (sub_func, suppress_positive) = match request.sub_function() {
+            Some(v) => (
+                Some(u8::from(v) & !SUPPRESS_POSITIVE),
+                v.is_suppress_positive(),
+            ),
+            None => (None, false),
+        };
+
+        let (ret, pending) = self.handle(service, handler.as_ref(), &request).await?;
+        match ret {
+            Ok(data) => {
+                if !suppress_positive || pending {
+                    match Response::new(service, sub_func, data, &self.did_config) {
+                        Ok(response) => self.send(&Vec::from(response)).await?,
+                        Err(e) => {
+                            rsutil::warn!("UDS - invalid response of service `{}`: {}", service, e);
+                            return self
+                                .negative(addressing, service, Code::GeneralReject)
+                                .await;
+                        }
+                    }
+                }
+                handler.responded(&request).await;
+
+                Ok(())
+            }
+            Err(code) => {
+                let code = match is_allowed(service, code) {
+                    true => code,
+                    false => {
+                        rsutil::warn!(
+                            "UDS - NRC {:?} is not allowed by service `{}`",
+                            code,
+                            service
+                        );
+                        Code::GeneralReject
+                    }
+                };
+                self.negative(addressing, service, code).await
+            }
+        }
+    }
+
+    /// The checks from NRC 0x7F to the message length of the request.
+    fn check(
+        &self,
+        service: Service,
+        handler: &dyn ServiceHandler,
+        data: &[u8],
+    ) -> Result<Request, Code> {
+        self.access.check_service(service)?;
+
+        if has_sub_function(service) {
+            // the minimum length of SID and sub-function
+            let sub_func = match data.get(1) {
+                Some(&v) => v & !SUPPRESS_POSITIVE,
+                None => return Err(Code::IncorrectMessageLengthOrInvalidFormat),
+            };
+            if !is_sub_function_defined(service, sub_func)
+                || handler
+                    .sub_functions()
+                    .is_some_and(|v| !v.contains(&sub_func))
+            {
+                return Err(Code::SubFunctionNotSupported);
+            }
+            self.access.check_sub_function(service, sub_func)?;
+        }
+
+        Request::try_from((data, &self.did_config)).map_err(|e| {
+            rsutil::debug!("UDS - invalid request of service `{}`: {}", service, e);
+            match e {
+                Error::InvalidDataLength { .. } | Error::InvalidData(_) => {
+                    Code::IncorrectMessageLengthOrInvalidFormat
+                }
+                _ => Code::RequestOutOfRange,
+            }
+        })
+    }
+
+    /// Wait for the handler and send NRC 0x78 before P2/P2* expiring,
+    /// return whether NRC 0x78 has been sent.
+    async fn handle(
+        &self,
+        service: Service,
+        handler: &dyn ServiceHandler,
+        request: &Request,
+    ) -> Result<(Result<Vec<u8>, Code>, bool), Error> {
+        let timing = self.access.timing();
+        let mut timeout = timing.p2_ms().saturating_sub(self.pending_margin);
+        let mut pending = false;
+        let mut future = handler.handle(request);
+        loop {
+            match tokio::time::timeout(Duration::from_millis(timeout), &mut future).await {
+                Ok(ret) => return Ok((ret, pending)),
+                Err(_) => {
+                    rsutil::debug!("UDS - service `{}` response pending", service);
+                    let response = Response::new_negative(
+                        service,
+                        Code::RequestCorrectlyReceivedResponsePending,
+                    );
+                    self.send(&Vec::from(response)).await?;
+                    pending = true;
+                    timeout = timing.p2_star_ms().saturating_sub(self.pending_margin);
+                }
+            }
+        }
+    }
+
+    async fn negative(
+        &self,
+        addressing: Addressing,
+        service: Service,
+        code: Code,
+    ) -> Result<(), Error> {
+        if addressing == Addressing::Functional
+            && matches!(
+                code,
+                Code::ServiceNotSupported
+                    | Code::SubFunctionNotSupported
+                    | Code::RequestOutOfRange
+                    | Code::SubFunctionNotSupportedInActiveSession
+                    | Code::ServiceNotSupportedInActiveSession
+            )
+        {
+            return Ok(());
+        }
+
+        let response = Response::new_negative(service, code);
+        self.send(&Vec::from(response)).await
+    }
+
+    #[inline]
+    async fn send(&self, data: &[u8]) -> Result<(), Error> {
+        rsutil::trace!("UDS - sending: {}", hex::encode(data));
+        self.transport.send(Addressing::Physical, data).await
+    }
+}
+
+/// The NRCs allowed by the service.
+pub fn negatives(service: Service) -> Option<&'static HashSet<Code>> {
+    let negatives: &'static LazyLock<HashSet<Code>> = match service {
+        Service::SessionCtrl => &response::SESSION_CTRL_NEGATIVES,
+        Service::ECUReset => &response::ECU_RESET_NEGATIVES,
+        Service::ClearDiagnosticInfo => &response::CLEAR_DIAGNOSTIC_INFO_NEGATIVES,
+        Service::ReadDTCInfo => &response::READ_DTC_INFO_NEGATIVES,
+        Service::ReadDID => &response::READ_DID_NEGATIVES,
+        Service::ReadMemByAddr => &response::READ_MEM_BY_ADDR_NEGATIVES,
+        Service::ReadScalingDID => &response::READ_SCALING_DID_NEGATIVES,
+        Service::SecurityAccess => &response::SECURITY_ACCESS_NEGATIVES,
+        Service::CommunicationCtrl => &response::COMMUNICATION_CTRL_NEGATIVES,
+        #[cfg(any(feature = "std2020"))]
+        Service::Authentication => &response::AUTH_NEGATIVES,
+        Service::ReadDataByPeriodId => &response::READ_DATA_BY_PERIOD_ID_NEGATIVES,
+        Service::DynamicalDefineDID => &response::DYNAMICAL_DID_NEGATIVES,
+        Service::WriteDID => &response::WRITE_DID_NEGATIVES,
+        Service::IOCtrl => &response::IO_CTRL_NEGATIVES,
+        Service::RoutineCtrl => &response::ROUTINE_CTRL_NEGATIVES,
+        Service::RequestDownload => &response::REQUEST_DOWNLOAD_NEGATIVES,
+        Service::RequestUpload => &response::REQUEST_UPLOAD_NEGATIVES,
+        Service::TransferData => &response::TRANSFER_DATA_NEGATIVES,
+        Service::RequestTransferExit => &response::REQUEST_TRANSFER_EXIT_NEGATIVES,
+        #[cfg(any(feature = "std2013", feature = "std2020"))]
+        Service::RequestFileTransfer => &response::REQUEST_FILE_TRANSFER_NEGATIVES,
+        Service::WriteMemByAddr => &response::WRITE_MEM_BY_ADDR_NEGATIVES,
+        Service::TesterPresent => &response::TESTER_PRESENT_NEGATIVES,
+        #[cfg(any(feature = "std2006", feature = "std2013"))]
+        Service::AccessTimingParam => &response::ACCESS_TIMING_PARAM_NEGATIVES,
+        Service::SecuredDataTrans => &response::SECURED_DATA_TRANS_NEGATIVES,
+        Service::CtrlDTCSetting => &response::CTRL_DTC_SETTING_NEGATIVES,
+        Service::ResponseOnEvent => &response::RESPONSE_ON_EVENT_NEGATIVES,
+        Service::LinkCtrl => &response::LINK_CTRL_NEGATIVES,
+        Service::NRC => return None,
+    };
+
+    Some(LazyLock::force(negatives))
+}
+
+/// Whether the handler of the service is allowed to return the NRC.
+pub fn is_allowed(service: Service, code: Code) -> bool {
+    match code {
+        Code::Positive => false,
+        Code::GeneralReject | Code::BusyRepeatRequest | Code::VehicleManufacturerSpecific(_) => {
+            true
+        }
+        _ => negatives(service).is_some_and(|v| {
+            // the conditions(0x81~0x94) specify 0x22(ConditionsNotCorrect)
+            v.contains(&code)
+                || (matches!(u8::from(code), 0x81..=0x94)
+                    && v.contains(&Code::ConditionsNotCorrect))
+        }),
+    }
+}
+
+#[inline]
+fn has_sub_function(service: Service) -> bool {
+    match service {
+        Service::SessionCtrl
+        | Service::ECUReset
+        | Service::SecurityAccess
+        | Service::CommunicationCtrl
+        | Service::ReadDTCInfo
+        | Service::RoutineCtrl
+        | Service::CtrlDTCSetting
+        | Service::TesterPresent
+        | Service::LinkCtrl
+        | Service::ResponseOnEvent
+        | Service::DynamicalDefineDID => true,
+        #[cfg(any(feature = "std2006", feature = "std2013"))]
+        Service::AccessTimingParam => true,
+        #[cfg(any(feature = "std2020"))]
+        Service::Authentication => true,
+        #[cfg(any(feature = "std2013", feature = "std2020"))]
+        Service::RequestFileTransfer => true,
+        _ => false,
+    }
+}
+
+/// Whether the sub-function(without suppressPosRspMsgIndicationBit) is not reserved
+/// for the service with sub-function.
+fn is_sub_function_defined(service: Service, sub_func: u8) -> bool {
+    match service {
+        Service::SessionCtrl => SessionType::try_from(sub_func).is_ok(),
+        Service::ECUReset => ECUResetType::try_from(sub_func).is_ok(),
+        Service::SecurityAccess => SecurityAccessLevel::try_from(sub_func).is_ok(),
+        Service::CommunicationCtrl => CommunicationCtrlType::try_from(sub_func).is_ok(),
+        Service::ReadDTCInfo => DTCReportType::try_from(sub_func).is_ok(),
+        Service::RoutineCtrl => RoutineCtrlType::try_from(sub_func).is_ok(),
+        Service::CtrlDTCSetting => DTCSettingType::try_from(sub_func).is_ok(),
+        Service::TesterPresent => TesterPresentType::try_from(sub_func).is_ok(),
+        Service::LinkCtrl => LinkCtrlType::try_from(sub_func).is_ok(),
+        Service::ResponseOnEvent => EventType::try_from(sub_func).is_ok(),
+        Service::DynamicalDefineDID => DefinitionType::try_from(sub_func).is_ok(),
+        #[cfg(any(feature = "std2006", feature = "std2013"))]
+        Service::AccessTimingParam => TimingParameterAccessType::try_from(sub_func).is_ok(),
+        #[cfg(any(feature = "std2020"))]
+        Service::Authentication => AuthenticationTask::try_from(sub_func).is_ok(),
+        #[cfg(any(feature = "std2013", feature = "std2020"))]
+        Service::RequestFileTransfer => ModeOfOperation::try_from(sub_func).is_ok(),
+        _ => true,
+    }
+}
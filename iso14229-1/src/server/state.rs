@@ -0,0 +1,374 @@
+use super::{AccessControl, ServiceHandler};
+use crate::{
+    request::Request,
+    response::{Code, SessionTiming},
+    ECUResetType, SecurityAccessLevel, Service, SessionType, S3_SERVER_MS,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::broadcast;
+
+/// capacity of the event channel of [`ServerState`]
+const EVENT_CAPACITY: usize = 16;
+
+/// The changes of [`ServerState`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum StateEvent {
+    SessionChanged {
+        previous: SessionType,
+        session: SessionType,
+    },
+    /// `None` if the security access is locked.
+    SecurityChanged(Option<SecurityAccessLevel>),
+    /// No request within S3 in a non-default session, followed by the change to the default one.
+    S3Timeout,
+    ECUReset(ECUResetType),
+}
+
+/// The sessions and the security levels in which a service or a sub-function is allowed.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct AccessRule {
+    /// allowed in all sessions if empty
+    pub sessions: Vec<SessionType>,
+    /// one of the levels shall be unlocked, no security access is required if empty
+    pub security: Vec<SecurityAccessLevel>,
+}
+
+impl AccessRule {
+    pub fn new(sessions: Vec<SessionType>) -> Self {
+        Self {
+            sessions,
+            security: Default::default(),
+        }
+    }
+
+    #[inline]
+    pub fn with_security(mut self, security: Vec<SecurityAccessLevel>) -> Self {
+        self.security = security;
+        self
+    }
+
+    /// NRC `session_nrc` if not allowed in the session, or 0x33 if the level is not unlocked.
+    fn check(
+        &self,
+        session: SessionType,
+        security: Option<SecurityAccessLevel>,
+        session_nrc: Code,
+    ) -> Result<(), Code> {
+        if !self.sessions.is_empty() && !self.sessions.contains(&session) {
+            return Err(session_nrc);
+        }
+        if !self.security.is_empty() && !security.is_some_and(|v| self.security.contains(&v)) {
+            return Err(Code::SecurityAccessDenied);
+        }
+
+        Ok(())
+    }
+}
+
+/// The services and sub-functions allowed per session and security level.
+///
+/// The services and sub-functions without rules are allowed in all sessions without security access.
+#[derive(Debug, Clone, Default)]
+pub struct AccessTable {
+    services: HashMap<Service, AccessRule>,
+    sub_functions: HashMap<(Service, u8), AccessRule>,
+}
+
+impl AccessTable {
+    #[inline]
+    pub fn with_service(mut self, service: Service, rule: AccessRule) -> Self {
+        self.services.insert(service, rule);
+        self
+    }
+
+    /// The rule of the sub-function(without suppressPosRspMsgIndicationBit).
+    #[inline]
+    pub fn with_sub_function(mut self, service: Service, sub_func: u8, rule: AccessRule) -> Self {
+        self.sub_functions.insert((service, sub_func), rule);
+        self
+    }
+
+    #[inline]
+    pub fn service(&self, service: Service) -> Option<&AccessRule> {
+        self.services.get(&service)
+    }
+
+    #[inline]
+    pub fn sub_function(&self, service: Service, sub_func: u8) -> Option<&AccessRule> {
+        self.sub_functions.get(&(service, sub_func))
+    }
+}
+
+#[derive(Debug)]
+struct Inner {
+    session: SessionType,
+    security: Option<SecurityAccessLevel>,
+    last_activity: Instant,
+}
+
+/// The diagnostic session, security access and S3 timer of a UDS server.
+///
+/// The security access is locked at each session change, and the default session
+/// is restored without any request within S3 in a non-default session or by the ECU reset.
+/// As [`AccessControl`] of [`super::UdsServer`], the requests are checked by the [`AccessTable`].
+#[derive(Debug)]
+pub struct ServerState {
+    inner: Mutex<Inner>,
+    table: AccessTable,
+    timings: HashMap<SessionType, SessionTiming>,
+    s3: u64,
+    events: broadcast::Sender<StateEvent>,
+}
+
+impl ServerState {
+    pub fn new(table: AccessTable) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            inner: Mutex::new(Inner {
+                session: Default::default(),
+                security: Default::default(),
+                last_activity: Instant::now(),
+            }),
+            table,
+            timings: Default::default(),
+            s3: S3_SERVER_MS,
+            events,
+        }
+    }
+
+    /// P2 and P2* of the session, the maximum values by default.
+    #[inline]
+    pub fn with_timing(mut self, session: SessionType, timing: SessionTiming) -> Self {
+        self.timings.insert(session, timing);
+        self
+    }
+
+    /// S3(ms) of the non-default sessions, 5000ms by default.
+    #[inline]
+    pub fn with_s3(mut self, s3: u64) -> Self {
+        self.s3 = s3;
+        self
+    }
+
+    #[inline]
+    pub fn table(&self) -> &AccessTable {
+        &self.table
+    }
+
+    #[inline]
+    pub fn session(&self) -> SessionType {
+        self.inner.lock().unwrap().session
+    }
+
+    /// The unlocked security level, `None` if locked.
+    #[inline]
+    pub fn security(&self) -> Option<SecurityAccessLevel> {
+        self.inner.lock().unwrap().security
+    }
+
+    /// P2 and P2* of the session.
+    #[inline]
+    pub fn session_timing(&self, session: SessionType) -> SessionTiming {
+        self.timings.get(&session).copied().unwrap_or_default()
+    }
+
+    /// Receive the events from now on.
+    #[inline]
+    pub fn subscribe(&self) -> broadcast::Receiver<StateEvent> {
+        self.events.subscribe()
+    }
+
+    /// Change the session and lock the security access.
+    pub fn set_session(&self, session: SessionType) {
+        let mut inner = self.inner.lock().unwrap();
+        self.change_session(&mut inner, session);
+    }
+
+    /// Unlock the security level, e.g. after the key of service 27 is accepted.
+    pub fn unlock(&self, level: SecurityAccessLevel) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.security != Some(level) {
+            inner.security = Some(level);
+            self.emit(StateEvent::SecurityChanged(Some(level)));
+        }
+    }
+
+    pub fn lock(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        self.lock_security(&mut inner);
+    }
+
+    /// Restore the default session with the security access locked.
+    pub fn ecu_reset(&self, reset: ECUResetType) {
+        let mut inner = self.inner.lock().unwrap();
+        self.emit(StateEvent::ECUReset(reset));
+        self.change_session(&mut inner, SessionType::Default);
+    }
+
+    /// Restart the S3 timer.
+    #[inline]
+    pub fn touch(&self) {
+        self.inner.lock().unwrap().last_activity = Instant::now();
+    }
+
+    /// Restore the default session if S3 expired in a non-default session,
+    /// return whether it's expired.
+    pub fn check_s3(&self) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.session == SessionType::Default
+            || inner.last_activity.elapsed() < Duration::from_millis(self.s3)
+        {
+            return false;
+        }
+
+        rsutil::debug!("UDS - S3 expired in session {:?}", inner.session);
+        self.emit(StateEvent::S3Timeout);
+        self.change_session(&mut inner, SessionType::Default);
+
+        true
+    }
+
+    fn change_session(&self, inner: &mut Inner, session: SessionType) {
+        let previous = inner.session;
+        inner.session = session;
+        inner.last_activity = Instant::now();
+        self.lock_security(inner);
+        if previous != session {
+            rsutil::debug!("UDS - session changed from {:?} to {:?}", previous, session);
+            self.emit(StateEvent::SessionChanged { previous, session });
+        }
+    }
+
+    fn lock_security(&self, inner: &mut Inner) {
+        if inner.security.take().is_some() {
+            self.emit(StateEvent::SecurityChanged(None));
+        }
+    }
+
+    #[inline]
+    fn emit(&self, event: StateEvent) {
+        // no error but no subscriber
+        let _ = self.events.send(event);
+    }
+}
+
+impl Default for ServerState {
+    fn default() -> Self {
+        Self::new(Default::default())
+    }
+}
+
+impl AccessControl for ServerState {
+    fn check_service(&self, service: Service) -> Result<(), Code> {
+        match self.table.service(service) {
+            Some(rule) => {
+                let inner = self.inner.lock().unwrap();
+                rule.check(
+                    inner.session,
+                    inner.security,
+                    Code::ServiceNotSupportedInActiveSession,
+                )
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn check_sub_function(&self, service: Service, sub_func: u8) -> Result<(), Code> {
+        match self.table.sub_function(service, sub_func) {
+            Some(rule) => {
+                let inner = self.inner.lock().unwrap();
+                rule.check(
+                    inner.session,
+                    inner.security,
+                    Code::SubFunctionNotSupportedInActiveSession,
+                )
+            }
+            None => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn timing(&self) -> SessionTiming {
+        self.session_timing(self.session())
+    }
+
+    #[inline]
+    fn activity(&self) {
+        self.touch();
+    }
+
+    #[inline]
+    fn poll(&self) {
+        self.check_s3();
+    }
+}
+
+/// The handler of service 10 changing the session of [`ServerState`],
+/// the sessions supported are the ones with the timing configured and the default one.
+pub struct SessionCtrlHandler(pub Arc<ServerState>);
+
+#[async_trait::async_trait]
+impl ServiceHandler for SessionCtrlHandler {
+    fn sub_functions(&self) -> Option<Vec<u8>> {
+        let mut result: Vec<u8> = self.0.timings.keys().map(|&v| v.into()).collect();
+        result.push(SessionType::Default.into());
+        result.sort();
+        result.dedup();
+
+        Some(result)
+    }
+
+    async fn handle(&self, request: &Request) -> Result<Vec<u8>, Code> {
+        let session = request
+            .sub_function()
+            .and_then(|v| v.function::<SessionType>().ok())
+            .ok_or(Code::SubFunctionNotSupported)?;
+        self.0.set_session(session);
+
+        Ok(self.0.session_timing(session).into())
+    }
+}
+
+/// The handler of service 11 restoring the default session of [`ServerState`]
+/// after the positive response is sent, the reset itself is left to the subscribers
+/// of [`StateEvent::ECUReset`].
+pub struct ECUResetHandler(pub Arc<ServerState>);
+
+#[async_trait::async_trait]
+impl ServiceHandler for ECUResetHandler {
+    async fn handle(&self, request: &Request) -> Result<Vec<u8>, Code> {
+        let reset = request
+            .sub_function()
+            .and_then(|v| v.function::<ECUResetType>().ok())
+            .ok_or(Code::SubFunctionNotSupported)?;
+
+        match reset {
+            // the power down time is not available
+            ECUResetType::EnableRapidPowerShutDown => Ok(vec![0xFF]),
+            _ => Ok(vec![]),
+        }
+    }
+
+    async fn responded(&self, request: &Request) {
+        if let Some(reset) = request
+            .sub_function()
+            .and_then(|v| v.function::<ECUResetType>().ok())
+        {
+            self.0.ecu_reset(reset);
+        }
+    }
+}
+
+/// The handler of service 3E, the S3 timer is restarted by any request.
+pub struct TesterPresentHandler;
+
+#[async_trait::async_trait]
+impl ServiceHandler for TesterPresentHandler {
+    async fn handle(&self, _: &Request) -> Result<Vec<u8>, Code> {
+        Ok(vec![])
+    }
+}
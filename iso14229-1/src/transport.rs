@@ -0,0 +1,154 @@
+//! Transports of the UDS PDUs, e.g. ISO-TP(ISO 15765-2), DoIP(ISO 13400-2)
+//! or an in-memory loopback.
+
+use crate::error::Error;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
+
+/// default max. PDU size of [`Loopback`]
+const DEFAULT_MAX_PDU_SIZE: usize = 0xFFFF;
+
+/// The target address type of a PDU.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum Addressing {
+    #[default]
+    Physical,
+    Functional,
+}
+
+/// A UDS PDU sent or received by [`DiagTransport`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Pdu {
+    pub addressing: Addressing,
+    pub data: Vec<u8>,
+}
+
+/// The transport of the UDS PDUs used by the UDS client and server.
+#[async_trait::async_trait]
+pub trait DiagTransport: Send + Sync {
+    /// max. size of the PDUs sent by the transport
+    fn max_pdu_size(&self) -> usize;
+    /// Send the PDU to the physical or functional address.
+    async fn send(&self, addressing: Addressing, data: &[u8]) -> Result<(), Error>;
+    /// Wait for a PDU within `timeout`(ms), [`Error::Timeout`] if none.
+    async fn receive(&self, timeout: u64) -> Result<Pdu, Error>;
+}
+
+/// One end of an in-memory transport, the PDUs sent are received by the other end.
+#[derive(Debug)]
+pub struct Loopback {
+    sender: mpsc::UnboundedSender<Pdu>,
+    receiver: Mutex<mpsc::UnboundedReceiver<Pdu>>,
+    max_pdu_size: usize,
+}
+
+impl Loopback {
+    /// The two connected ends, e.g. for the client and the server.
+    pub fn pair() -> (Self, Self) {
+        let (tx1, rx1) = mpsc::unbounded_channel();
+        let (tx2, rx2) = mpsc::unbounded_channel();
+
+        (Self::new(tx1, rx2), Self::new(tx2, rx1))
+    }
+
+    /// Limit the size of the PDUs sent, 0xFFFF by default.
+    #[inline]
+    pub fn with_max_pdu_size(mut self, max_pdu_size: usize) -> Self {
+        self.max_pdu_size = max_pdu_size;
+        self
+    }
+
+    fn new(sender: mpsc::UnboundedSender<Pdu>, receiver: mpsc::UnboundedReceiver<Pdu>) -> Self {
+        Self {
+            sender,
+            receiver: Mutex::new(receiver),
+            max_pdu_size: DEFAULT_MAX_PDU_SIZE,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl DiagTransport for Loopback {
+    #[inline]
+    fn max_pdu_size(&self) -> usize {
+        self.max_pdu_size
+    }
+
+    async fn send(&self, addressing: Addressing, data: &[u8]) -> Result<(), Error> {
+        if data.len() > self.max_pdu_size {
+            return Err(Error::InvalidDataLength {
+                expect: self.max_pdu_size,
+                actual: data.len(),
+            });
+        }
+
+        self.sender
+            .send(Pdu {
+                addressing,
+                data: data.to_vec(),
+            })
+            .map_err(|_| Error::TransportError("the other end is closed".into()))
+    }
+
+    async fn receive(&self, timeout: u64) -> Result<Pdu, Error> {
+        let mut receiver = self.receiver.lock().await;
+        match tokio::time::timeout(Duration::from_millis(timeout), receiver.recv()).await {
+            Ok(Some(pdu)) => Ok(pdu),
+            Ok(None) => Err(Error::TransportError("the other end is closed".into())),
+            Err(_) => Err(Error::Timeout {
+                value: timeout,
+                unit: "ms",
+            }),
+        }
+    }
+}
+
+#[cfg(feature = "can")]
+mod can {
+    use super::{Addressing, DiagTransport, Pdu};
+    use crate::error::Error;
+    use iso15765_2::{
+        can::{AddressType, CanIsoTp},
+        IsoTp, IsoTpError, MAX_LENGTH_2004,
+    };
+    use rs_can::{CanDevice, CanFrame};
+    use std::fmt::Display;
+
+    /// The received PDUs are reported as physical, ISO-TP doesn't tell the target address type.
+    ///
+    /// So a server on it handles the functional requests as physical ones,
+    /// e.g. without suppressing the NRCs.
+    #[async_trait::async_trait]
+    impl<D, C, F> DiagTransport for CanIsoTp<D, C, F>
+    where
+        D: CanDevice<Channel = C, Frame = F> + Clone + Send + 'static,
+        C: Clone + Eq + Display + Send + Sync + 'static,
+        F: CanFrame<Channel = C> + Clone + Display + Send + 'static,
+    {
+        #[inline]
+        fn max_pdu_size(&self) -> usize {
+            MAX_LENGTH_2004
+        }
+
+        async fn send(&self, addressing: Addressing, data: &[u8]) -> Result<(), Error> {
+            let addr_type = match addressing {
+                Addressing::Physical => AddressType::Physical,
+                Addressing::Functional => AddressType::Functional,
+            };
+            self.transmit(addr_type, data).await?;
+
+            Ok(())
+        }
+
+        async fn receive(&self, timeout: u64) -> Result<Pdu, Error> {
+            match self.wait_data(timeout).await {
+                Ok(data) => Ok(Pdu {
+                    addressing: Addressing::Physical,
+                    data: data.to_vec(),
+                }),
+                Err(IsoTpError::Timeout { value, unit }) => Err(Error::Timeout { value, unit }),
+                Err(e) => Err(e.into()),
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! UDS client
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use iso14229_1::{
+        client::UdsClient,
+        response::Code,
+        transport::{Addressing, DiagTransport, Loopback, Pdu},
+        DataIdentifier, DidConfig, ECUResetType, Iso14229Error, Service, SessionType,
+    };
+    use std::time::Duration;
+
+    /// Assert the request received by the ECU end.
+    async fn expect(ecu: &Loopback, request: &str) -> anyhow::Result<()> {
+        let pdu = ecu.receive(1_000).await?;
+        assert_eq!(
+            pdu,
+            Pdu {
+                addressing: Addressing::Physical,
+                data: hex::decode(request)?,
+            }
+        );
+
+        Ok(())
+    }
+
+    async fn respond(ecu: &Loopback, response: &str) -> anyhow::Result<()> {
+        ecu.send(Addressing::Physical, &hex::decode(response)?)
+            .await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_response_pending() -> anyhow::Result<()> {
+        let (tester, ecu) = Loopback::pair();
+        let mut client = UdsClient::new(tester, DidConfig::default());
+        let task = tokio::spawn(async move {
+            expect(&ecu, "1003").await?;
+            respond(&ecu, "7F1078").await?;
+            // longer than P2 of the default session
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            respond(&ecu, "5003001901F4").await?;
+
+            // P2 and P2* of the latest session
+            expect(&ecu, "3E00").await?;
+            respond(&ecu, "7F3E78").await?;
+            tokio::time::sleep(Duration::from_millis(40)).await;
+            respond(&ecu, "7E00").await?;
+
+            anyhow::Ok(())
+        });
+
+        let session = client.session_ctrl(SessionType::Extended, false).await?;
+        assert_eq!(session.map(|v| v.0.p2), Some(25));
+        assert_eq!(client.timing().p2_ms(), 25);
+        assert!(client.tester_present(false).await?.is_some());
+
+        task.await??;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_suppress_positive() -> anyhow::Result<()> {
+        let (tester, ecu) = Loopback::pair();
+        let mut client = UdsClient::new(tester, DidConfig::default());
+
+        assert!(client.tester_present(true).await?.is_none());
+        expect(&ecu, "3E80").await?;
+
+        // the negative response is still sent
+        let task = tokio::spawn(async move {
+            expect(&ecu, "1083").await?;
+            respond(&ecu, "7F1012").await?;
+            // the final response after NRC 0x78
+            expect(&ecu, "3E80").await?;
+            respond(&ecu, "7F3E78").await?;
+            respond(&ecu, "7E00").await?;
+
+            anyhow::Ok(ecu)
+        });
+        let err = client
+            .session_ctrl(SessionType::Extended, true)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Iso14229Error::NRCError {
+                service: Service::SessionCtrl,
+                code: Code::SubFunctionNotSupported,
+            }
+        ));
+        assert!(client.tester_present(true).await?.is_some());
+
+        // nothing is left for the next request
+        let ecu = task.await??;
+        respond(&ecu, "7E00").await?;
+        assert!(client.tester_present(false).await?.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unexpected_response() -> anyhow::Result<()> {
+        let (tester, ecu) = Loopback::pair();
+        let mut client = UdsClient::new(tester, DidConfig::default());
+
+        respond(&ecu, "7F1122").await?;
+        let err = client
+            .ecu_reset(ECUResetType::HardReset, false)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Iso14229Error::NRCError {
+                service: Service::ECUReset,
+                code: Code::ConditionsNotCorrect,
+            }
+        ));
+
+        respond(&ecu, "5002003201F4").await?;
+        let err = client
+            .session_ctrl(SessionType::Extended, false)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Iso14229Error::UnexpectedSubFunction {
+                service: Service::SessionCtrl,
+                expect: 0x03,
+                actual: 0x02,
+            }
+        ));
+
+        respond(&ecu, "5101").await?;
+        let err = client
+            .session_ctrl(SessionType::Extended, false)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Iso14229Error::UnexpectedResponse {
+                expect: Service::SessionCtrl,
+                actual: Service::ECUReset,
+            }
+        ));
+
+        // no response within P2
+        let err = client
+            .ecu_reset(ECUResetType::HardReset, false)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Iso14229Error::Timeout { value: 50, .. }));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_read_did() -> anyhow::Result<()> {
+        let (tester, ecu) = Loopback::pair();
+        let mut client = UdsClient::new(tester, DidConfig::default());
+        client.add_did(DataIdentifier::VIN, 17);
+
+        respond(&ecu, "62F1904441564443313030394E544C5036313338").await?;
+        let data = client.read_did(DataIdentifier::VIN, vec![]).await?;
+        assert_eq!(data.data.did, DataIdentifier::VIN);
+        assert_eq!(data.data.data, b"DAVDC1009NTLP6138".to_vec());
+        assert!(data.others.is_empty());
+        expect(&ecu, "22F190").await?;
+
+        Ok(())
+    }
+}
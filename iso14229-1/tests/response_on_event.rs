@@ -2,21 +2,389 @@
 
 #[cfg(test)]
 mod tests {
-    use iso14229_1::{response, DidConfig, Service};
+    use iso14229_1::{
+        request::{self, ComparisonLogicID, EventTypeParameter, Localization},
+        response, DTCReportType, DidConfig, EventType, Iso14229Error, ResponseOnEventType, Service,
+    };
 
-    /// not implement
     #[test]
-    #[ignore]
     fn test_request() -> anyhow::Result<()> {
-        // TODO
+        let cfg = DidConfig::default();
+
+        let source = hex::decode("86010208190208")?;
+        let request = request::Request::try_from((&source, &cfg))?;
+        let sub_func = request.sub_function().unwrap();
+        assert!(!sub_func.is_suppress_positive());
+        let event_type = sub_func.function::<EventType>()?;
+        assert!(!event_type.store_event());
+        assert_eq!(
+            event_type.event_type(),
+            ResponseOnEventType::OnDTCStatusChange
+        );
+        let data = request.data::<request::ResponseOnEvent>(&cfg)?;
+        assert_eq!(data.window_time, Some(0x02));
+        match data.param {
+            EventTypeParameter::OnDTCStatusChange {
+                dtc_status_mask,
+                service_to_respond,
+            } => {
+                assert_eq!(dtc_status_mask, 0x08);
+                assert_eq!(service_to_respond.service(), Service::ReadDTCInfo);
+                assert_eq!(
+                    service_to_respond
+                        .sub_function()
+                        .unwrap()
+                        .function::<DTCReportType>()?,
+                    DTCReportType::ReportDTCByStatusMask
+                );
+                assert_eq!(service_to_respond.raw_data(), &[0x08]);
+            }
+            _ => panic!("Unexpected data {:?}", data),
+        }
+
+        // the stored event
+        let source = hex::decode("864302F19022F190")?;
+        let request = request::Request::try_from((&source, &cfg))?;
+        let event_type = request.sub_function().unwrap().function::<EventType>()?;
+        assert!(event_type.store_event());
+        assert_eq!(
+            event_type.event_type(),
+            ResponseOnEventType::OnChangeOfDataIdentifier
+        );
+        let data = request.data::<request::ResponseOnEvent>(&cfg)?;
+        match data.param {
+            EventTypeParameter::OnChangeOfDataIdentifier {
+                did,
+                service_to_respond,
+            } => {
+                assert_eq!(did, 0xF190);
+                assert_eq!(Vec::from(service_to_respond), hex::decode("22F190")?);
+            }
+            _ => panic!("Unexpected data {:?}", data),
+        }
+
+        let source = hex::decode("860702F19003000000640A801022F190")?;
+        let request = request::Request::try_from((&source, &cfg))?;
+        let data = request.data::<request::ResponseOnEvent>(&cfg)?;
+        match data.param {
+            EventTypeParameter::OnComparisonOfValues {
+                did,
+                logic_id,
+                comparison_ref,
+                hysteresis_value,
+                localization,
+                service_to_respond,
+            } => {
+                assert_eq!(did, 0xF190);
+                assert_eq!(logic_id, ComparisonLogicID::Equal);
+                assert_eq!(comparison_ref, 100);
+                assert_eq!(hysteresis_value, 10);
+                assert!(localization.is_sign());
+                assert_eq!(localization.length_value(), 0);
+                assert_eq!(localization.offset_value(), 0x10);
+                assert_eq!(service_to_respond.service(), Service::ReadDID);
+            }
+            _ => panic!("Unexpected data {:?}", data),
+        }
+
+        let source = hex::decode("868502")?;
+        let request = request::Request::try_from((&source, &cfg))?;
+        let sub_func = request.sub_function().unwrap();
+        assert!(sub_func.is_suppress_positive());
+        assert_eq!(
+            sub_func.function::<EventType>()?.event_type(),
+            ResponseOnEventType::StartResponseOnEvent
+        );
+        let data = request.data::<request::ResponseOnEvent>(&cfg)?;
+        assert_eq!(data.param, EventTypeParameter::StartResponseOnEvent);
+
+        let source = hex::decode("8609020F0601")?;
+        let request = request::Request::try_from((&source, &cfg))?;
+        let data = request.data::<request::ResponseOnEvent>(&cfg)?;
+        assert_eq!(
+            data.param,
+            EventTypeParameter::ReportDTCRecordInformationOnDtcStatusChange {
+                dtc_status_mask: 0x0F,
+                dtc_sub_func: 0x06,
+                dtc_ext_data_record_num: 0x01,
+            }
+        );
+
+        // the eventWindowTime of ReportActivatedEvents is optional
+        let source = hex::decode("8604")?;
+        let request = request::Request::try_from((&source, &cfg))?;
+        let data = request.data::<request::ResponseOnEvent>(&cfg)?;
+        assert_eq!(data.window_time, None);
+        assert_eq!(data.param, EventTypeParameter::ReportActivatedEvents);
+        assert_eq!(Vec::from(request), source);
+
+        let source = hex::decode("860402")?;
+        let request = request::Request::try_from((&source, &cfg))?;
+        let data = request.data::<request::ResponseOnEvent>(&cfg)?;
+        assert_eq!(data.window_time, Some(0x02));
+        assert_eq!(data.param, EventTypeParameter::ReportActivatedEvents);
+
+        let source = hex::decode("8600")?;
+        let err = request::Request::try_from((&source, &cfg)).unwrap_err();
+        match err {
+            Iso14229Error::InvalidDataLength { expect, actual } => {
+                assert_eq!(expect, 1);
+                assert_eq!(actual, 0);
+            }
+            _ => panic!("Unexpected error: {:?}", err),
+        }
+
+        let source = hex::decode("86000200")?;
+        let err = request::Request::try_from((&source, &cfg)).unwrap_err();
+        match err {
+            Iso14229Error::InvalidDataLength { expect, actual } => {
+                assert_eq!(expect, 1);
+                assert_eq!(actual, 2);
+            }
+            _ => panic!("Unexpected error: {:?}", err),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_request_round_trip() -> anyhow::Result<()> {
+        let cfg = DidConfig::default();
+
+        let mut localization = Localization::new();
+        localization.sign_set(true).offset_set(0x10);
+        let service_to_respond = request::Request::new(Service::ReadDID, None, [0xF1, 0x90], &cfg)?;
+        let data = request::ResponseOnEvent {
+            window_time: Some(0x02),
+            param: EventTypeParameter::OnComparisonOfValues {
+                did: 0xF190,
+                logic_id: ComparisonLogicID::Equal,
+                comparison_ref: 100,
+                hysteresis_value: 10,
+                localization,
+                service_to_respond,
+            },
+        };
+        let event_type = EventType::new(true, data.param.event_type());
+        let request = request::Request::new(
+            Service::ResponseOnEvent,
+            Some(event_type.into()),
+            Vec::from(data.clone()),
+            &cfg,
+        )?;
+        assert_eq!(
+            Vec::from(request.clone()),
+            hex::decode("864702F19003000000640A801022F190")?
+        );
+        assert_eq!(request.data::<request::ResponseOnEvent>(&cfg)?, data);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_report_most_recent_dtc_round_trip() -> anyhow::Result<()> {
+        let cfg = DidConfig::default();
+
+        let source = hex::decode("86080209")?;
+        let request = request::Request::try_from((&source, &cfg))?;
+        let data = request.data::<request::ResponseOnEvent>(&cfg)?;
+        assert_eq!(
+            data,
+            request::ResponseOnEvent {
+                window_time: Some(0x02),
+                param: EventTypeParameter::ReportMostRecentDtcOnStatusChange { report_type: 0x09 },
+            }
+        );
+
+        let request = request::Request::new(
+            Service::ResponseOnEvent,
+            Some(EventType::new(false, data.param.event_type()).into()),
+            Vec::from(data.clone()),
+            &cfg,
+        )?;
+        assert_eq!(Vec::from(request.clone()), source);
+        assert_eq!(request.data::<request::ResponseOnEvent>(&cfg)?, data);
+
+        Ok(())
+    }
+
+    #[cfg(any(feature = "std2006", feature = "std2013"))]
+    #[test]
+    fn test_on_timer_interrupt_round_trip() -> anyhow::Result<()> {
+        let cfg = DidConfig::default();
+
+        let source = hex::decode("8602020122F190")?;
+        let request = request::Request::try_from((&source, &cfg))?;
+        let data = request.data::<request::ResponseOnEvent>(&cfg)?;
+        let service_to_respond = request::Request::new(Service::ReadDID, None, [0xF1, 0x90], &cfg)?;
+        assert_eq!(
+            data,
+            request::ResponseOnEvent {
+                window_time: Some(0x02),
+                param: EventTypeParameter::OnTimerInterrupt {
+                    timer_schedule: 0x01,
+                    service_to_respond,
+                },
+            }
+        );
+
+        let request = request::Request::new(
+            Service::ResponseOnEvent,
+            Some(EventType::new(false, data.param.event_type()).into()),
+            Vec::from(data.clone()),
+            &cfg,
+        )?;
+        assert_eq!(Vec::from(request.clone()), source);
+        assert_eq!(request.data::<request::ResponseOnEvent>(&cfg)?, data);
+
+        Ok(())
+    }
+
+    #[cfg(not(any(feature = "std2006", feature = "std2013")))]
+    #[test]
+    fn test_on_timer_interrupt_reserved() -> anyhow::Result<()> {
+        let cfg = DidConfig::default();
+
+        let source = hex::decode("8602020122F190")?;
+        let err = request::Request::try_from((&source, &cfg)).unwrap_err();
+        assert!(matches!(err, Iso14229Error::ReservedError(0x02)));
+
         Ok(())
     }
 
-    /// not implement
     #[test]
-    #[ignore]
     fn test_response() -> anyhow::Result<()> {
-        // TODO
+        let cfg = DidConfig::default();
+
+        let source = hex::decode("C601010208190208")?;
+        let response = response::Response::try_from((&source, &cfg))?;
+        let sub_func = response.sub_function().unwrap();
+        assert_eq!(
+            sub_func.function::<EventType>()?.event_type(),
+            ResponseOnEventType::OnDTCStatusChange
+        );
+        let data = response.data::<response::ResponseOnEvent>(&cfg)?;
+        match data {
+            response::ResponseOnEvent::Identified { number, event } => {
+                assert_eq!(number, 1);
+                assert_eq!(event.window_time, Some(0x02));
+                assert_eq!(
+                    event.param.event_type(),
+                    ResponseOnEventType::OnDTCStatusChange
+                );
+            }
+            _ => panic!("Unexpected data {:?}", data),
+        }
+
+        let source = hex::decode("C6000102")?;
+        let response = response::Response::try_from((&source, &cfg))?;
+        let data = response.data::<response::ResponseOnEvent>(&cfg)?;
+        assert_eq!(
+            data,
+            response::ResponseOnEvent::Identified {
+                number: 1,
+                event: request::ResponseOnEvent {
+                    window_time: Some(0x02),
+                    param: EventTypeParameter::StopResponseOnEvent,
+                },
+            }
+        );
+
+        let source = hex::decode("C600010200")?;
+        let err = response::Response::try_from((&source, &cfg)).unwrap_err();
+        match err {
+            Iso14229Error::InvalidDataLength { expect, actual } => {
+                assert_eq!(expect, 2);
+                assert_eq!(actual, 3);
+            }
+            _ => panic!("Unexpected error: {:?}", err),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_activated_events() -> anyhow::Result<()> {
+        let cfg = DidConfig::default();
+
+        let source = hex::decode("C604024302F19022F190010208190208")?;
+        let response = response::Response::try_from((&source, &cfg))?;
+        let data = response.data::<response::ResponseOnEvent>(&cfg)?;
+        let events = match &data {
+            response::ResponseOnEvent::ActivatedEvents(v) => v.events(),
+            _ => panic!("Unexpected data {:?}", data),
+        };
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&data, response::ResponseOnEvent::ActivatedEvents(v) if v.number() == 2));
+        assert!(events[0].event_type.store_event());
+        assert_eq!(events[0].event.window_time, Some(0x02));
+        assert_eq!(
+            events[0].event.param,
+            EventTypeParameter::OnChangeOfDataIdentifier {
+                did: 0xF190,
+                service_to_respond: request::Request::new(
+                    Service::ReadDID,
+                    None,
+                    [0xF1, 0x90],
+                    &cfg
+                )?,
+            }
+        );
+        assert!(!events[1].event_type.store_event());
+        assert_eq!(
+            events[1].event.param.event_type(),
+            ResponseOnEventType::OnDTCStatusChange
+        );
+
+        let response = response::Response::new(
+            Service::ResponseOnEvent,
+            Some(ResponseOnEventType::ReportActivatedEvents.into()),
+            Vec::from(data),
+            &cfg,
+        )?;
+        assert_eq!(Vec::from(response), source);
+
+        let source = hex::decode("C60400")?;
+        let response = response::Response::try_from((&source, &cfg))?;
+        let data = response.data::<response::ResponseOnEvent>(&cfg)?;
+        assert_eq!(data, response::ResponseOnEvent::activated_events(vec![])?);
+
+        // less events than the number
+        let source = hex::decode("C604024302F19022F190")?;
+        let err = response::Response::try_from((&source, &cfg)).unwrap_err();
+        assert!(matches!(err, Iso14229Error::InvalidDataLength { .. }));
+
+        // the request reading multiple DIDs is decoded completely as the last event
+        let source = hex::decode("C604020102081902080302F19022F190F191")?;
+        let response = response::Response::try_from((&source, &cfg))?;
+        let data = response.data::<response::ResponseOnEvent>(&cfg)?;
+        let events = match &data {
+            response::ResponseOnEvent::ActivatedEvents(v) => v.events(),
+            _ => panic!("Unexpected data {:?}", data),
+        };
+        match &events[1].event.param {
+            EventTypeParameter::OnChangeOfDataIdentifier {
+                service_to_respond, ..
+            } => assert_eq!(service_to_respond.raw_data(), &[0xF1, 0x90, 0xF1, 0x91]),
+            _ => panic!("Unexpected data {:?}", data),
+        }
+
+        // but it is cut to the shortest valid request when it isn't the last one,
+        // the rest (`F1 91`) is decoded as the next event
+        let source = hex::decode("C604020302F19022F190F191010208190208")?;
+        let err = response::Response::try_from((&source, &cfg)).unwrap_err();
+        assert!(matches!(err, Iso14229Error::ReservedError(0xB1)));
+
+        // more events than the numberOfActivatedEvents can hold
+        let event = events[0].clone();
+        let err =
+            response::ResponseOnEvent::activated_events(vec![event.clone(); 256]).unwrap_err();
+        assert!(matches!(err, Iso14229Error::InvalidParam(_)));
+        let err = response::ActivatedEvents::new(vec![event.clone(); 256]).unwrap_err();
+        assert!(matches!(err, Iso14229Error::InvalidParam(_)));
+        let data = response::ResponseOnEvent::activated_events(vec![event; 255])?;
+        assert_eq!(Vec::from(data)[0], 0xFF);
+
         Ok(())
     }
 
@@ -0,0 +1,363 @@
+//! UDS server
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use iso14229_1::{
+        request::Request,
+        response::{Code, SessionTiming},
+        server::{
+            is_allowed, AccessControl, AccessRule, AccessTable, ECUResetHandler, ServerState,
+            ServiceHandler, SessionCtrlHandler, StateEvent, TesterPresentHandler, UdsServer,
+        },
+        transport::{Addressing, DiagTransport, Loopback},
+        DidConfig, ECUResetType, RoutineCtrlType, SecurityAccessLevel, Service, SessionType,
+    };
+    use std::{sync::Arc, time::Duration};
+    use tokio::task::JoinHandle;
+
+    struct Echo;
+
+    #[async_trait::async_trait]
+    impl ServiceHandler for Echo {
+        async fn handle(&self, _: &Request) -> Result<Vec<u8>, Code> {
+            Ok(vec![])
+        }
+    }
+
+    struct Session;
+
+    #[async_trait::async_trait]
+    impl ServiceHandler for Session {
+        fn sub_functions(&self) -> Option<Vec<u8>> {
+            Some(vec![0x01, 0x03])
+        }
+
+        async fn handle(&self, _: &Request) -> Result<Vec<u8>, Code> {
+            Ok(vec![0x00, 0x32, 0x01, 0xF4])
+        }
+    }
+
+    /// sleep before responding, or return the NRC
+    struct Routine(u64, Option<Code>);
+
+    #[async_trait::async_trait]
+    impl ServiceHandler for Routine {
+        async fn handle(&self, request: &Request) -> Result<Vec<u8>, Code> {
+            tokio::time::sleep(Duration::from_millis(self.0)).await;
+            match self.1 {
+                Some(code) => Err(code),
+                None => Ok(request.raw_data().to_vec()),
+            }
+        }
+    }
+
+    /// the service 2E is locked, the sub-function 0x02 is not supported in the session
+    struct Locked;
+
+    impl AccessControl for Locked {
+        fn check_service(&self, service: Service) -> Result<(), Code> {
+            match service {
+                Service::WriteDID => Err(Code::SecurityAccessDenied),
+                _ => Ok(()),
+            }
+        }
+
+        fn check_sub_function(&self, _: Service, sub_func: u8) -> Result<(), Code> {
+            match sub_func {
+                0x02 => Err(Code::SubFunctionNotSupportedInActiveSession),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    async fn request(
+        tester: &Loopback,
+        addressing: Addressing,
+        request: &str,
+    ) -> anyhow::Result<()> {
+        tester.send(addressing, &hex::decode(request)?).await?;
+
+        Ok(())
+    }
+
+    async fn expect(tester: &Loopback, response: &str) -> anyhow::Result<()> {
+        let pdu = tester.receive(1_000).await?;
+        assert_eq!(hex::encode_upper(pdu.data), response);
+
+        Ok(())
+    }
+
+    fn spawn<F>(
+        ecu: Loopback,
+        access: Option<Arc<dyn AccessControl>>,
+        register: F,
+    ) -> JoinHandle<()>
+    where
+        F: FnOnce(&mut UdsServer<Loopback>),
+    {
+        let mut server = UdsServer::new(ecu, DidConfig::default());
+        if let Some(access) = access {
+            server = server.with_access(access);
+        }
+        register(&mut server);
+
+        tokio::spawn(async move {
+            let _ = server.serve().await;
+        })
+    }
+
+    #[tokio::test]
+    async fn test_nrc_order() -> anyhow::Result<()> {
+        let (tester, ecu) = Loopback::pair();
+        let server = spawn(ecu, Some(Arc::new(Locked)), |s| {
+            s.register(Service::SessionCtrl, Session);
+            s.register(Service::WriteDID, Echo);
+        });
+
+        // service not supported
+        request(&tester, Addressing::Physical, "A0").await?;
+        expect(&tester, "7FA011").await?;
+        request(&tester, Addressing::Physical, "1101").await?;
+        expect(&tester, "7F1111").await?;
+        // security access denied before the message length
+        request(&tester, Addressing::Physical, "2E").await?;
+        expect(&tester, "7F2E33").await?;
+        // incorrect message length
+        request(&tester, Addressing::Physical, "10").await?;
+        expect(&tester, "7F1013").await?;
+        // sub-function not supported
+        request(&tester, Addressing::Physical, "1004").await?;
+        expect(&tester, "7F1012").await?;
+        // not supported by the handler before checking the active session
+        request(&tester, Addressing::Physical, "1002").await?;
+        expect(&tester, "7F1012").await?;
+        // sub-function not supported before the total length
+        request(&tester, Addressing::Physical, "100200").await?;
+        expect(&tester, "7F1012").await?;
+        request(&tester, Addressing::Physical, "100300").await?;
+        expect(&tester, "7F1013").await?;
+        request(&tester, Addressing::Physical, "1003").await?;
+        expect(&tester, "5003003201F4").await?;
+
+        server.abort();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sub_function_in_session() -> anyhow::Result<()> {
+        let (tester, ecu) = Loopback::pair();
+        let server = spawn(ecu, Some(Arc::new(Locked)), |s| {
+            s.register(Service::ECUReset, Echo)
+        });
+
+        request(&tester, Addressing::Physical, "1102").await?;
+        expect(&tester, "7F117E").await?;
+        // not supported in the session before the total length
+        request(&tester, Addressing::Physical, "110200").await?;
+        expect(&tester, "7F117E").await?;
+        // the reserved sub-function
+        request(&tester, Addressing::Physical, "1100").await?;
+        expect(&tester, "7F1112").await?;
+        request(&tester, Addressing::Physical, "11").await?;
+        expect(&tester, "7F1113").await?;
+        request(&tester, Addressing::Physical, "1101").await?;
+        expect(&tester, "5101").await?;
+
+        server.abort();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_suppress() -> anyhow::Result<()> {
+        let (tester, ecu) = Loopback::pair();
+        let server = spawn(ecu, None, |s| s.register(Service::TesterPresent, Echo));
+
+        request(&tester, Addressing::Physical, "3E80").await?;
+        // the NRCs 0x11 and 0x12 of the functional requests are suppressed
+        request(&tester, Addressing::Functional, "1101").await?;
+        request(&tester, Addressing::Functional, "3E01").await?;
+        request(&tester, Addressing::Functional, "3E00").await?;
+        expect(&tester, "7E00").await?;
+        // the other NRCs are not suppressed
+        request(&tester, Addressing::Functional, "3E0000").await?;
+        expect(&tester, "7F3E13").await?;
+
+        server.abort();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_response_pending() -> anyhow::Result<()> {
+        let (tester, ecu) = Loopback::pair();
+        let server = spawn(ecu, None, |s| {
+            // longer than P2 of the default session
+            s.register(Service::TransferData, Routine(100, None));
+            s.register(
+                Service::RequestTransferExit,
+                Routine(0, Some(Code::InvalidKey)),
+            );
+        });
+
+        request(&tester, Addressing::Physical, "360112").await?;
+        expect(&tester, "7F3678").await?;
+        expect(&tester, "760112").await?;
+        // NRC 0x35 is not allowed by service 37
+        request(&tester, Addressing::Physical, "37").await?;
+        expect(&tester, "7F3710").await?;
+
+        server.abort();
+
+        Ok(())
+    }
+
+    /// service 31 in the extended session with level 1 unlocked,
+    /// and its sub-function 03 in the programming session only
+    fn state() -> anyhow::Result<Arc<ServerState>> {
+        let level = SecurityAccessLevel::new(0x01)?;
+        let table = AccessTable::default()
+            .with_service(
+                Service::RoutineCtrl,
+                AccessRule::new(vec![SessionType::Programming, SessionType::Extended])
+                    .with_security(vec![level]),
+            )
+            .with_sub_function(
+                Service::RoutineCtrl,
+                RoutineCtrlType::RequestRoutineResults.into(),
+                AccessRule::new(vec![SessionType::Programming]),
+            );
+        let timing = SessionTiming {
+            p2: 25,
+            p2_star: 10,
+        };
+
+        Ok(Arc::new(
+            ServerState::new(table)
+                .with_timing(SessionType::Extended, timing)
+                .with_s3(100),
+        ))
+    }
+
+    fn spawn_state(ecu: Loopback, state: &Arc<ServerState>) -> JoinHandle<()> {
+        let access: Arc<dyn AccessControl> = state.clone();
+        spawn(ecu, Some(access), |s| {
+            s.register(Service::SessionCtrl, SessionCtrlHandler(state.clone()));
+            s.register(Service::ECUReset, ECUResetHandler(state.clone()));
+            s.register(Service::TesterPresent, TesterPresentHandler);
+            s.register(Service::RoutineCtrl, Routine(0, None));
+        })
+    }
+
+    #[tokio::test]
+    async fn test_state() -> anyhow::Result<()> {
+        let (tester, ecu) = Loopback::pair();
+        let state = state()?;
+        let mut events = state.subscribe();
+        let server = spawn_state(ecu, &state);
+
+        request(&tester, Addressing::Physical, "3101FF00").await?;
+        expect(&tester, "7F317F").await?;
+        // the programming session is not configured
+        request(&tester, Addressing::Physical, "1002").await?;
+        expect(&tester, "7F1012").await?;
+        request(&tester, Addressing::Physical, "1003").await?;
+        expect(&tester, "50030019000A").await?;
+        assert_eq!(
+            events.recv().await?,
+            StateEvent::SessionChanged {
+                previous: SessionType::Default,
+                session: SessionType::Extended,
+            }
+        );
+        assert_eq!(state.timing().p2_ms(), 25);
+
+        request(&tester, Addressing::Physical, "3101FF00").await?;
+        expect(&tester, "7F3133").await?;
+        let level = SecurityAccessLevel::new(0x01)?;
+        state.unlock(level);
+        assert_eq!(
+            events.recv().await?,
+            StateEvent::SecurityChanged(Some(level))
+        );
+        request(&tester, Addressing::Physical, "3101FF00").await?;
+        expect(&tester, "7101FF00").await?;
+        request(&tester, Addressing::Physical, "3103FF00").await?;
+        expect(&tester, "7F317E").await?;
+
+        request(&tester, Addressing::Physical, "1101").await?;
+        expect(&tester, "5101").await?;
+        assert_eq!(
+            events.recv().await?,
+            StateEvent::ECUReset(ECUResetType::HardReset)
+        );
+        assert_eq!(events.recv().await?, StateEvent::SecurityChanged(None));
+        assert_eq!(
+            events.recv().await?,
+            StateEvent::SessionChanged {
+                previous: SessionType::Extended,
+                session: SessionType::Default,
+            }
+        );
+        assert_eq!(state.session(), SessionType::Default);
+        assert_eq!(state.security(), None);
+
+        // reset with the positive response suppressed
+        request(&tester, Addressing::Physical, "1182").await?;
+        assert_eq!(
+            events.recv().await?,
+            StateEvent::ECUReset(ECUResetType::KeyOffOnReset)
+        );
+
+        server.abort();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_s3() -> anyhow::Result<()> {
+        let (tester, ecu) = Loopback::pair();
+        let state = state()?;
+        let server = spawn_state(ecu, &state);
+
+        request(&tester, Addressing::Physical, "1003").await?;
+        expect(&tester, "50030019000A").await?;
+        let mut events = state.subscribe();
+        // kept by the tester present
+        for _ in 0..4 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            request(&tester, Addressing::Physical, "3E80").await?;
+        }
+        assert_eq!(state.session(), SessionType::Extended);
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(state.session(), SessionType::Default);
+        assert_eq!(events.recv().await?, StateEvent::S3Timeout);
+        assert_eq!(
+            events.recv().await?,
+            StateEvent::SessionChanged {
+                previous: SessionType::Extended,
+                session: SessionType::Default,
+            }
+        );
+
+        server.abort();
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_allowed() {
+        assert!(is_allowed(Service::ReadDID, Code::RequestOutOfRange));
+        assert!(is_allowed(Service::ReadDID, Code::BusyRepeatRequest));
+        assert!(is_allowed(Service::ReadDID, Code::VoltageTooLow));
+        assert!(is_allowed(
+            Service::TesterPresent,
+            Code::VehicleManufacturerSpecific(0xF0)
+        ));
+        assert!(!is_allowed(Service::TesterPresent, Code::RequestOutOfRange));
+        assert!(!is_allowed(Service::TesterPresent, Code::VoltageTooLow));
+        assert!(!is_allowed(Service::ReadDID, Code::Positive));
+    }
+}
@@ -0,0 +1,50 @@
+//! Transports
+
+#[cfg(all(test, feature = "transport"))]
+mod tests {
+    use iso14229_1::{
+        transport::{Addressing, DiagTransport, Loopback, Pdu},
+        Iso14229Error,
+    };
+
+    #[tokio::test]
+    async fn test_loopback() -> anyhow::Result<()> {
+        let (tester, ecu) = Loopback::pair();
+        let ecu = ecu.with_max_pdu_size(8);
+        assert_eq!(tester.max_pdu_size(), 0xFFFF);
+        assert_eq!(ecu.max_pdu_size(), 8);
+
+        tester.send(Addressing::Functional, &[0x3E, 0x80]).await?;
+        assert_eq!(
+            ecu.receive(10).await?,
+            Pdu {
+                addressing: Addressing::Functional,
+                data: vec![0x3E, 0x80],
+            }
+        );
+
+        ecu.send(Addressing::Physical, &[0x7E, 0x00]).await?;
+        assert_eq!(tester.receive(10).await?.data, vec![0x7E, 0x00]);
+
+        let err = ecu
+            .send(Addressing::Physical, &[0x00; 9])
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Iso14229Error::InvalidDataLength {
+                expect: 8,
+                actual: 9
+            }
+        ));
+
+        let err = tester.receive(10).await.unwrap_err();
+        assert!(matches!(err, Iso14229Error::Timeout { value: 10, .. }));
+
+        drop(ecu);
+        let err = tester.send(Addressing::Physical, &[0x3E, 0x00]).await;
+        assert!(matches!(err, Err(Iso14229Error::TransportError(_))));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,225 @@
+use crate::can::cannelloni::{decode_packet, encode_packet, CannelloniFrame, MAX_PACKET_SIZE};
+use rs_can::{
+    can_utils::system_timestamp, CanDevice, CanDirect, CanError, CanFrame, CanResult,
+    ChannelConfig, DeviceBuilder,
+};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+use tokio::{net::UdpSocket, sync::Mutex, time::timeout};
+
+const LOCAL_ADDR: &str = "cannelloni.local_addr";
+const REMOTE_ADDR: &str = "cannelloni.remote_addr";
+
+/// One tunnelled CAN bus.
+#[derive(Debug, Clone)]
+struct Link {
+    socket: Arc<UdpSocket>,
+    remote: SocketAddr,
+    sequence: Arc<AtomicU8>,
+    /// the receiving buffer, reused by every receiving
+    buffer: Arc<Mutex<Vec<u8>>>,
+}
+
+/// A [`CanDevice`] that transmits and receives frames over UDP with cannelloni framing.
+///
+/// Every channel is a pair of local/remote UDP endpoint, see [`CannelloniDevice::channel_config`].
+///
+/// The device must be built inside a tokio runtime.
+#[derive(Debug, Clone)]
+pub struct CannelloniDevice {
+    links: Arc<RwLock<HashMap<u8, Link>>>,
+}
+
+impl CannelloniDevice {
+    /// Create the configuration of a channel.
+    ///
+    /// * `local` - the local address that bind.
+    /// * `remote` - the address of remote cannelloni endpoint.
+    pub fn channel_config(local: SocketAddr, remote: SocketAddr) -> ChannelConfig {
+        let mut cfg = ChannelConfig::new(Default::default());
+        cfg.add_other(LOCAL_ADDR, Box::new(local))
+            .add_other(REMOTE_ADDR, Box::new(remote));
+
+        cfg
+    }
+
+    /// Get the local address of channel.
+    pub fn local_addr(&self, channel: u8) -> CanResult<SocketAddr, CanError> {
+        self.link(channel)?
+            .socket
+            .local_addr()
+            .map_err(|e| CanError::operation_error(e.to_string()))
+    }
+
+    /// Set the remote address of channel, e.g. when the remote endpoint bound to port 0.
+    pub fn set_remote_addr(&self, channel: u8, remote: SocketAddr) -> CanResult<(), CanError> {
+        let mut guard = self
+            .links
+            .write()
+            .map_err(|e| CanError::other_error(e.to_string()))?;
+        let link = guard
+            .get_mut(&channel)
+            .ok_or_else(|| CanError::channel_not_opened(channel))?;
+        rsutil::debug!(
+            "ISO-TP - cannelloni channel {} remote {} -> {}",
+            channel,
+            link.remote,
+            remote
+        );
+        link.remote = remote;
+
+        Ok(())
+    }
+
+    #[inline]
+    fn link(&self, channel: u8) -> CanResult<Link, CanError> {
+        self.links
+            .read()
+            .map_err(|e| CanError::other_error(e.to_string()))?
+            .get(&channel)
+            .cloned()
+            .ok_or_else(|| CanError::channel_not_opened(channel))
+    }
+}
+
+impl TryFrom<DeviceBuilder<u8>> for CannelloniDevice {
+    type Error = CanError;
+
+    fn try_from(builder: DeviceBuilder<u8>) -> Result<Self, Self::Error> {
+        if tokio::runtime::Handle::try_current().is_err() {
+            return Err(CanError::InitializeError(
+                "cannelloni device must be built inside a tokio runtime".into(),
+            ));
+        }
+
+        let mut links = HashMap::new();
+        for (&channel, cfg) in builder.channel_configs() {
+            let local = cfg
+                .get_other::<SocketAddr>(LOCAL_ADDR)?
+                .ok_or_else(|| CanError::InitializeError(format!("{} is required", LOCAL_ADDR)))?;
+            let remote = cfg
+                .get_other::<SocketAddr>(REMOTE_ADDR)?
+                .ok_or_else(|| CanError::InitializeError(format!("{} is required", REMOTE_ADDR)))?;
+
+            let socket = std::net::UdpSocket::bind(local)
+                .and_then(|s| s.set_nonblocking(true).map(|_| s))
+                .and_then(UdpSocket::from_std)
+                .map_err(CanError::device_open_error)?;
+            rsutil::debug!(
+                "ISO-TP - cannelloni channel {} opened {} <-> {}",
+                channel,
+                local,
+                remote
+            );
+
+            links.insert(
+                channel,
+                Link {
+                    socket: Arc::new(socket),
+                    remote,
+                    sequence: Default::default(),
+                    buffer: Arc::new(Mutex::new(vec![0; MAX_PACKET_SIZE])),
+                },
+            );
+        }
+
+        Ok(Self {
+            links: Arc::new(RwLock::new(links)),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl CanDevice for CannelloniDevice {
+    type Channel = u8;
+    type Frame = CannelloniFrame;
+
+    fn opened_channels(&self) -> Vec<Self::Channel> {
+        match self.links.read() {
+            Ok(guard) => guard.keys().copied().collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    async fn transmit(
+        &self,
+        mut msg: Self::Frame,
+        timeout_ms: Option<u32>,
+    ) -> CanResult<(), CanError> {
+        let channel = msg.channel();
+        let link = self.link(channel)?;
+        msg.set_direct(CanDirect::Transmit).set_timestamp(None);
+
+        let sequence = link.sequence.fetch_add(1, Ordering::Relaxed);
+        let packet = encode_packet(sequence, &[msg]);
+        let send = link.socket.send_to(&packet, link.remote);
+        match timeout_ms {
+            Some(ms) => timeout(Duration::from_millis(ms as u64), send)
+                .await
+                .map_err(|_| CanError::channel_timeout(channel))?,
+            None => send.await,
+        }
+        .map_err(|e| CanError::operation_error(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn receive(
+        &self,
+        channel: Self::Channel,
+        timeout_ms: Option<u32>,
+    ) -> CanResult<Vec<Self::Frame>, CanError> {
+        let link = self.link(channel)?;
+        let mut buffer = link.buffer.lock().await;
+
+        let recv = link.socket.recv_from(&mut buffer);
+        let (mut size, mut from) = match timeout_ms {
+            Some(ms) => timeout(Duration::from_millis(ms as u64), recv)
+                .await
+                .map_err(|_| CanError::channel_timeout(channel))?,
+            None => recv.await,
+        }
+        .map_err(|e| CanError::operation_error(e.to_string()))?;
+
+        let mut results = Vec::new();
+        loop {
+            if from == link.remote {
+                match decode_packet(channel, &buffer[..size], system_timestamp()) {
+                    Ok((_, mut frames)) => results.append(&mut frames),
+                    Err(e) => rsutil::warn!("ISO-TP - cannelloni channel {}: {}", channel, e),
+                }
+            } else {
+                rsutil::warn!(
+                    "ISO-TP - cannelloni channel {} dropped packet from {}",
+                    channel,
+                    from
+                );
+            }
+
+            // drain the packets that already arrived
+            match link.socket.try_recv_from(&mut buffer) {
+                Ok((s, f)) => {
+                    size = s;
+                    from = f;
+                }
+                Err(_) => break,
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn shutdown(&mut self) {
+        match self.links.write() {
+            Ok(mut guard) => guard.clear(),
+            Err(e) => rsutil::warn!("ISO-TP - cannelloni shutdown error: {}", e),
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use rs_can::{
+    can_utils::{can_dlc, data_resize, system_timestamp},
+    CanDirect, CanFrame, CanId, CanType, MAX_FD_FRAME_SIZE, MAX_FRAME_SIZE,
+};
+use std::fmt::{Display, Formatter};
+
+/// CAN/CAN-FD frame that transmitted by [`CannelloniDevice`](super::CannelloniDevice).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CannelloniFrame {
+    pub(crate) timestamp: u64,
+    pub(crate) id: CanId,
+    pub(crate) can_type: CanType,
+    pub(crate) remote: bool,
+    pub(crate) error_frame: bool,
+    pub(crate) direct: CanDirect,
+    pub(crate) bitrate_switch: bool,
+    pub(crate) esi: bool,
+    pub(crate) channel: u8,
+    pub(crate) data: Vec<u8>,
+    /// the length of remote frame
+    pub(crate) length: usize,
+}
+
+impl CanFrame for CannelloniFrame {
+    type Channel = u8;
+
+    fn new(id: impl Into<CanId>, data: &[u8]) -> Option<Self> {
+        let length = data.len();
+        let can_type = if length <= MAX_FRAME_SIZE {
+            CanType::Can
+        } else if length <= MAX_FD_FRAME_SIZE {
+            CanType::CanFd
+        } else {
+            return None;
+        };
+        let mut data = data.to_vec();
+        // CAN-FD only supports the lengths of DLC table
+        let dlc = can_dlc(length, can_type);
+        data_resize(&mut data, dlc as usize);
+
+        Some(Self {
+            timestamp: Default::default(),
+            id: id.into(),
+            can_type,
+            remote: false,
+            error_frame: false,
+            direct: Default::default(),
+            bitrate_switch: false,
+            esi: false,
+            channel: Default::default(),
+            length: data.len(),
+            data,
+        })
+    }
+
+    fn new_remote(id: impl Into<CanId>, len: usize) -> Option<Self> {
+        if len > MAX_FRAME_SIZE {
+            return None;
+        }
+
+        Some(Self {
+            timestamp: Default::default(),
+            id: id.into(),
+            can_type: CanType::Can,
+            remote: true,
+            error_frame: false,
+            direct: Default::default(),
+            bitrate_switch: false,
+            esi: false,
+            channel: Default::default(),
+            data: Default::default(),
+            length: len,
+        })
+    }
+
+    #[inline]
+    fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    #[inline]
+    fn set_timestamp(&mut self, value: Option<u64>) -> &mut Self {
+        self.timestamp = value.unwrap_or_else(system_timestamp);
+        self
+    }
+
+    #[inline]
+    fn id(&self) -> CanId {
+        self.id
+    }
+
+    #[inline]
+    fn can_type(&self) -> CanType {
+        self.can_type
+    }
+
+    #[inline]
+    fn set_can_type(&mut self, r#type: CanType) -> &mut Self {
+        self.can_type = r#type;
+        self
+    }
+
+    #[inline]
+    fn is_remote(&self) -> bool {
+        self.remote
+    }
+
+    #[inline]
+    fn is_extended(&self) -> bool {
+        self.id.is_extended()
+    }
+
+    #[inline]
+    fn direct(&self) -> CanDirect {
+        self.direct
+    }
+
+    #[inline]
+    fn set_direct(&mut self, direct: CanDirect) -> &mut Self {
+        self.direct = direct;
+        self
+    }
+
+    #[inline]
+    fn is_bitrate_switch(&self) -> bool {
+        self.bitrate_switch
+    }
+
+    #[inline]
+    fn set_bitrate_switch(&mut self, value: bool) -> &mut Self {
+        self.bitrate_switch = value;
+        self
+    }
+
+    #[inline]
+    fn is_error_frame(&self) -> bool {
+        self.error_frame
+    }
+
+    #[inline]
+    fn set_error_frame(&mut self, value: bool) -> &mut Self {
+        self.error_frame = value;
+        self
+    }
+
+    #[inline]
+    fn is_esi(&self) -> bool {
+        self.esi
+    }
+
+    #[inline]
+    fn set_esi(&mut self, value: bool) -> &mut Self {
+        self.esi = value;
+        self
+    }
+
+    #[inline]
+    fn channel(&self) -> Self::Channel {
+        self.channel
+    }
+
+    #[inline]
+    fn set_channel(&mut self, value: Self::Channel) -> &mut Self {
+        self.channel = value;
+        self
+    }
+
+    #[inline]
+    fn data(&self) -> &[u8] {
+        self.data.as_slice()
+    }
+
+    #[inline]
+    fn length(&self) -> usize {
+        self.length
+    }
+}
+
+impl Display for CannelloniFrame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        <dyn CanFrame<Channel = u8> as Display>::fmt(self, f)
+    }
+}
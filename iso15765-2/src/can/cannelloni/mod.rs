@@ -0,0 +1,175 @@
+//! CAN/CAN-FD frames tunnelled over UDP with the [cannelloni](https://github.com/mguentner/cannelloni) framing.
+//!
+//! | - packet - | - size - | - note - |
+//!
+//! | version | 1 | always [`FRAME_VERSION`] |
+//!
+//! | op_code | 1 | only `DATA` is used |
+//!
+//! | seq_no  | 1 | incremented for each packet |
+//!
+//! | count   | 2 | number of frames that follow |
+//!
+//! | - frame - | - size - | - note - |
+//!
+//! | can_id  | 4 | SocketCAN id with EFF/RTR/ERR flags |
+//!
+//! | len     | 1 | bit7 is set for CAN-FD frames |
+//!
+//! | flags   | 1 | only present for CAN-FD frames(BRS/ESI) |
+//!
+//! | data    | len | absent for remote frames |
+mod device;
+mod frame;
+
+pub use self::{device::CannelloniDevice, frame::CannelloniFrame};
+
+use rs_can::{CanDirect, CanError, CanFrame, CanId, CanType, IdentifierFlags};
+
+pub(crate) const FRAME_VERSION: u8 = 2;
+pub(crate) const OP_CODE_DATA: u8 = 0;
+pub(crate) const SIZE_OF_PACKET_HEADER: usize = 5;
+pub(crate) const SIZE_OF_FRAME_HEADER: usize = 5;
+pub(crate) const CANFD_FRAME: u8 = 0x80;
+pub(crate) const CANFD_BRS: u8 = 0x01;
+pub(crate) const CANFD_ESI: u8 = 0x02;
+/// max size of an UDP datagram.
+pub(crate) const MAX_PACKET_SIZE: usize = 0xFFFF;
+
+/// Encode frames into one cannelloni data packet.
+pub(crate) fn encode_packet(sequence: u8, frames: &[CannelloniFrame]) -> Vec<u8> {
+    let mut result = vec![FRAME_VERSION, OP_CODE_DATA, sequence];
+    result.extend((frames.len() as u16).to_be_bytes());
+    for frame in frames {
+        let mut can_id = frame.id().into_bits();
+        if frame.is_remote() {
+            can_id |= IdentifierFlags::REMOTE.bits();
+        }
+        if frame.is_error_frame() {
+            can_id |= IdentifierFlags::ERROR.bits();
+        }
+        result.extend(can_id.to_be_bytes());
+
+        let length = frame.length() as u8;
+        match frame.can_type() {
+            CanType::CanFd => {
+                result.push(length | CANFD_FRAME);
+                let mut flags = 0;
+                if frame.is_bitrate_switch() {
+                    flags |= CANFD_BRS;
+                }
+                if frame.is_esi() {
+                    flags |= CANFD_ESI;
+                }
+                result.push(flags);
+            }
+            _ => result.push(length),
+        }
+
+        if !frame.is_remote() {
+            result.extend_from_slice(frame.data());
+        }
+    }
+
+    result
+}
+
+/// Decode one cannelloni data packet that received from `channel`.
+pub(crate) fn decode_packet(
+    channel: u8,
+    data: &[u8],
+    timestamp: u64,
+) -> Result<(u8, Vec<CannelloniFrame>), CanError> {
+    let data_len = data.len();
+    if data_len < SIZE_OF_PACKET_HEADER {
+        return Err(CanError::operation_error(format!(
+            "cannelloni packet too short: {}",
+            hex::encode(data)
+        )));
+    }
+
+    let mut offset = 0;
+    let version = data[offset];
+    offset += 1;
+    if version != FRAME_VERSION {
+        return Err(CanError::operation_error(format!(
+            "unsupported cannelloni version: {}",
+            version
+        )));
+    }
+    let op_code = data[offset];
+    offset += 1;
+    if op_code != OP_CODE_DATA {
+        return Err(CanError::operation_error(format!(
+            "unsupported cannelloni op code: {}",
+            op_code
+        )));
+    }
+    let sequence = data[offset];
+    offset += 1;
+    let count = u16::from_be_bytes([data[offset], data[offset + 1]]);
+    offset += 2;
+
+    let mut results = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        if data_len < offset + SIZE_OF_FRAME_HEADER {
+            return Err(CanError::operation_error(
+                "cannelloni frame header truncated",
+            ));
+        }
+        let can_id = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap());
+        offset += 4;
+        let len = data[offset];
+        offset += 1;
+
+        let is_fd = (len & CANFD_FRAME) == CANFD_FRAME;
+        let length = (len & !CANFD_FRAME) as usize;
+        let flags = if is_fd {
+            if data_len < offset + 1 {
+                return Err(CanError::operation_error(
+                    "cannelloni frame flags truncated",
+                ));
+            }
+            let flags = data[offset];
+            offset += 1;
+            flags
+        } else {
+            0
+        };
+
+        let remote = (can_id & IdentifierFlags::REMOTE.bits()) > 0;
+        let error = (can_id & IdentifierFlags::ERROR.bits()) > 0;
+        let id = CanId::from_bits(
+            can_id & !(IdentifierFlags::REMOTE | IdentifierFlags::ERROR).bits(),
+            None,
+        );
+
+        let mut frame = if remote {
+            CannelloniFrame::new_remote(id, length)
+        } else {
+            if data_len < offset + length {
+                return Err(CanError::operation_error("cannelloni frame data truncated"));
+            }
+            let frame = CannelloniFrame::new(id, &data[offset..offset + length]);
+            offset += length;
+            frame
+        }
+        .ok_or_else(|| CanError::operation_error(format!("invalid frame length: {}", length)))?;
+
+        if is_fd {
+            frame
+                .set_can_type(CanType::CanFd)
+                .set_bitrate_switch((flags & CANFD_BRS) > 0)
+                .set_esi((flags & CANFD_ESI) > 0);
+        }
+        frame
+            .set_error_frame(error)
+            .set_channel(channel)
+            .set_direct(CanDirect::Receive)
+            .set_timestamp(Some(timestamp));
+
+        results.push(frame);
+    }
+
+    Ok((sequence, results))
+}
@@ -1,8 +1,12 @@
 pub(crate) mod address;
+#[cfg(feature = "cannelloni")]
+pub(crate) mod cannelloni;
 pub(crate) mod constants;
 pub(crate) mod isotp;
 pub(crate) mod standard;
 
+#[cfg(feature = "cannelloni")]
+pub use self::cannelloni::{CannelloniDevice, CannelloniFrame};
 pub use self::{
     address::{Address, AddressFormat, AddressType},
     isotp::CanIsoTp,
@@ -0,0 +1,120 @@
+#![cfg(feature = "cannelloni")]
+
+#[cfg(test)]
+mod tests {
+    use iso15765_2::{
+        can::{Address, AddressType, CanIsoTp, CannelloniDevice, CannelloniFrame},
+        IsoTp,
+    };
+    use rs_can::{CanDevice, CanFrame, CanId, CanType, DeviceBuilder};
+    use std::net::SocketAddr;
+
+    const CHANNEL: u8 = 0;
+
+    fn device_pair() -> anyhow::Result<(CannelloniDevice, CannelloniDevice)> {
+        // bind to port 0 and read back the local addresses
+        let any: SocketAddr = "127.0.0.1:0".parse()?;
+
+        let mut builder = DeviceBuilder::new();
+        builder.add_config(CHANNEL, CannelloniDevice::channel_config(any, any));
+        let device_a = builder.build::<CannelloniDevice>()?;
+        let addr_a = device_a.local_addr(CHANNEL)?;
+
+        let mut builder = DeviceBuilder::new();
+        builder.add_config(CHANNEL, CannelloniDevice::channel_config(any, addr_a));
+        let device_b = builder.build::<CannelloniDevice>()?;
+        let addr_b = device_b.local_addr(CHANNEL)?;
+        assert_ne!(addr_a.port(), 0);
+        assert_ne!(addr_b.port(), 0);
+
+        device_a.set_remote_addr(CHANNEL, addr_b)?;
+
+        Ok((device_a, device_b))
+    }
+
+    #[tokio::test]
+    async fn test_frames() -> anyhow::Result<()> {
+        let (device_a, device_b) = device_pair()?;
+        assert_eq!(device_a.opened_channels(), vec![CHANNEL]);
+
+        let mut frame =
+            CannelloniFrame::new(CanId::from_bits(0x7E0, None), &[0x02, 0x10, 0x01]).unwrap();
+        frame.set_channel(CHANNEL);
+        device_a.transmit(frame, Some(100)).await?;
+
+        let mut frame =
+            CannelloniFrame::new(CanId::from_bits(0x18DA00F1, Some(true)), &[0x55; 21]).unwrap();
+        frame.set_channel(CHANNEL).set_bitrate_switch(true);
+        device_a.transmit(frame, Some(100)).await?;
+
+        let mut frame = CannelloniFrame::new_remote(CanId::from_bits(0x123, None), 4).unwrap();
+        frame.set_channel(CHANNEL);
+        device_a.transmit(frame, Some(100)).await?;
+
+        let mut frames = Vec::new();
+        while frames.len() < 3 {
+            frames.append(&mut device_b.receive(CHANNEL, Some(1000)).await?);
+        }
+
+        let frame = &frames[0];
+        assert_eq!(frame.id(), CanId::Standard(0x7E0));
+        assert_eq!(frame.can_type(), CanType::Can);
+        assert_eq!(frame.data(), &[0x02, 0x10, 0x01]);
+
+        // CAN-FD frames are padded to the length of DLC
+        let frame = &frames[1];
+        assert_eq!(frame.id(), CanId::Extended(0x18DA00F1));
+        assert_eq!(frame.can_type(), CanType::CanFd);
+        assert!(frame.is_bitrate_switch());
+        assert!(!frame.is_esi());
+        assert_eq!(frame.length(), 24);
+        assert_eq!(&frame.data()[..21], &[0x55; 21]);
+
+        let frame = &frames[2];
+        assert!(frame.is_remote());
+        assert_eq!(frame.length(), 4);
+        assert!(frame.data().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_receive_timeout() -> anyhow::Result<()> {
+        let (mut device_a, _device_b) = device_pair()?;
+        assert!(device_a.receive(CHANNEL, Some(10)).await.is_err());
+
+        device_a.shutdown();
+        assert!(device_a.is_closed());
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_iso_tp() -> anyhow::Result<()> {
+        let (device_a, device_b) = device_pair()?;
+
+        let mut client = CanIsoTp::new(device_a, CHANNEL, Address::default(), false).await;
+        let server_address = Address {
+            tx_id: 0x7E8,
+            rx_id: 0x7E0,
+            fid: 0x7DF,
+        };
+        let mut server = CanIsoTp::new(device_b, CHANNEL, server_address, true).await;
+        client.start(100).await;
+        server.start(100).await;
+
+        let data = (0..0x40).collect::<Vec<u8>>();
+        client.transmit(AddressType::Physical, &data).await?;
+        let received = server.wait_data(1000).await?;
+        assert_eq!(received.as_ref(), data.as_slice());
+
+        server.transmit(AddressType::Physical, [0x50, 0x01]).await?;
+        let received = client.wait_data(1000).await?;
+        assert_eq!(received.as_ref(), &[0x50, 0x01]);
+
+        client.stop().await;
+        server.stop().await;
+
+        Ok(())
+    }
+}